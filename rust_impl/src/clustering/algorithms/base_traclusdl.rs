@@ -1,12 +1,19 @@
-use super::super::geometry::{segment::Segment, trajectory::Trajectory};
+use super::super::geometry::{
+    segment::Segment,
+    trajectory::{Leg, Trajectory, angle_difference},
+};
 use super::super::objects::{
     cluster::Cluster,
     cluster_member::{ClusterMember, ClusterSeed},
 };
 use super::super::storage::{
-    clustered_trajectories::ClusteredTrajectories, raw_trajectories::RawTrajectories,
+    clustered_trajectories::ClusteredTrajectories,
+    raw_trajectories::{NearbyTrajectories, RawTrajectories},
 };
+use super::segment_distance::SegmentDistance;
 use crate::io::args::TraclusArgs;
+use crate::utils::cancellation_token::{CancellationToken, Cancelled};
+use crate::utils::progress_reporter::ProgressReporter;
 
 /// Base trait for TraClus algorithm implementations.
 ///
@@ -18,42 +25,65 @@ pub trait TraclusAlgorithm {
     // Shared Data Accessors
     // ============================================================
     fn args(&self) -> &TraclusArgs;
+    fn cancel_token(&self) -> &CancellationToken;
+    fn progress(&self) -> &ProgressReporter;
+    fn segment_distance(&self) -> &dyn SegmentDistance;
 
     // ============================================================
     // Required Methods (Must Be Implemented by Implementations)
     // ============================================================
 
-    /// Performs DB-SCAN clustering on trajectory segments.
+    /// Performs the DB-SCAN discovery of clusters on trajectory segments.
     ///
     /// This is the main method to partitions the trajectory into clusters
-    /// based constraints and then creates the appropriate corridors.
+    /// based constraints; `create_corridors` then turns them into corridors.
     /// # Arguments
     /// * `raw_trajectories` - The raw trajectory storage containing all trajectories
     /// * `clustered_trajectories` - The clustered trajectory storage to populate with clusters
-    fn db_scan_clustering(
+    ///
+    /// # Returns
+    /// * `Err(Cancelled)` if the cancellation token was triggered; the clustered storage is then incomplete
+    fn discover_clusters(
         &self,
         raw_trajectories: &RawTrajectories,
         clustered_trajectories: &mut ClusteredTrajectories,
-    );
+    ) -> Result<(), Cancelled>;
 
     // ============================================================
     // Default Methods (Can Be Overridden If Needed)
     // ============================================================
 
+    /// Creates corridors from the discovered clusters and finalizes the non-clustered segments.
+    /// Kept apart from `discover_clusters` so the two phases can be timed separately.
+    /// # Arguments
+    /// * `clustered_trajectories` - The clustered trajectory storage containing all clusters
+    fn create_corridors(
+        &self,
+        clustered_trajectories: &mut ClusteredTrajectories,
+    ) -> Result<(), Cancelled> {
+        clustered_trajectories.finalize_corridors(self.args(), self.cancel_token(), self.progress())
+    }
+
     /// Finds all reachable trajectory segments from a given seed segment.
     ///
     /// This method applies four constraints to determine reachability:
     /// 1. **Same trajectory constraint**: Excludes segments from the same trajectory
-    /// 2. **Angle constraint**: Filters by direction similarity (max_angle)
-    /// 3. **Distance constraint**: Filters by spatial proximity (max_dist)
+    /// 2. **Angle constraint**: Filters by direction similarity (max_angle), or by axis
+    ///    similarity in the undirected mode
+    /// 3. **Distance constraint**: Filters by the distance metric of the args (max_dist)
     /// 4. **Density constraint**: Ensures minimum cluster weight (min_density)
     ///
+    /// Constraints 2 and 3 are checked against each leg of the nearby trajectories; only the
+    /// legs returned by the spatial index within the search radius of the metric are tested,
+    /// the others cannot pass constraint 3. A trajectory with several qualifying legs adds a
+    /// single candidate, on its closest leg.
+    ///
     /// # Time Complexity
     /// O(n × d / bucket_size) where n is nearby trajectories, d is avg trajectory length
     ///
     /// # Arguments
     /// * `seed` - The seed segment to cluster around
-    /// * `nearby_trajs` - Candidate trajectories within spatial proximity
+    /// * `nearby_trajs` - Trajectories of the neighbouring angle buckets
     ///
     /// # Returns
     /// * `Some(Cluster)` if density constraint is met
@@ -61,40 +91,67 @@ pub trait TraclusAlgorithm {
     fn cluster_reachable_segs(
         &self,
         seed: ClusterSeed,
-        nearby_trajs: &Vec<&Trajectory>,
+        nearby_trajs: &NearbyTrajectories,
     ) -> Option<Cluster> {
         let mut cluster: Cluster = Cluster::new(seed, Vec::new());
         let seed_ref: &ClusterSeed = &cluster.seed;
         let mut local_weight: u32 = seed_ref.cm.weight;
 
-        for nearby_traj in nearby_trajs {
+        let metric: &dyn SegmentDistance = self.segment_distance();
+        let radius: f64 = metric.search_radius(&seed_ref.cm, self.args().max_dist + 1e-9);
+        let candidate_legs: Vec<(&Trajectory, &Leg)> =
+            nearby_trajs.query(&seed_ref.cm.center, radius);
+        // (trajectory id, candidate index, distance) of the candidates from multi-leg trajectories
+        let mut multi_leg_candidates: Vec<(usize, usize, f64)> = Vec::new();
+
+        for (nearby_traj, leg) in candidate_legs {
             // Constraint 1: Skip if same trajectory
             if seed_ref.cm.traj_id == nearby_traj.id {
                 continue;
             }
 
-            // Constraint 2: Check angle difference
-            let angle_diff: f64 = (seed_ref.angle - nearby_traj.angle).abs();
-            let min_angle_diff: f64 = angle_diff.min(360.0 - angle_diff);
+            // Constraint 2: Check angle difference, modulo 180° when undirected
+            let min_angle_diff: f64 =
+                angle_difference(seed_ref.angle, leg.angle, self.args().undirected);
             if min_angle_diff > self.args().max_angle + 1e-9 {
                 continue;
             }
 
-            // Constraint 3: Check spatial distance
-            let (dist, segment_id) = nearby_traj.distance_to_point(&seed_ref.cm.center);
+            // Constraint 3: Check the distance to the leg
+            let (dist, segment) = metric.distance(&seed_ref.cm, nearby_traj, leg);
             if dist > self.args().max_dist + 1e-9 {
                 continue;
             }
 
-            // Add qualifying segment as a candidate
-            let segment = nearby_traj.segment(segment_id).unwrap();
+            // Add qualifying segment as a candidate; a leg without segments has none
+            let Some(segment) = segment else {
+                continue;
+            };
             let candidate = ClusterMember::new(
                 nearby_traj.id,
-                segment_id,
+                segment.id,
                 nearby_traj.weight,
-                segment.middle.clone(),
-                segment.start.clone(),
+                segment.middle,
+                segment.start,
             );
+
+            if nearby_traj.legs().len() > 1 {
+                let previous: Option<&mut (usize, usize, f64)> = multi_leg_candidates
+                    .iter_mut()
+                    .find(|(traj_id, _, _)| *traj_id == nearby_traj.id);
+                match previous {
+                    Some((_, index, best_dist)) => {
+                        if dist < *best_dist {
+                            *best_dist = dist;
+                            cluster.candidates[*index] = candidate;
+                        }
+                        continue;
+                    }
+                    None => {
+                        multi_leg_candidates.push((nearby_traj.id, cluster.candidates.len(), dist))
+                    }
+                }
+            }
             local_weight += candidate.weight;
             cluster.candidates.push(candidate);
         }
@@ -111,7 +168,8 @@ pub trait TraclusAlgorithm {
     ///
     /// This method implements a breadth-first expansion where each candidate
     /// segment is used as a new seed to find additional reachable segments.
-    /// The process continues until no new candidates are found.
+    /// The process continues until no new candidates are found, or the cancellation
+    /// token is cancelled, checked before each round of candidates.
     ///
     /// # Time Complexity
     /// O(m' × cluster_reachable_segs) = O(m' × n × d / bucket_size)
@@ -122,13 +180,15 @@ pub trait TraclusAlgorithm {
     /// * `nearby_trajs` - Candidate trajectories to consider
     ///
     /// # Returns
-    /// A mutable reference to the expanded cluster
+    /// * A mutable reference to the expanded cluster
+    /// * `Err(Cancelled)` if the cancellation token was triggered; the cluster is then partly expanded
     fn expand_segment_cluster<'a>(
         &self,
         cluster: &'a mut Cluster,
-        nearby_trajs: &Vec<&Trajectory>,
-    ) -> &'a mut Cluster {
+        nearby_trajs: &NearbyTrajectories,
+    ) -> Result<&'a mut Cluster, Cancelled> {
         while !cluster.candidates.is_empty() {
+            self.cancel_token().check()?;
             let mut new_clusters: Vec<Cluster> = Vec::new();
 
             // Process candidates in reverse order for consistency with v1 behavior
@@ -152,13 +212,14 @@ pub trait TraclusAlgorithm {
             }
         }
 
-        cluster
+        Ok(cluster)
     }
 
     /// Initializes a cluster from a seed segment.
     ///
     /// This is a convenience method that finds the initial reachable segments
-    /// for a given seed without performing expansion.
+    /// for a given seed without performing expansion. The cluster keeps the angle of
+    /// the seed segment, that is of its leg.
     ///
     /// # Time Complexity
     /// O(n × d / bucket_size)
@@ -173,10 +234,10 @@ pub trait TraclusAlgorithm {
     fn initial_segment_cluster(
         &self,
         seed: (&Segment, &Trajectory),
-        nearby_trajs: &Vec<&Trajectory>,
+        nearby_trajs: &NearbyTrajectories,
     ) -> Option<Cluster> {
         let member: ClusterMember = ClusterMember::new_from_traj(seed.1, seed.0);
-        let seed_member: ClusterSeed = ClusterSeed::new(member, seed.1.angle);
+        let seed_member: ClusterSeed = ClusterSeed::new(member, seed.0.angle);
         self.cluster_reachable_segs(seed_member, nearby_trajs)
     }
 }
@@ -1,3 +1,4 @@
 pub mod base_traclusdl;
 pub mod parallel_rayon_traclusdl;
+pub mod segment_distance;
 pub mod serial_traclusdl;
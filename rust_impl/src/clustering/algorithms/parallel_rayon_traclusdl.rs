@@ -1,27 +1,43 @@
 use crate::io::args::TraclusArgs;
+use crate::utils::cancellation_token::{CancellationToken, Cancelled};
+use crate::utils::progress_reporter::ProgressReporter;
 
-use super::super::geometry::trajectory::Trajectory;
+use super::super::geometry::trajectory::{Leg, Trajectory};
 use super::super::objects::cluster::Cluster;
 use super::super::storage::{
     clustered_trajectories::ClusteredTrajectories,
-    raw_trajectories::{Bucket, RawTrajectories},
+    raw_trajectories::{Bucket, LegRef, NearbyTrajectories, RawTrajectories},
 };
 use super::base_traclusdl::TraclusAlgorithm;
+use super::segment_distance::{self, SegmentDistance};
 
 use rayon::prelude::*;
 use rayon::slice::Iter;
 
 pub struct ParallelRayonTraclusDL {
     args: TraclusArgs,
+    cancel_token: CancellationToken,
+    progress: ProgressReporter,
+    segment_distance: Box<dyn SegmentDistance>,
 }
 
 impl ParallelRayonTraclusDL {
-    pub fn new(args: TraclusArgs) -> Self {
-        Self { args }
+    pub fn new(
+        args: TraclusArgs,
+        cancel_token: CancellationToken,
+        progress: ProgressReporter,
+    ) -> Self {
+        Self {
+            segment_distance: segment_distance::from_options(&args.distance),
+            args,
+            cancel_token,
+            progress,
+        }
     }
 
     /// Completes the parallel clustering using Rayon by iterating over angle buckets
-    /// Each trajectory inside each bucket is computed in parallel
+    /// Each trajectory leg inside each bucket is computed in parallel
+    /// Once cancelled, the remaining legs are skipped and Err(Cancelled) is returned
     ///
     /// # Arguments
     /// * `raw_trajectories` - The raw trajectory storage containing all trajectories
@@ -29,32 +45,45 @@ impl ParallelRayonTraclusDL {
     fn complete_parallel_clustering(
         &self,
         raw_trajectories: &RawTrajectories,
-    ) -> Vec<Vec<Cluster>> {
+    ) -> Result<Vec<Vec<Cluster>>, Cancelled> {
         // Parallelize over angle buckets using Rayon
         let bucket_parallel_iter: Iter<'_, Bucket> = raw_trajectories.traj_buckets.par_iter();
 
-        bucket_parallel_iter
+        let results: Vec<Vec<Cluster>> = bucket_parallel_iter
             .flat_map(|bucket| {
                 // Get nearby trajectories for this angle bucket: contains all trajectories within angle range
-                let nearby_trajs: Vec<&Trajectory> = raw_trajectories
-                    .iter_nearby_angle(bucket.angle_start)
-                    .collect();
-
-                // Parallelize over trajectories in this bucket using Rayon
-                let traj_parallel_iter: Iter<'_, Trajectory> = bucket.trajectories.par_iter();
-                traj_parallel_iter
-                    .map(|traj_seed| {
-                        self.individual_trajectory_clustering(traj_seed, &nearby_trajs)
+                let nearby_trajs: NearbyTrajectories =
+                    raw_trajectories.nearby_angle_buckets(bucket.angle_start);
+
+                // Parallelize over trajectory legs in this bucket using Rayon
+                let leg_parallel_iter: Iter<'_, LegRef> = bucket.legs.par_iter();
+                leg_parallel_iter
+                    .map(|&leg_ref| {
+                        if self.cancel_token.is_cancelled() {
+                            return Vec::new();
+                        }
+                        let (traj_seed, leg_seed): (&Trajectory, &Leg) =
+                            raw_trajectories.leg(leg_ref);
+                        let Ok(clusters) =
+                            self.individual_leg_clustering(traj_seed, leg_seed, &nearby_trajs)
+                        else {
+                            return Vec::new();
+                        };
+                        self.progress.trajectory_done(&clusters); // atomic count shared by all workers
+                        clusters
                     })
                     .collect::<Vec<_>>()
             })
-            .collect()
+            .collect();
+
+        self.cancel_token.check()?;
+        Ok(results)
     }
-    /// Trying a diffenrent approach to parallelization
-    ///
-    /// # Arguments
-    /// * `raw_trajectories` - The raw trajectory storage containing all trajectories
-    /// * `clustered_trajectories` - The clustered trajectory storage to populate with clusters
+    // Trying a diffenrent approach to parallelization
+    //
+    // # Arguments
+    // * `raw_trajectories` - The raw trajectory storage containing all trajectories
+    // * `clustered_trajectories` - The clustered trajectory storage to populate with clusters
     // fn complete_parallel_clustering_v2(
     //     &self,
     //     raw_trajectories: &RawTrajectories,
@@ -77,27 +106,27 @@ impl ParallelRayonTraclusDL {
 
     /// Same logic as the serial version — unchanged
     #[inline]
-    fn individual_trajectory_clustering(
+    fn individual_leg_clustering(
         &self,
         traj_seed: &Trajectory,
-        nearby_trajs: &[&Trajectory],
-    ) -> Vec<Cluster> {
+        leg_seed: &Leg,
+        nearby_trajs: &NearbyTrajectories,
+    ) -> Result<Vec<Cluster>, Cancelled> {
         let mut cluster_group = Vec::new();
 
-        for seed_segment in traj_seed.segments_iter() {
-            let cluster =
-                self.initial_segment_cluster((&seed_segment, traj_seed), &nearby_trajs.to_vec());
+        for seed_segment in traj_seed.leg_segments(leg_seed) {
+            let cluster = self.initial_segment_cluster((seed_segment, traj_seed), nearby_trajs);
 
             if let Some(mut cluster) = cluster {
-                self.expand_segment_cluster(&mut cluster, &nearby_trajs.to_vec());
+                self.expand_segment_cluster(&mut cluster, nearby_trajs)?;
                 cluster_group.push(cluster);
             }
         }
 
-        cluster_group
+        Ok(cluster_group)
     }
 
-    /// Serially cycle through all trajectories and fill non-clustered segments
+    /// Serially cycle through all trajectory legs and fill non-clustered segments
     ///
     /// # Arguments
     /// * `raw_trajectories` - The raw trajectory storage containing all trajectories
@@ -108,16 +137,11 @@ impl ParallelRayonTraclusDL {
         clustered_trajectories: &mut ClusteredTrajectories,
     ) {
         for bucket in &raw_trajectories.traj_buckets {
-            for traj_seed in &bucket.trajectories {
-                clustered_trajectories.fill_non_clustered_segments(traj_seed);
+            for (traj_seed, leg_seed) in raw_trajectories.bucket_legs(bucket) {
+                clustered_trajectories.fill_non_clustered_segments(traj_seed, leg_seed);
             }
         }
     }
-
-    /// Same logic as the serial version — unchanged
-    fn create_corridors(&self, clustered_trajectories: &mut ClusteredTrajectories) {
-        clustered_trajectories.finalize_corridors(self.args());
-    }
 }
 
 impl TraclusAlgorithm for ParallelRayonTraclusDL {
@@ -125,15 +149,28 @@ impl TraclusAlgorithm for ParallelRayonTraclusDL {
         &self.args
     }
 
+    fn cancel_token(&self) -> &CancellationToken {
+        &self.cancel_token
+    }
+
+    fn progress(&self) -> &ProgressReporter {
+        &self.progress
+    }
+
+    fn segment_distance(&self) -> &dyn SegmentDistance {
+        self.segment_distance.as_ref()
+    }
+
     /// Performs a version of DBSCAN clustering on trajectory segments organized in angle-based buckets.
     /// Implements the main clustering logic for the parallel TraClusDL algorithm using Rayon for parallelism.
-    fn db_scan_clustering(
+    /// Corridors are created afterwards by the default `create_corridors`, serially.
+    fn discover_clusters(
         &self,
         raw_trajectories: &RawTrajectories,
         clustered_trajectories: &mut ClusteredTrajectories,
-    ) {
+    ) -> Result<(), Cancelled> {
         // Phase 1: parallel discovery
-        let results: Vec<Vec<Cluster>> = self.complete_parallel_clustering(raw_trajectories);
+        let results: Vec<Vec<Cluster>> = self.complete_parallel_clustering(raw_trajectories)?;
 
         // Phase 2: serial fill in non-clustered segments
         self.fill_non_clustered_segments(raw_trajectories, clustered_trajectories);
@@ -143,7 +180,6 @@ impl TraclusAlgorithm for ParallelRayonTraclusDL {
             clustered_trajectories.add_list_cluster(clusters);
         }
 
-        // Phase 3: create corridors from clusters and finalize non-clustered segments
-        self.create_corridors(clustered_trajectories);
+        Ok(())
     }
 }
@@ -0,0 +1,365 @@
+// segment_distance.rs - Distance metrics of the reachability test (constraint 3)
+//
+// Every metric compares the seed with one segment per candidate leg: the segment of the leg
+// nearest to the seed middle. The metrics only differ in the distance they give that pair,
+// and in how far from the seed middle such a pair can still be within max_dist, which sizes
+// the spatial query. They hold no state, so a run gives the same clusters every time.
+
+use super::super::geometry::{
+    point::Point,
+    segment::Segment,
+    trajectory::{Leg, Trajectory},
+};
+use super::super::objects::cluster_member::ClusterMember;
+use crate::io::args::{DistanceMetric, DistanceOptions};
+
+/// Distance between the seed of a cluster and a candidate leg.
+pub trait SegmentDistance: Send + Sync {
+    /// Distance between the segments `a` and `b`, given by their ends.
+    fn between_segments(&self, a: (Point, Point), b: (Point, Point)) -> f64;
+
+    /// Radius around the middle of `seed` beyond which no segment is within `max_dist`.
+    fn search_radius(&self, seed: &ClusterMember, max_dist: f64) -> f64;
+
+    /// Distance from `seed` to `leg`, and the segment of the leg it was measured to.
+    /// The segment is None only for a leg too short to be cut into one.
+    fn distance<'a>(
+        &self,
+        seed: &ClusterMember,
+        traj: &'a Trajectory,
+        leg: &Leg,
+    ) -> (f64, Option<&'a Segment>) {
+        let (_, segment) = traj.distance_to_point(leg, &seed.center);
+        match segment {
+            Some(segment) => (
+                self.between_segments(
+                    (seed.start, seed.end_point()),
+                    (segment.start, segment.get_end()),
+                ),
+                Some(segment),
+            ),
+            None => (f64::INFINITY, None),
+        }
+    }
+}
+
+/// Metric selected by `options`.
+pub fn from_options(options: &DistanceOptions) -> Box<dyn SegmentDistance> {
+    match options.metric {
+        DistanceMetric::Midpoint => Box::new(MidpointToLine),
+        DistanceMetric::Traclus => Box::new(TraclusWeighted {
+            w_perpendicular: options.w_perpendicular,
+            w_parallel: options.w_parallel,
+            w_angular: options.w_angular,
+        }),
+        DistanceMetric::Hausdorff => Box::new(Hausdorff),
+    }
+}
+
+// ─────────────────────────────────────────────
+// MidpointToLine  — the TraclusDL metric
+// ─────────────────────────────────────────────
+
+/// From the middle of the seed to the closest point of the whole candidate leg.
+pub struct MidpointToLine;
+
+impl SegmentDistance for MidpointToLine {
+    fn between_segments(&self, a: (Point, Point), b: (Point, Point)) -> f64 {
+        let middle: Point = Point {
+            x: (a.0.x + a.1.x) / 2.0,
+            y: (a.0.y + a.1.y) / 2.0,
+        };
+        point_to_segment(&middle, &b.0, &b.1)
+    }
+
+    fn search_radius(&self, _seed: &ClusterMember, max_dist: f64) -> f64 {
+        max_dist
+    }
+
+    fn distance<'a>(
+        &self,
+        seed: &ClusterMember,
+        traj: &'a Trajectory,
+        leg: &Leg,
+    ) -> (f64, Option<&'a Segment>) {
+        traj.distance_to_point(leg, &seed.center)
+    }
+}
+
+// ─────────────────────────────────────────────
+// TraclusWeighted  — Lee, Han & Whang, 2007, section 2.3
+// ─────────────────────────────────────────────
+
+/// w⊥·d⊥ + w∥·d∥ + wθ·dθ, measured from the longer of the two segments.
+pub struct TraclusWeighted {
+    pub w_perpendicular: f64,
+    pub w_parallel: f64,
+    pub w_angular: f64,
+}
+
+impl SegmentDistance for TraclusWeighted {
+    fn between_segments(&self, a: (Point, Point), b: (Point, Point)) -> f64 {
+        let (long, short): ((Point, Point), (Point, Point)) =
+            if length(&b.0, &b.1) > length(&a.0, &a.1) {
+                (b, a)
+            } else {
+                (a, b)
+            };
+
+        let (l_perp_1, l_par_1): (f64, f64) = project(&long, &short.0);
+        let (l_perp_2, l_par_2): (f64, f64) = project(&long, &short.1);
+
+        let d_perpendicular: f64 = if l_perp_1 + l_perp_2 == 0.0 {
+            0.0
+        } else {
+            (l_perp_1 * l_perp_1 + l_perp_2 * l_perp_2) / (l_perp_1 + l_perp_2)
+        };
+        let d_parallel: f64 = l_par_1.min(l_par_2);
+        let d_angular: f64 = angular(&long, &short);
+
+        self.w_perpendicular * d_perpendicular
+            + self.w_parallel * d_parallel
+            + self.w_angular * d_angular
+    }
+
+    // One end of the shorter segment projects within d∥ of an end of the longer one, at most
+    // 2·d⊥ away from its projection: some pair of ends is within 2·d⊥ + d∥, and the seed
+    // middle within half the seed length of its end. Without a weight on d⊥ or d∥, anywhere.
+    fn search_radius(&self, seed: &ClusterMember, max_dist: f64) -> f64 {
+        if self.w_perpendicular <= 0.0 || self.w_parallel <= 0.0 {
+            return f64::INFINITY;
+        }
+        let ends_dist: f64 = max_dist * (2.0 / self.w_perpendicular).max(1.0 / self.w_parallel);
+        length(&seed.start, &seed.center) + ends_dist
+    }
+}
+
+// Distance of `point` to the line of `segment`, and of its projection to the nearer end
+fn project(segment: &(Point, Point), point: &Point) -> (f64, f64) {
+    let (start, end): (&Point, &Point) = (&segment.0, &segment.1);
+    let dx: f64 = end.x - start.x;
+    let dy: f64 = end.y - start.y;
+    let length_sq: f64 = dx * dx + dy * dy;
+    if length_sq == 0.0 {
+        return (length(start, point), 0.0);
+    }
+
+    let t: f64 = ((point.x - start.x) * dx + (point.y - start.y) * dy) / length_sq;
+    let projection: Point = Point {
+        x: start.x + t * dx,
+        y: start.y + t * dy,
+    };
+    let to_start: f64 = length(start, &projection);
+    let to_end: f64 = length(end, &projection);
+    (length(point, &projection), to_start.min(to_end))
+}
+
+// dθ: length of `short` times the sine of the angle between the segments, its full length
+// from 90° on
+fn angular(long: &(Point, Point), short: &(Point, Point)) -> f64 {
+    let (lx, ly): (f64, f64) = (long.1.x - long.0.x, long.1.y - long.0.y);
+    let (sx, sy): (f64, f64) = (short.1.x - short.0.x, short.1.y - short.0.y);
+    let long_length: f64 = lx.hypot(ly);
+    let short_length: f64 = sx.hypot(sy);
+    if long_length == 0.0 || short_length == 0.0 {
+        return 0.0;
+    }
+
+    let cos: f64 = (lx * sx + ly * sy) / (long_length * short_length);
+    if cos <= 0.0 {
+        short_length
+    } else {
+        short_length * (1.0 - cos * cos).max(0.0).sqrt()
+    }
+}
+
+// ─────────────────────────────────────────────
+// Hausdorff
+// ─────────────────────────────────────────────
+
+/// Largest distance from a point of either segment to the other segment.
+pub struct Hausdorff;
+
+impl SegmentDistance for Hausdorff {
+    // The distance to a segment is convex along the other one: its maximum is at an end
+    fn between_segments(&self, a: (Point, Point), b: (Point, Point)) -> f64 {
+        point_to_segment(&a.0, &b.0, &b.1)
+            .max(point_to_segment(&a.1, &b.0, &b.1))
+            .max(point_to_segment(&b.0, &a.0, &a.1))
+            .max(point_to_segment(&b.1, &a.0, &a.1))
+    }
+
+    // Never less than the distance from the seed middle to the other segment
+    fn search_radius(&self, _seed: &ClusterMember, max_dist: f64) -> f64 {
+        max_dist
+    }
+}
+
+// ─────────────────────────────────────────────
+// Helpers
+// ─────────────────────────────────────────────
+
+fn length(a: &Point, b: &Point) -> f64 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+fn point_to_segment(point: &Point, start: &Point, end: &Point) -> f64 {
+    let dx: f64 = end.x - start.x;
+    let dy: f64 = end.y - start.y;
+    let length_sq: f64 = dx * dx + dy * dy;
+    if length_sq == 0.0 {
+        return length(start, point);
+    }
+
+    let t: f64 = ((point.x - start.x) * dx + (point.y - start.y) * dy) / length_sq;
+    let t: f64 = t.clamp(0.0, 1.0);
+    let near: Point = Point {
+        x: start.x + t * dx,
+        y: start.y + t * dy,
+    };
+    length(point, &near)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seeded_rng::SeededRng;
+
+    fn segment(x1: f64, y1: f64, x2: f64, y2: f64) -> (Point, Point) {
+        (Point { x: x1, y: y1 }, Point { x: x2, y: y2 })
+    }
+
+    fn seed_of(ends: (Point, Point)) -> ClusterMember {
+        let center: Point = Point {
+            x: (ends.0.x + ends.1.x) / 2.0,
+            y: (ends.0.y + ends.1.y) / 2.0,
+        };
+        ClusterMember::new(0, 0, 1, center, ends.0)
+    }
+
+    fn random_segment(rng: &mut SeededRng) -> (Point, Point) {
+        let start: Point = Point {
+            x: rng.uniform(-300.0, 300.0),
+            y: rng.uniform(-300.0, 300.0),
+        };
+        let length: f64 = rng.uniform(0.0, 200.0);
+        let angle: f64 = rng.uniform(0.0, 2.0 * std::f64::consts::PI);
+        let end: Point = Point {
+            x: start.x + length * angle.cos(),
+            y: start.y + length * angle.sin(),
+        };
+        (start, end)
+    }
+
+    const TRACLUS: TraclusWeighted = TraclusWeighted {
+        w_perpendicular: 1.0,
+        w_parallel: 1.0,
+        w_angular: 1.0,
+    };
+
+    #[test]
+    fn midpoint_measures_from_the_seed_middle() {
+        let seed: (Point, Point) = segment(0.0, 0.0, 10.0, 0.0);
+        let other: (Point, Point) = segment(0.0, 3.0, 10.0, 3.0);
+        assert_eq!(MidpointToLine.between_segments(seed, other), 3.0);
+
+        // Past the end of the other segment, to its nearest end
+        let other: (Point, Point) = segment(8.0, 4.0, 20.0, 4.0);
+        assert_eq!(MidpointToLine.between_segments(seed, other), 5.0);
+    }
+
+    #[test]
+    fn traclus_components() {
+        let seed: (Point, Point) = segment(0.0, 0.0, 10.0, 0.0);
+        assert_eq!(TRACLUS.between_segments(seed, seed), 0.0);
+
+        // Parallel at 3: only d⊥
+        let other: (Point, Point) = segment(0.0, 3.0, 10.0, 3.0);
+        assert_eq!(TRACLUS.between_segments(seed, other), 3.0);
+
+        // Collinear and shifted along the line: only d∥, to the nearer end
+        let other: (Point, Point) = segment(12.0, 0.0, 16.0, 0.0);
+        assert_eq!(TRACLUS.between_segments(seed, other), 2.0);
+
+        // Opposite direction: dθ is the whole length of the shorter segment, plus d∥ = 2
+        let other: (Point, Point) = segment(6.0, 0.0, 2.0, 0.0);
+        assert_eq!(TRACLUS.between_segments(seed, other), 4.0 + 2.0);
+    }
+
+    #[test]
+    fn traclus_weights_scale_their_component() {
+        let weighted: TraclusWeighted = TraclusWeighted {
+            w_perpendicular: 2.0,
+            w_parallel: 0.5,
+            w_angular: 0.0,
+        };
+        let seed: (Point, Point) = segment(0.0, 0.0, 10.0, 0.0);
+        let other: (Point, Point) = segment(0.0, 3.0, 10.0, 3.0);
+        assert_eq!(weighted.between_segments(seed, other), 6.0);
+        let other: (Point, Point) = segment(12.0, 0.0, 16.0, 0.0);
+        assert_eq!(weighted.between_segments(seed, other), 1.0);
+        let other: (Point, Point) = segment(6.0, 0.0, 2.0, 0.0);
+        assert_eq!(weighted.between_segments(seed, other), 1.0);
+    }
+
+    #[test]
+    fn hausdorff_takes_the_farthest_end() {
+        let seed: (Point, Point) = segment(0.0, 0.0, 10.0, 0.0);
+        let other: (Point, Point) = segment(0.0, 0.0, 5.0, 0.0);
+        assert_eq!(Hausdorff.between_segments(seed, other), 5.0);
+        assert_eq!(Hausdorff.between_segments(other, seed), 5.0);
+
+        let other: (Point, Point) = segment(2.0, 1.0, 8.0, 2.0);
+        // From the seed end (10, 0) to the other end (8, 2)
+        assert_eq!(Hausdorff.between_segments(seed, other), 2.0_f64.hypot(2.0));
+    }
+
+    #[test]
+    fn search_radius_without_a_weight_is_unbounded() {
+        let seed: ClusterMember = seed_of(segment(0.0, 0.0, 10.0, 0.0));
+        for (w_perpendicular, w_parallel) in [(0.0, 1.0), (1.0, 0.0)] {
+            let weighted: TraclusWeighted = TraclusWeighted {
+                w_perpendicular,
+                w_parallel,
+                w_angular: 1.0,
+            };
+            assert_eq!(weighted.search_radius(&seed, 50.0), f64::INFINITY);
+        }
+    }
+
+    // Any segment within max_dist of the seed must be within the search radius of its middle,
+    // or the spatial query would miss it
+    #[test]
+    fn search_radius_bounds_every_metric() {
+        let mut rng: SeededRng = SeededRng::new(11);
+        let metrics: Vec<Box<dyn SegmentDistance>> = vec![
+            Box::new(MidpointToLine),
+            Box::new(Hausdorff),
+            Box::new(TRACLUS),
+            Box::new(TraclusWeighted {
+                w_perpendicular: 0.3,
+                w_parallel: 2.5,
+                w_angular: 0.1,
+            }),
+            Box::new(TraclusWeighted {
+                w_perpendicular: 4.0,
+                w_parallel: 0.2,
+                w_angular: 3.0,
+            }),
+        ];
+
+        for metric in &metrics {
+            for _ in 0..5000 {
+                let (seed, other): ((Point, Point), (Point, Point)) =
+                    (random_segment(&mut rng), random_segment(&mut rng));
+                let member: ClusterMember = seed_of(seed);
+
+                // The tightest max_dist that still accepts the pair
+                let max_dist: f64 = metric.between_segments(seed, other);
+                let radius: f64 = metric.search_radius(&member, max_dist);
+                let reach: f64 = point_to_segment(&member.center, &other.0, &other.1);
+                assert!(reach <= radius + 1e-6, "{} beyond radius {}", reach, radius);
+            }
+        }
+    }
+}
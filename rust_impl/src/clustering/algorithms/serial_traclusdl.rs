@@ -1,13 +1,20 @@
-use super::super::geometry::trajectory::Trajectory;
+use super::super::geometry::trajectory::{Leg, Trajectory};
 use super::super::objects::cluster::Cluster;
 use super::super::storage::{
-    clustered_trajectories::ClusteredTrajectories, raw_trajectories::RawTrajectories,
+    clustered_trajectories::ClusteredTrajectories,
+    raw_trajectories::{NearbyTrajectories, RawTrajectories},
 };
 use super::base_traclusdl::TraclusAlgorithm;
+use super::segment_distance::{self, SegmentDistance};
 use crate::io::args::TraclusArgs;
+use crate::utils::cancellation_token::{CancellationToken, Cancelled};
+use crate::utils::progress_reporter::ProgressReporter;
 
 pub struct SerialTraclusDL {
     args: TraclusArgs,
+    cancel_token: CancellationToken,
+    progress: ProgressReporter,
+    segment_distance: Box<dyn SegmentDistance>,
 }
 
 impl TraclusAlgorithm for SerialTraclusDL {
@@ -18,29 +25,50 @@ impl TraclusAlgorithm for SerialTraclusDL {
         &self.args
     }
 
+    fn cancel_token(&self) -> &CancellationToken {
+        &self.cancel_token
+    }
+
+    fn progress(&self) -> &ProgressReporter {
+        &self.progress
+    }
+
+    fn segment_distance(&self) -> &dyn SegmentDistance {
+        self.segment_distance.as_ref()
+    }
+
     // ============================================================
     // Required Method
     // ============================================================
 
     /// Performs a version of DBSCAN clustering on trajectory segments organized in angle-based buckets.
     /// Implements the main clustering logic for the Serial TraClusDL algorithm.
-    fn db_scan_clustering(
+    fn discover_clusters(
         &self,
         raw_trajectories: &RawTrajectories,
         clustered_trajectories: &mut ClusteredTrajectories,
-    ) {
-        self.complete_serial_clustering(raw_trajectories, clustered_trajectories);
-        self.create_corridors(clustered_trajectories);
+    ) -> Result<(), Cancelled> {
+        self.complete_serial_clustering(raw_trajectories, clustered_trajectories)
     }
 }
 
 impl SerialTraclusDL {
-    pub fn new(args: TraclusArgs) -> Self {
-        Self { args }
+    pub fn new(
+        args: TraclusArgs,
+        cancel_token: CancellationToken,
+        progress: ProgressReporter,
+    ) -> Self {
+        Self {
+            segment_distance: segment_distance::from_options(&args.distance),
+            args,
+            cancel_token,
+            progress,
+        }
     }
 
     /// Completes the serial clustering process by iterating over angle buckets
-    /// Clusters each trajectory and fills non-clustered segments
+    /// Clusters each trajectory leg and fills non-clustered segments
+    /// The cancellation token is checked and progress reported for each leg
     ///
     /// # Arguments
     /// * `raw_trajectories` - The raw trajectory storage containing all trajectories
@@ -49,64 +77,146 @@ impl SerialTraclusDL {
         &self,
         raw_trajectories: &RawTrajectories,
         clustered_trajectories: &mut ClusteredTrajectories,
-    ) {
+    ) -> Result<(), Cancelled> {
         for bucket in &raw_trajectories.traj_buckets {
             // Get nearby trajectories for this angle bucket: contains all trajectories within angle range
-            let nearby_trajs: Vec<&Trajectory> = raw_trajectories
-                .iter_nearby_angle(bucket.angle_start)
-                .collect();
+            let nearby_trajs: NearbyTrajectories =
+                raw_trajectories.nearby_angle_buckets(bucket.angle_start);
+
+            for (traj_seed, leg_seed) in raw_trajectories.bucket_legs(bucket) {
+                self.cancel_token.check()?;
 
-            for traj_seed in &bucket.trajectories {
-                // Cluster segments from this trajectory using nearby trajectories
+                // Cluster segments from this leg using nearby trajectories
                 let clusters: Vec<Cluster> =
-                    self.individual_trajectory_clustering(traj_seed, &nearby_trajs);
+                    self.individual_leg_clustering(traj_seed, leg_seed, &nearby_trajs)?;
+                self.progress.trajectory_done(&clusters);
                 clustered_trajectories.add_list_cluster(clusters);
 
                 // Fill all segments to be treated as non-clustered later
-                clustered_trajectories.fill_non_clustered_segments(traj_seed);
+                clustered_trajectories.fill_non_clustered_segments(traj_seed, leg_seed);
             }
         }
+
+        Ok(())
     }
 
-    /// Clusters an individual trajectory against nearby trajectories.
-    /// For each segment:
+    /// Clusters an individual trajectory leg against nearby trajectories.
+    /// For each segment of the leg:
     /// - Attempts to create an initial cluster if density requirements are met
     /// - Expands the cluster to include all reachable segments
     /// - Stores the completed cluster
     ///
     /// # Arguments
     /// * `traj_seed` - The trajectory to use as a clustering seed
-    /// * `nearby_trajs` - Vector of nearby trajectories to consider for clustering
+    /// * `leg_seed` - The leg of `traj_seed` whose segments are the seeds
+    /// * `nearby_trajs` - Nearby angle buckets to consider for clustering
     /// # Returns
-    /// * A vector of clusters formed from the trajectory segments
+    /// * A vector of clusters formed from the leg segments
+    /// * `Err(Cancelled)` if the cancellation token was triggered during an expansion
     #[inline]
-    fn individual_trajectory_clustering(
+    fn individual_leg_clustering(
         &self,
         traj_seed: &Trajectory,
-        nearby_trajs: &Vec<&Trajectory>,
-    ) -> Vec<Cluster> {
+        leg_seed: &Leg,
+        nearby_trajs: &NearbyTrajectories,
+    ) -> Result<Vec<Cluster>, Cancelled> {
         let mut cluster_group: Vec<Cluster> = Vec::new();
 
-        for seed_segment in traj_seed.segments_iter() {
+        for seed_segment in traj_seed.leg_segments(leg_seed) {
             // Try to form an initial cluster from this seed segment
             let cluster: Option<Cluster> =
-                self.initial_segment_cluster((&seed_segment, &traj_seed), nearby_trajs);
+                self.initial_segment_cluster((seed_segment, traj_seed), nearby_trajs);
 
             if let Some(mut cluster) = cluster {
                 // Expand the cluster to include all density-reachable segments
-                self.expand_segment_cluster(&mut cluster, nearby_trajs);
+                self.expand_segment_cluster(&mut cluster, nearby_trajs)?;
                 cluster_group.push(cluster);
             }
             // If no cluster forms, continue to next segment (not dense enough)
         }
 
-        return cluster_group;
+        Ok(cluster_group)
     }
+}
 
-    /// Creates corridors for all clustered trajectories based on the clustering results
-    /// # Arguments
-    /// * `clustered_trajectories` - The clustered trajectory storage containing all clusters
-    fn create_corridors(&self, clustered_trajectories: &mut ClusteredTrajectories) {
-        clustered_trajectories.finalize_corridors(self.args());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::geometry::input_od_line::InputODLine;
+    use crate::clustering::geometry::point::Point;
+    use crate::clustering::storage::raw_trajectories::RawTrajectories;
+
+    // Five parallel lines 20 apart: each one only reaches its neighbours within max_dist 30
+    fn chain_of_lines() -> RawTrajectories {
+        let mut raw_storage: RawTrajectories = RawTrajectories::new(30.0, false);
+        for index in 0..5 {
+            let y: f64 = index as f64 * 20.0;
+            let input: InputODLine = InputODLine {
+                line_id: index + 1,
+                weight: 1,
+                start: Point { x: 0.0, y },
+                end: Point { x: 100.0, y },
+                via: Vec::new(),
+            };
+            raw_storage.add_trajectory(Trajectory::new(input, 100.0));
+        }
+        raw_storage.build_spatial_index(50.0);
+        raw_storage
+    }
+
+    fn algorithm(cancel_token: &CancellationToken) -> SerialTraclusDL {
+        let args: TraclusArgs = TraclusArgs {
+            max_dist: 30.0,
+            min_density: 1,
+            segment_size: 100.0,
+            ..TraclusArgs::default()
+        };
+        SerialTraclusDL::new(args, cancel_token.clone(), ProgressReporter::disabled())
+    }
+
+    fn seed_cluster(algorithm: &SerialTraclusDL, raw_storage: &RawTrajectories) -> Cluster {
+        let traj: &Trajectory = &raw_storage.trajectories[0];
+        let nearby_trajs: NearbyTrajectories = raw_storage.nearby_angle_buckets(0.0);
+        algorithm
+            .initial_segment_cluster(
+                (&traj.leg_segments(&traj.legs()[0])[0], traj),
+                &nearby_trajs,
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn expansion_reaches_the_whole_chain() {
+        let raw_storage: RawTrajectories = chain_of_lines();
+        let algorithm: SerialTraclusDL = algorithm(&CancellationToken::new());
+        let mut cluster: Cluster = seed_cluster(&algorithm, &raw_storage);
+
+        let nearby_trajs: NearbyTrajectories = raw_storage.nearby_angle_buckets(0.0);
+        assert!(
+            algorithm
+                .expand_segment_cluster(&mut cluster, &nearby_trajs)
+                .is_ok()
+        );
+        assert!(cluster.candidates.is_empty());
+        assert_eq!(cluster.members.len(), 4); // the other lines, besides the seed
+    }
+
+    #[test]
+    fn cancelled_expansion_stops_before_the_next_round() {
+        let raw_storage: RawTrajectories = chain_of_lines();
+        let cancel_token: CancellationToken = CancellationToken::new();
+        let algorithm: SerialTraclusDL = algorithm(&cancel_token);
+        let mut cluster: Cluster = seed_cluster(&algorithm, &raw_storage);
+        let members_before: usize = cluster.members.len();
+
+        cancel_token.cancel();
+        let nearby_trajs: NearbyTrajectories = raw_storage.nearby_angle_buckets(0.0);
+        assert!(
+            algorithm
+                .expand_segment_cluster(&mut cluster, &nearby_trajs)
+                .is_err()
+        );
+        assert_eq!(cluster.members.len(), members_before);
+        assert!(!cluster.candidates.is_empty());
     }
 }
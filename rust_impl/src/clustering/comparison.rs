@@ -0,0 +1,378 @@
+// comparison.rs - Side-by-side comparison of two clustering runs
+//
+// Segments are matched by (trajectory_id, segment_id), so both runs must come from the
+// same input cut with the same segment size. Each corridor of run A is paired with the
+// corridor of run B holding most of its matched segments. A matched segment is assigned
+// consistently when both runs leave it outside any corridor, or when its corridor in B is
+// the best match of its corridor in A.
+
+use std::collections::{HashMap, HashSet};
+
+use super::geometry::point::Point;
+use super::storage::clustered_trajectories::ClusteredTrajectories;
+
+// (trajectory_id, segment_id)
+pub type SegmentKey = (usize, usize);
+
+// ─────────────────────────────────────────────
+// RunSnapshot — what the comparison needs of a run
+// ─────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+pub struct CorridorLine {
+    pub id: usize,
+    pub weight: u32,
+    pub start: Point,
+    pub end: Point,
+}
+
+/// Corridors and segment assignments of a run, taken from memory or read from output files.
+#[derive(Debug, Clone, Default)]
+pub struct RunSnapshot {
+    pub corridors: Vec<CorridorLine>,
+    pub segments: HashMap<SegmentKey, Option<usize>>, // corridor id, None when not clustered
+    pub partition: Option<String>, // settings that numbered the segments, when the files record them
+}
+
+impl RunSnapshot {
+    pub fn from_clust_storage(clust_storage: &ClusteredTrajectories) -> Self {
+        let corridors: Vec<CorridorLine> = clust_storage
+            .corridors
+            .iter()
+            .map(|corridor| CorridorLine {
+                id: corridor.id,
+                weight: corridor.weight,
+                start: corridor.start,
+                end: corridor.end,
+            })
+            .collect();
+
+        // Corridor index -1 marks the non-clustered segments
+        let segments: HashMap<SegmentKey, Option<usize>> = clust_storage
+            .get_all_cluster_members_iter()
+            .map(|(corridor_id, cm)| {
+                let corridor: Option<usize> = (corridor_id >= 0).then_some(corridor_id as usize);
+                ((cm.traj_id, cm.segment_id), corridor)
+            })
+            .collect();
+
+        Self {
+            corridors,
+            segments,
+            partition: None, // runs in memory compare their arguments instead
+        }
+    }
+}
+
+// ─────────────────────────────────────────────
+// ComparisonReport
+// ─────────────────────────────────────────────
+
+/// Corridor of run A and its best match in run B.
+#[derive(Debug, Clone)]
+pub struct CorridorMatch {
+    pub corridor_a: usize,
+    pub corridor_b: usize,
+    pub shared_segments: usize,
+    pub weight_a: u32,
+    pub weight_b: u32,
+    pub start_displacement: f64, // distance between the two start points
+    pub end_displacement: f64,
+}
+
+impl CorridorMatch {
+    /// Weight of B minus weight of A.
+    pub fn weight_difference(&self) -> i64 {
+        self.weight_b as i64 - self.weight_a as i64
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ComparisonReport {
+    pub segments_a: usize,
+    pub segments_b: usize,
+    pub matched_segments: usize, // present in both runs
+    pub both_clustered: usize,
+    pub both_non_clustered: usize,
+    pub only_clustered_a: usize,
+    pub only_clustered_b: usize,
+    pub consistent_segments: usize,
+
+    pub corridor_matches: Vec<CorridorMatch>, // by corridor of A
+    pub unmatched_corridors_a: Vec<usize>,    // no matched segment in any corridor of B
+    pub unmatched_corridors_b: Vec<usize>,    // best match of no corridor of A
+}
+
+impl ComparisonReport {
+    /// Share of the matched segments assigned consistently; 1 when nothing matched.
+    pub fn consistency_share(&self) -> f64 {
+        share(self.consistent_segments, self.matched_segments)
+    }
+
+    /// Segments clustered in both runs over the segments clustered in at least one
+    /// (similarity_index_1 of benchmarks/measurements.py).
+    pub fn clustered_similarity(&self) -> f64 {
+        let disagreements: usize = self.only_clustered_a + self.only_clustered_b;
+        share(self.both_clustered, self.both_clustered + disagreements)
+    }
+
+    /// Segments with the same clustered / non-clustered status over all matched segments
+    /// (similarity_index_2 of benchmarks/measurements.py).
+    pub fn status_similarity(&self) -> f64 {
+        let agreements: usize = self.both_clustered + self.both_non_clustered;
+        share(agreements, self.matched_segments)
+    }
+}
+
+fn share(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        1.0 // nothing to compare counts as identical, as in measurements.py
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+// ─────────────────────────────────────────────
+// Comparison
+// ─────────────────────────────────────────────
+
+pub fn compare_runs(a: &RunSnapshot, b: &RunSnapshot) -> ComparisonReport {
+    let mut report: ComparisonReport = ComparisonReport {
+        segments_a: a.segments.len(),
+        segments_b: b.segments.len(),
+        ..ComparisonReport::default()
+    };
+
+    // Status counts, and how many segments each pair of corridors shares
+    let mut shared: HashMap<(usize, usize), usize> = HashMap::new();
+    for (key, corridor_a) in &a.segments {
+        let Some(corridor_b) = b.segments.get(key) else {
+            continue;
+        };
+        report.matched_segments += 1;
+        match (corridor_a, corridor_b) {
+            (Some(ca), Some(cb)) => {
+                report.both_clustered += 1;
+                *shared.entry((*ca, *cb)).or_default() += 1;
+            }
+            (None, None) => report.both_non_clustered += 1,
+            (Some(_), None) => report.only_clustered_a += 1,
+            (None, Some(_)) => report.only_clustered_b += 1,
+        }
+    }
+
+    // Best match: most shared segments, the lowest id of B on a tie
+    let mut best_match: HashMap<usize, (usize, usize)> = HashMap::new(); // A → (B, shared)
+    for (&(ca, cb), &count) in &shared {
+        let best: &mut (usize, usize) = best_match.entry(ca).or_insert((cb, count));
+        if count > best.1 || (count == best.1 && cb < best.0) {
+            *best = (cb, count);
+        }
+    }
+
+    report.consistent_segments = report.both_non_clustered
+        + shared
+            .iter()
+            .filter(|((ca, cb), _)| best_match.get(ca).is_some_and(|(best, _)| best == cb))
+            .map(|(_, count)| count)
+            .sum::<usize>();
+
+    let corridors_b: HashMap<usize, &CorridorLine> =
+        b.corridors.iter().map(|c| (c.id, c)).collect();
+    for corridor_a in &a.corridors {
+        let matched = best_match
+            .get(&corridor_a.id)
+            .and_then(|(cb, count)| Some((corridors_b.get(cb)?, *count)));
+        match matched {
+            Some((corridor_b, shared_segments)) => {
+                report.corridor_matches.push(CorridorMatch {
+                    corridor_a: corridor_a.id,
+                    corridor_b: corridor_b.id,
+                    shared_segments,
+                    weight_a: corridor_a.weight,
+                    weight_b: corridor_b.weight,
+                    start_displacement: distance(&corridor_a.start, &corridor_b.start),
+                    end_displacement: distance(&corridor_a.end, &corridor_b.end),
+                });
+            }
+            None => report.unmatched_corridors_a.push(corridor_a.id),
+        }
+    }
+    report.corridor_matches.sort_by_key(|m| m.corridor_a);
+    report.unmatched_corridors_a.sort_unstable();
+
+    let matched_b: HashSet<usize> = report
+        .corridor_matches
+        .iter()
+        .map(|m| m.corridor_b)
+        .collect();
+    report.unmatched_corridors_b = b
+        .corridors
+        .iter()
+        .map(|c| c.id)
+        .filter(|id| !matched_b.contains(id))
+        .collect();
+    report.unmatched_corridors_b.sort_unstable();
+
+    report
+}
+
+fn distance(p: &Point, q: &Point) -> f64 {
+    (p.x - q.x).hypot(p.y - q.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corridor(id: usize, weight: u32, start: (f64, f64), end: (f64, f64)) -> CorridorLine {
+        CorridorLine {
+            id,
+            weight,
+            start: Point {
+                x: start.0,
+                y: start.1,
+            },
+            end: Point { x: end.0, y: end.1 },
+        }
+    }
+
+    fn snapshot(
+        corridors: Vec<CorridorLine>,
+        segments: &[(SegmentKey, Option<usize>)],
+    ) -> RunSnapshot {
+        RunSnapshot {
+            corridors,
+            segments: segments.iter().copied().collect(),
+            partition: None,
+        }
+    }
+
+    #[test]
+    fn identical_runs_are_fully_consistent() {
+        let run: RunSnapshot = snapshot(
+            vec![corridor(0, 3, (0.0, 0.0), (10.0, 0.0))],
+            &[((0, 0), Some(0)), ((0, 1), Some(0)), ((1, 0), None)],
+        );
+        let report: ComparisonReport = compare_runs(&run, &run);
+
+        assert_eq!(report.matched_segments, 3);
+        assert_eq!(report.consistent_segments, 3);
+        assert_eq!(report.consistency_share(), 1.0);
+        assert_eq!(report.clustered_similarity(), 1.0);
+        assert_eq!(report.status_similarity(), 1.0);
+        assert_eq!(report.corridor_matches.len(), 1);
+        assert_eq!(report.corridor_matches[0].start_displacement, 0.0);
+        assert!(report.unmatched_corridors_a.is_empty() && report.unmatched_corridors_b.is_empty());
+    }
+
+    #[test]
+    fn status_counts_cover_every_matched_segment() {
+        let a: RunSnapshot = snapshot(
+            vec![corridor(0, 2, (0.0, 0.0), (1.0, 0.0))],
+            &[
+                ((0, 0), Some(0)),
+                ((0, 1), Some(0)),
+                ((0, 2), None),
+                ((0, 3), None),
+                ((9, 9), None),
+            ],
+        );
+        let b: RunSnapshot = snapshot(
+            vec![corridor(5, 2, (0.0, 0.0), (1.0, 0.0))],
+            &[
+                ((0, 0), Some(5)),
+                ((0, 1), None),
+                ((0, 2), Some(5)),
+                ((0, 3), None),
+            ],
+        );
+        let report: ComparisonReport = compare_runs(&a, &b);
+
+        assert_eq!((report.segments_a, report.segments_b), (5, 4));
+        assert_eq!(report.matched_segments, 4);
+        assert_eq!(report.both_clustered, 1);
+        assert_eq!(report.both_non_clustered, 1);
+        assert_eq!(report.only_clustered_a, 1);
+        assert_eq!(report.only_clustered_b, 1);
+        assert_eq!(report.clustered_similarity(), 1.0 / 3.0);
+        assert_eq!(report.status_similarity(), 0.5);
+    }
+
+    #[test]
+    fn corridor_is_paired_with_the_one_sharing_most_segments() {
+        let a: RunSnapshot = snapshot(
+            vec![corridor(0, 3, (0.0, 0.0), (10.0, 0.0))],
+            &[((0, 0), Some(0)), ((0, 1), Some(0)), ((0, 2), Some(0))],
+        );
+        let b: RunSnapshot = snapshot(
+            vec![
+                corridor(1, 1, (0.0, 0.0), (10.0, 0.0)),
+                corridor(2, 2, (3.0, 4.0), (10.0, 1.0)),
+            ],
+            &[((0, 0), Some(1)), ((0, 1), Some(2)), ((0, 2), Some(2))],
+        );
+        let report: ComparisonReport = compare_runs(&a, &b);
+
+        let best: &CorridorMatch = &report.corridor_matches[0];
+        assert_eq!(
+            (best.corridor_a, best.corridor_b, best.shared_segments),
+            (0, 2, 2)
+        );
+        assert_eq!(best.weight_difference(), -1);
+        assert_eq!(best.start_displacement, 5.0);
+        assert_eq!(best.end_displacement, 1.0);
+
+        // The segment sent to corridor 1 is not where the rest of its corridor went
+        assert_eq!(report.consistent_segments, 2);
+        assert_eq!(report.unmatched_corridors_b, vec![1]);
+    }
+
+    #[test]
+    fn tie_goes_to_the_lowest_corridor_id() {
+        let a: RunSnapshot = snapshot(
+            vec![corridor(0, 2, (0.0, 0.0), (1.0, 0.0))],
+            &[((0, 0), Some(0)), ((0, 1), Some(0))],
+        );
+        let b: RunSnapshot = snapshot(
+            vec![
+                corridor(7, 1, (0.0, 0.0), (1.0, 0.0)),
+                corridor(3, 1, (0.0, 0.0), (1.0, 0.0)),
+            ],
+            &[((0, 0), Some(7)), ((0, 1), Some(3))],
+        );
+        let report: ComparisonReport = compare_runs(&a, &b);
+
+        assert_eq!(report.corridor_matches[0].corridor_b, 3);
+        assert_eq!(report.unmatched_corridors_b, vec![7]);
+    }
+
+    #[test]
+    fn corridors_without_shared_segments_are_unmatched() {
+        let a: RunSnapshot = snapshot(
+            vec![
+                corridor(0, 1, (0.0, 0.0), (1.0, 0.0)),
+                corridor(1, 1, (5.0, 5.0), (6.0, 5.0)),
+            ],
+            &[((0, 0), Some(0)), ((1, 0), Some(1))],
+        );
+        let b: RunSnapshot = snapshot(
+            vec![corridor(0, 1, (0.0, 0.0), (1.0, 0.0))],
+            &[((0, 0), Some(0)), ((1, 0), None)],
+        );
+        let report: ComparisonReport = compare_runs(&a, &b);
+
+        assert_eq!(report.corridor_matches.len(), 1);
+        assert_eq!(report.unmatched_corridors_a, vec![1]);
+        assert!(report.unmatched_corridors_b.is_empty());
+    }
+
+    #[test]
+    fn empty_runs_count_as_identical() {
+        let report: ComparisonReport =
+            compare_runs(&RunSnapshot::default(), &RunSnapshot::default());
+        assert_eq!(report.matched_segments, 0);
+        assert_eq!(report.consistency_share(), 1.0);
+        assert_eq!(report.clustered_similarity(), 1.0);
+    }
+}
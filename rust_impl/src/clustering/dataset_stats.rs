@@ -0,0 +1,295 @@
+// dataset_stats.rs - Statistics of a loaded input, to check it before a run
+//
+// Computed from the RawTrajectories the clustering would use, so the angle histogram is the
+// bucket layout of that max_angle. The largest bucket bounds how well ParallelRayonTraclusDL
+// spreads its work: one bucket is processed by one task.
+
+use std::collections::HashMap;
+
+use super::geometry::point::Point;
+use super::geometry::trajectory::Trajectory;
+use super::storage::raw_trajectories::RawTrajectories;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+/// Line lengths along all their legs, in input units.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LengthDistribution {
+    pub min: f64,
+    pub p25: f64,
+    pub median: f64,
+    pub p75: f64,
+    pub p90: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BucketStats {
+    pub index: usize,
+    pub angle_start: f64,
+    pub angle_end: f64,
+    pub lines: usize, // legs in the bucket, one per straight line
+    pub weight: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SegmentCount {
+    pub segment_size: f64,
+    pub segments: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct DatasetStats {
+    pub lines: usize,
+    pub total_weight: u64,
+    pub bounding_box: Option<BoundingBox>, // None without any finite line
+    pub lengths: LengthDistribution,
+    pub buckets: Vec<BucketStats>,
+    pub segment_counts: Vec<SegmentCount>,
+    pub zero_length_lines: usize,
+    pub non_finite_lines: usize, // NaN or infinite coordinate
+    pub duplicate_lines: usize,  // lines repeating the vertices of an earlier line
+    pub duplicate_pairs: usize,  // distinct OD pairs (vertex sequences) given more than once
+}
+
+impl DatasetStats {
+    pub fn from_raw_storage(raw_storage: &RawTrajectories, segment_sizes: &[f64]) -> Self {
+        let trajectories: Vec<&Trajectory> = raw_storage.trajectories.iter().collect();
+
+        let is_finite = |traj: &&Trajectory| {
+            traj.points()
+                .iter()
+                .all(|p| p.x.is_finite() && p.y.is_finite())
+        };
+        let finite: Vec<&Trajectory> = trajectories.iter().copied().filter(is_finite).collect();
+        let lengths: Vec<f64> = finite
+            .iter()
+            .map(|traj| traj.get_spatial_length())
+            .collect();
+
+        let buckets: Vec<BucketStats> = raw_storage
+            .traj_buckets
+            .iter()
+            .enumerate()
+            .map(|(index, bucket)| BucketStats {
+                index,
+                angle_start: bucket.angle_start,
+                angle_end: bucket.angle_end,
+                lines: bucket.legs.len(),
+                weight: raw_storage
+                    .bucket_legs(bucket)
+                    .map(|(traj, _)| traj.weight as u64)
+                    .sum(),
+            })
+            .collect();
+
+        let segment_counts: Vec<SegmentCount> = segment_sizes
+            .iter()
+            .map(|&segment_size| SegmentCount {
+                segment_size,
+                segments: finite
+                    .iter()
+                    .flat_map(|traj| traj.legs().iter())
+                    .map(|leg| Trajectory::segment_count(leg.get_spatial_length(), segment_size))
+                    .sum(),
+            })
+            .collect();
+
+        // Exact coordinates, as the clustering sees them
+        let mut od_pairs: HashMap<Vec<u64>, usize> = HashMap::new();
+        for traj in &trajectories {
+            let key: Vec<u64> = traj
+                .points()
+                .iter()
+                .flat_map(|p| [p.x.to_bits(), p.y.to_bits()])
+                .collect();
+            *od_pairs.entry(key).or_default() += 1;
+        }
+
+        Self {
+            lines: trajectories.len(),
+            total_weight: trajectories.iter().map(|t| t.weight as u64).sum(),
+            bounding_box: bounding_box(&finite),
+            lengths: length_distribution(lengths.clone()),
+            buckets,
+            segment_counts,
+            zero_length_lines: lengths.iter().filter(|&&length| length == 0.0).count(),
+            non_finite_lines: trajectories.len() - finite.len(),
+            duplicate_lines: od_pairs.values().map(|&count| count - 1).sum(),
+            duplicate_pairs: od_pairs.values().filter(|&&count| count > 1).count(),
+        }
+    }
+
+    /// Bucket with the most lines, the first one on a tie.
+    pub fn largest_bucket(&self) -> Option<&BucketStats> {
+        self.buckets
+            .iter()
+            .rev()
+            .max_by_key(|bucket| bucket.lines)
+            .filter(|bucket| bucket.lines > 0)
+    }
+
+    /// Legs of the largest bucket over the legs of all buckets.
+    pub fn largest_bucket_share(&self) -> f64 {
+        let total: usize = self.buckets.iter().map(|bucket| bucket.lines).sum();
+        match self.largest_bucket() {
+            Some(bucket) => bucket.lines as f64 / total as f64,
+            None => 0.0,
+        }
+    }
+}
+
+fn bounding_box(trajectories: &[&Trajectory]) -> Option<BoundingBox> {
+    let mut points = trajectories.iter().flat_map(|traj| traj.points());
+    let first: Point = points.next()?;
+    Some(points.fold(
+        BoundingBox {
+            min: first,
+            max: first,
+        },
+        |bbox, p| BoundingBox {
+            min: Point {
+                x: bbox.min.x.min(p.x),
+                y: bbox.min.y.min(p.y),
+            },
+            max: Point {
+                x: bbox.max.x.max(p.x),
+                y: bbox.max.y.max(p.y),
+            },
+        },
+    ))
+}
+
+fn length_distribution(mut lengths: Vec<f64>) -> LengthDistribution {
+    if lengths.is_empty() {
+        return LengthDistribution::default();
+    }
+    lengths.sort_by(f64::total_cmp);
+
+    // Nearest rank
+    let quantile = |q: f64| -> f64 {
+        let rank: usize = (q * lengths.len() as f64).ceil() as usize;
+        lengths[rank.clamp(1, lengths.len()) - 1]
+    };
+    LengthDistribution {
+        min: lengths[0],
+        p25: quantile(0.25),
+        median: quantile(0.5),
+        p75: quantile(0.75),
+        p90: quantile(0.9),
+        max: lengths[lengths.len() - 1],
+        mean: lengths.iter().sum::<f64>() / lengths.len() as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::geometry::input_od_line::InputODLine;
+
+    // weight, start, end
+    type Line = (u32, (f64, f64), (f64, f64));
+
+    fn storage_of(lines: &[Line]) -> RawTrajectories {
+        let mut raw_storage: RawTrajectories = RawTrajectories::new(90.0, false);
+        for (index, &(weight, start, end)) in lines.iter().enumerate() {
+            let input: InputODLine = InputODLine {
+                line_id: index + 1,
+                weight,
+                start: Point {
+                    x: start.0,
+                    y: start.1,
+                },
+                end: Point { x: end.0, y: end.1 },
+                via: Vec::new(),
+            };
+            raw_storage.add_trajectory(Trajectory::new(input, 10.0));
+        }
+        raw_storage
+    }
+
+    #[test]
+    fn duplicate_od_pairs_are_counted_once_per_pair() {
+        let raw_storage: RawTrajectories = storage_of(&[
+            (1, (0.0, 0.0), (10.0, 0.0)),
+            (2, (0.0, 0.0), (10.0, 0.0)),
+            (1, (0.0, 0.0), (10.0, 0.0)),
+            (1, (5.0, 5.0), (5.0, 25.0)),
+            (1, (5.0, 5.0), (5.0, 25.0)),
+            (1, (10.0, 0.0), (0.0, 0.0)), // reverse direction is another pair
+        ]);
+        let stats: DatasetStats = DatasetStats::from_raw_storage(&raw_storage, &[]);
+
+        assert_eq!(stats.lines, 6);
+        assert_eq!(stats.total_weight, 7);
+        assert_eq!(stats.duplicate_lines, 3);
+        assert_eq!(stats.duplicate_pairs, 2);
+    }
+
+    #[test]
+    fn degenerate_lines_are_reported() {
+        let raw_storage: RawTrajectories = storage_of(&[
+            (1, (0.0, 0.0), (30.0, 0.0)),
+            (1, (4.0, 4.0), (4.0, 4.0)),
+            (1, (f64::NAN, 0.0), (1.0, 1.0)),
+        ]);
+        let stats: DatasetStats = DatasetStats::from_raw_storage(&raw_storage, &[10.0]);
+
+        assert_eq!(stats.zero_length_lines, 1);
+        assert_eq!(stats.non_finite_lines, 1);
+        // The non-finite line is left out of the box and of the lengths
+        let bbox: BoundingBox = stats.bounding_box.unwrap();
+        assert_eq!(
+            (bbox.min.x, bbox.min.y, bbox.max.x, bbox.max.y),
+            (0.0, 0.0, 30.0, 4.0)
+        );
+        assert_eq!((stats.lengths.min, stats.lengths.max), (0.0, 30.0));
+        assert_eq!(
+            stats.segment_counts[0].segments,
+            3 + Trajectory::segment_count(0.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn lengths_use_the_nearest_rank() {
+        let lines: Vec<Line> = (1..=10)
+            .map(|i| (1, (0.0, 0.0), (i as f64 * 10.0, 0.0)))
+            .collect();
+        let stats: DatasetStats = DatasetStats::from_raw_storage(&storage_of(&lines), &[]);
+
+        assert_eq!(stats.lengths.p25, 30.0);
+        assert_eq!(stats.lengths.median, 50.0);
+        assert_eq!(stats.lengths.p75, 80.0);
+        assert_eq!(stats.lengths.p90, 90.0);
+        assert_eq!(stats.lengths.mean, 55.0);
+    }
+
+    #[test]
+    fn largest_bucket_holds_the_most_lines() {
+        let raw_storage: RawTrajectories = storage_of(&[
+            (1, (0.0, 0.0), (10.0, -1.0)),
+            (3, (0.0, 0.0), (1.0, 10.0)),
+            (1, (0.0, 0.0), (2.0, 10.0)),
+            (1, (0.0, 0.0), (-10.0, 1.0)),
+        ]);
+        let stats: DatasetStats = DatasetStats::from_raw_storage(&raw_storage, &[]);
+
+        assert_eq!(stats.buckets.len(), 4);
+        let largest: &BucketStats = stats.largest_bucket().unwrap();
+        assert_eq!((largest.lines, largest.weight), (2, 4));
+        assert_eq!(stats.largest_bucket_share(), 0.5);
+    }
+
+    #[test]
+    fn empty_input_has_no_box_and_no_largest_bucket() {
+        let stats: DatasetStats = DatasetStats::from_raw_storage(&storage_of(&[]), &[]);
+        assert!(stats.bounding_box.is_none());
+        assert!(stats.largest_bucket().is_none());
+        assert_eq!(stats.largest_bucket_share(), 0.0);
+    }
+}
@@ -6,4 +6,14 @@ pub struct InputODLine {
     pub weight: u32,
     pub start: Point,
     pub end: Point,
+    pub via: Vec<Point>, // vertices between start and end, empty for a straight line
+}
+
+impl InputODLine {
+    /// Vertices of the line in order: start, via, end.
+    pub fn points(&self) -> impl Iterator<Item = &Point> {
+        std::iter::once(&self.start)
+            .chain(self.via.iter())
+            .chain(std::iter::once(&self.end))
+    }
 }
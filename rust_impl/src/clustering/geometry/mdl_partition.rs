@@ -0,0 +1,212 @@
+// mdl_partition.rs - Characteristic points of a polyline by minimum description length
+//
+// Approximate partitioning of TRACLUS (Lee, Han & Whang, 2007, section 4.1): the polyline is
+// walked from the last characteristic point, and the vertex before the current one becomes
+// characteristic as soon as describing the stretch by a single segment costs more bits than
+// keeping its legs. A straight stretch therefore stays one segment, a turn starts a new one.
+//
+// Costs in bits, for the hypothesis segment H from the last characteristic point:
+//   MDL_par   = L(H) + cost_weight * L(D|H)   with L(D|H) = Σ log2(d⊥) + log2(dθ)
+//   MDL_nopar = Σ log2(leg length)
+// both sums running over the legs the hypothesis replaces.
+// A value of 1 or less counts 0 bits, so coordinates are expected in projected units.
+
+use super::point::Point;
+
+/// Indexes in `points` of the characteristic points, first and last point included.
+/// A characteristic point is never placed closer than `min_segment_length` to the previous
+/// one; only the last segment, ending on the last point, can be shorter.
+pub fn characteristic_points(
+    points: &[Point],
+    cost_weight: f64,
+    min_segment_length: f64,
+) -> Vec<usize> {
+    if points.len() <= 2 {
+        return (0..points.len()).collect();
+    }
+
+    let mut result: Vec<usize> = vec![0];
+    let mut start: usize = 0;
+    let mut length: usize = 1;
+
+    while start + length < points.len() {
+        let current: usize = start + length;
+        let cost_par: f64 = mdl_par(points, start, current, cost_weight);
+        let cost_nopar: f64 = mdl_nopar(points, start, current);
+
+        let candidate: usize = current - 1;
+        let is_long_enough: bool =
+            distance(&points[start], &points[candidate]) >= min_segment_length;
+        if cost_par > cost_nopar && candidate > start && is_long_enough {
+            result.push(candidate);
+            start = candidate;
+            length = 1;
+        } else {
+            length += 1;
+        }
+    }
+
+    result.push(points.len() - 1);
+    result
+}
+
+// Bits to describe a value, 0 for values that do not need any
+fn bits(value: f64) -> f64 {
+    if value > 1.0 { value.log2() } else { 0.0 }
+}
+
+fn mdl_par(points: &[Point], start: usize, end: usize, cost_weight: f64) -> f64 {
+    let (h_start, h_end): (&Point, &Point) = (&points[start], &points[end]);
+
+    let error: f64 = points[start..=end]
+        .windows(2)
+        .map(|leg| {
+            bits(perpendicular_distance(h_start, h_end, &leg[0], &leg[1]))
+                + bits(angle_distance(h_start, h_end, &leg[0], &leg[1]))
+        })
+        .sum();
+
+    bits(distance(h_start, h_end)) + cost_weight * error
+}
+
+fn mdl_nopar(points: &[Point], start: usize, end: usize) -> f64 {
+    points[start..=end]
+        .windows(2)
+        .map(|leg| bits(distance(&leg[0], &leg[1])))
+        .sum()
+}
+
+fn distance(a: &Point, b: &Point) -> f64 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+// Distance from `p` to the infinite line through `a` and `b`
+fn line_distance(a: &Point, b: &Point, p: &Point) -> f64 {
+    let length: f64 = distance(a, b);
+    if length == 0.0 {
+        return distance(a, p);
+    }
+    ((b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)).abs() / length
+}
+
+// d⊥ of TRACLUS: Lehmer mean of the distances of the leg ends to the hypothesis line
+fn perpendicular_distance(h_start: &Point, h_end: &Point, start: &Point, end: &Point) -> f64 {
+    let l1: f64 = line_distance(h_start, h_end, start);
+    let l2: f64 = line_distance(h_start, h_end, end);
+    if l1 + l2 == 0.0 {
+        0.0
+    } else {
+        (l1 * l1 + l2 * l2) / (l1 + l2)
+    }
+}
+
+// dθ of TRACLUS: leg length times the sine of the angle to the hypothesis,
+// the full leg length from 90° on
+fn angle_distance(h_start: &Point, h_end: &Point, start: &Point, end: &Point) -> f64 {
+    let (hx, hy): (f64, f64) = (h_end.x - h_start.x, h_end.y - h_start.y);
+    let (lx, ly): (f64, f64) = (end.x - start.x, end.y - start.y);
+    let leg_length: f64 = lx.hypot(ly);
+    let h_length: f64 = hx.hypot(hy);
+    if leg_length == 0.0 || h_length == 0.0 {
+        return 0.0;
+    }
+
+    let cos: f64 = (hx * lx + hy * ly) / (h_length * leg_length);
+    if cos <= 0.0 {
+        leg_length
+    } else {
+        leg_length * (1.0 - cos * cos).max(0.0).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seeded_rng::SeededRng;
+
+    fn polyline(vertices: &[(f64, f64)]) -> Vec<Point> {
+        vertices.iter().map(|&(x, y)| Point { x, y }).collect()
+    }
+
+    // Three legs east, then three legs north
+    fn right_angle() -> Vec<Point> {
+        polyline(&[
+            (0.0, 0.0),
+            (100.0, 0.0),
+            (200.0, 0.0),
+            (300.0, 0.0),
+            (300.0, 100.0),
+            (300.0, 200.0),
+            (300.0, 300.0),
+        ])
+    }
+
+    #[test]
+    fn short_polylines_keep_every_point() {
+        assert_eq!(characteristic_points(&[], 1.0, 0.0), Vec::<usize>::new());
+        assert_eq!(
+            characteristic_points(&polyline(&[(0.0, 0.0)]), 1.0, 0.0),
+            vec![0]
+        );
+        let points: Vec<Point> = polyline(&[(0.0, 0.0), (500.0, 0.0)]);
+        assert_eq!(characteristic_points(&points, 1.0, 0.0), vec![0, 1]);
+    }
+
+    #[test]
+    fn straight_polyline_is_a_single_segment() {
+        let points: Vec<Point> = (0..10)
+            .map(|i| Point {
+                x: i as f64 * 100.0,
+                y: 0.0,
+            })
+            .collect();
+        assert_eq!(characteristic_points(&points, 1.0, 0.0), vec![0, 9]);
+    }
+
+    #[test]
+    fn turn_starts_a_new_segment() {
+        assert_eq!(
+            characteristic_points(&right_angle(), 1.0, 0.0),
+            vec![0, 3, 6]
+        );
+    }
+
+    #[test]
+    fn zero_cost_weight_never_partitions() {
+        // Without the error term a single segment is always cheaper than its legs
+        assert_eq!(characteristic_points(&right_angle(), 0.0, 0.0), vec![0, 6]);
+    }
+
+    #[test]
+    fn min_segment_length_holds_back_close_points() {
+        assert_eq!(
+            characteristic_points(&right_angle(), 1.0, 1000.0),
+            vec![0, 6]
+        );
+    }
+
+    #[test]
+    fn points_are_increasing_and_spaced_by_the_min_length() {
+        let mut rng: SeededRng = SeededRng::new(3);
+        for min_segment_length in [0.0, 150.0, 400.0] {
+            for _ in 0..50 {
+                let num_points: usize = 3 + (rng.next_u64() % 30) as usize;
+                let points: Vec<Point> = (0..num_points)
+                    .map(|_| Point {
+                        x: rng.uniform(-1000.0, 1000.0),
+                        y: rng.uniform(-1000.0, 1000.0),
+                    })
+                    .collect();
+                let cps: Vec<usize> = characteristic_points(&points, 1.0, min_segment_length);
+
+                assert_eq!(cps.first(), Some(&0));
+                assert_eq!(cps.last(), Some(&(num_points - 1)));
+                assert!(cps.windows(2).all(|pair| pair[0] < pair[1]));
+                // Only the last segment may be shorter
+                for pair in cps[..cps.len() - 1].windows(2) {
+                    assert!(distance(&points[pair[0]], &points[pair[1]]) >= min_segment_length);
+                }
+            }
+        }
+    }
+}
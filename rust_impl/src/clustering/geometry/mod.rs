@@ -1,4 +1,6 @@
 pub mod point;
 pub mod input_od_line;
 pub mod trajectory;
-pub mod segment;
\ No newline at end of file
+pub mod segment;
+pub mod mdl_partition;
+pub mod projection;
\ No newline at end of file
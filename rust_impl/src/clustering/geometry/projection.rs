@@ -0,0 +1,272 @@
+// projection.rs - Transverse Mercator zones between longitude/latitude and metres
+//
+// Krüger's series to the fourth order in n (Karney, 2011, "Transverse Mercator with an
+// accuracy of a few nanometers"), accurate to well under a millimetre within a few thousand
+// kilometres of the central meridian: UTM and MTM zones lie far inside that.
+// Longitude/latitude points hold the longitude in x and the latitude in y, in degrees.
+// NAD83, the datum of MTM, is taken as WGS84: they differ by about a metre in Quebec.
+
+use std::fmt;
+
+use super::point::Point;
+
+// Semi-major axis (m) and flattening
+const WGS84: (f64, f64) = (6_378_137.0, 1.0 / 298.257_223_563);
+const GRS80: (f64, f64) = (6_378_137.0, 1.0 / 298.257_222_101);
+
+/// Metric CRS of the clustering for longitude/latitude input.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MetricZone {
+    /// WGS 84 / UTM zone 1 to 60, north or south hemisphere
+    Utm { zone: u8, north: bool },
+    /// NAD83 / MTM zone 8, Montreal
+    Mtm8,
+}
+
+impl MetricZone {
+    /// UTM zone holding `lon_lat`; the Norway and Svalbard exceptions are ignored.
+    pub fn utm_at(lon_lat: &Point) -> Self {
+        let zone: f64 = ((lon_lat.x + 180.0) / 6.0).floor() + 1.0;
+        MetricZone::Utm {
+            zone: zone.clamp(1.0, 60.0) as u8,
+            north: lon_lat.y >= 0.0,
+        }
+    }
+
+    pub fn epsg(&self) -> u32 {
+        match self {
+            MetricZone::Utm { zone, north: true } => 32600 + *zone as u32,
+            MetricZone::Utm { zone, north: false } => 32700 + *zone as u32,
+            MetricZone::Mtm8 => 32188,
+        }
+    }
+
+    /// Longitude of the central meridian, in degrees.
+    pub fn central_meridian(&self) -> f64 {
+        match self {
+            MetricZone::Utm { zone, .. } => *zone as f64 * 6.0 - 183.0,
+            MetricZone::Mtm8 => -73.5,
+        }
+    }
+}
+
+impl fmt::Display for MetricZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricZone::Utm { zone, north } => {
+                let hemisphere: char = if *north { 'N' } else { 'S' };
+                write!(f, "UTM zone {}{} (EPSG:{})", zone, hemisphere, self.epsg())
+            }
+            MetricZone::Mtm8 => write!(f, "MTM zone 8 (EPSG:{})", self.epsg()),
+        }
+    }
+}
+
+/// Transverse Mercator transform of a zone, with its series coefficients.
+#[derive(Copy, Clone, Debug)]
+pub struct Projection {
+    pub zone: MetricZone,
+    central_meridian: f64, // radians
+    false_easting: f64,
+    false_northing: f64,
+    eccentricity: f64,
+    radius: f64, // scale factor times the rectifying radius A
+    alpha: [f64; 4],
+    beta: [f64; 4],
+    delta: [f64; 4],
+}
+
+impl Projection {
+    pub fn new(zone: MetricZone) -> Self {
+        let ((a, f), scale, false_easting, false_northing): ((f64, f64), f64, f64, f64) = match zone
+        {
+            MetricZone::Utm { north, .. } => (
+                WGS84,
+                0.9996,
+                500_000.0,
+                if north { 0.0 } else { 10_000_000.0 },
+            ),
+            MetricZone::Mtm8 => (GRS80, 0.9999, 304_800.0, 0.0),
+        };
+
+        let n: f64 = f / (2.0 - f);
+        let (n2, n3, n4): (f64, f64, f64) = (n * n, n * n * n, n * n * n * n);
+
+        Self {
+            zone,
+            central_meridian: zone.central_meridian().to_radians(),
+            false_easting,
+            false_northing,
+            eccentricity: (f * (2.0 - f)).sqrt(),
+            radius: scale * a / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0),
+            alpha: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0,
+                13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0,
+                61.0 * n3 / 240.0 - 103.0 * n4 / 140.0,
+                49561.0 * n4 / 161280.0,
+            ],
+            beta: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0,
+                n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0,
+                17.0 * n3 / 480.0 - 37.0 * n4 / 840.0,
+                4397.0 * n4 / 161280.0,
+            ],
+            delta: [
+                2.0 * n - 2.0 * n2 / 3.0 - 2.0 * n3 + 116.0 * n4 / 45.0,
+                7.0 * n2 / 3.0 - 8.0 * n3 / 5.0 - 227.0 * n4 / 45.0,
+                56.0 * n3 / 15.0 - 136.0 * n4 / 35.0,
+                4279.0 * n4 / 630.0,
+            ],
+        }
+    }
+
+    /// Easting and northing of `lon_lat`, in metres.
+    pub fn forward(&self, lon_lat: &Point) -> Point {
+        let lat: f64 = lon_lat.y.to_radians();
+        let d_lon: f64 = lon_lat.x.to_radians() - self.central_meridian;
+
+        // Conformal latitude, as its tangent
+        let e: f64 = self.eccentricity;
+        let t: f64 = (lat.sin().atanh() - e * (e * lat.sin()).atanh()).sinh();
+        let xi_prime: f64 = t.atan2(d_lon.cos());
+        let eta_prime: f64 = (d_lon.sin() / (1.0 + t * t).sqrt()).atanh();
+
+        let mut xi: f64 = xi_prime;
+        let mut eta: f64 = eta_prime;
+        for (j, alpha) in self.alpha.iter().enumerate() {
+            let k: f64 = 2.0 * (j + 1) as f64;
+            xi += alpha * (k * xi_prime).sin() * (k * eta_prime).cosh();
+            eta += alpha * (k * xi_prime).cos() * (k * eta_prime).sinh();
+        }
+
+        Point {
+            x: self.false_easting + self.radius * eta,
+            y: self.false_northing + self.radius * xi,
+        }
+    }
+
+    /// Longitude and latitude of `point`, in degrees.
+    pub fn inverse(&self, point: &Point) -> Point {
+        let xi: f64 = (point.y - self.false_northing) / self.radius;
+        let eta: f64 = (point.x - self.false_easting) / self.radius;
+
+        let mut xi_prime: f64 = xi;
+        let mut eta_prime: f64 = eta;
+        for (j, beta) in self.beta.iter().enumerate() {
+            let k: f64 = 2.0 * (j + 1) as f64;
+            xi_prime -= beta * (k * xi).sin() * (k * eta).cosh();
+            eta_prime -= beta * (k * xi).cos() * (k * eta).sinh();
+        }
+
+        let chi: f64 = (xi_prime.sin() / eta_prime.cosh()).asin();
+        let mut lat: f64 = chi;
+        for (j, delta) in self.delta.iter().enumerate() {
+            lat += delta * (2.0 * (j + 1) as f64 * chi).sin();
+        }
+        let d_lon: f64 = eta_prime.sinh().atan2(xi_prime.cos());
+
+        Point {
+            x: (self.central_meridian + d_lon).to_degrees(),
+            y: lat.to_degrees(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seeded_rng::SeededRng;
+
+    // Meridian arc of WGS84 from the equator to 45°, in metres
+    const MERIDIAN_ARC_45: f64 = 4_984_944.378;
+
+    fn lon_lat(lon: f64, lat: f64) -> Point {
+        Point { x: lon, y: lat }
+    }
+
+    #[test]
+    fn utm_zone_of_montreal() {
+        let zone: MetricZone = MetricZone::utm_at(&lon_lat(-73.57, 45.5));
+        assert_eq!(
+            zone,
+            MetricZone::Utm {
+                zone: 18,
+                north: true
+            }
+        );
+        assert_eq!(zone.epsg(), 32618);
+        assert_eq!(zone.central_meridian(), -75.0);
+
+        let zone: MetricZone = MetricZone::utm_at(&lon_lat(151.2, -33.9));
+        assert_eq!(zone.epsg(), 32756);
+        assert_eq!(MetricZone::utm_at(&lon_lat(180.0, 0.0)).epsg(), 32660);
+    }
+
+    #[test]
+    fn central_meridian_maps_to_the_false_easting() {
+        let utm: Projection = Projection::new(MetricZone::Utm {
+            zone: 18,
+            north: true,
+        });
+        let origin: Point = utm.forward(&lon_lat(-75.0, 0.0));
+        assert!((origin.x - 500_000.0).abs() < 1e-6 && origin.y.abs() < 1e-6);
+
+        let at_45: Point = utm.forward(&lon_lat(-75.0, 45.0));
+        assert!((at_45.x - 500_000.0).abs() < 1e-6);
+        assert!((at_45.y - 0.9996 * MERIDIAN_ARC_45).abs() < 0.01);
+
+        let mtm8: Projection = Projection::new(MetricZone::Mtm8);
+        let at_45: Point = mtm8.forward(&lon_lat(-73.5, 45.0));
+        assert!((at_45.x - 304_800.0).abs() < 1e-6);
+        assert!((at_45.y - 0.9999 * MERIDIAN_ARC_45).abs() < 0.01);
+    }
+
+    #[test]
+    fn southern_zones_add_the_false_northing() {
+        let utm: Projection = Projection::new(MetricZone::Utm {
+            zone: 56,
+            north: false,
+        });
+        let at_45: Point = utm.forward(&lon_lat(153.0, -45.0));
+        assert!((at_45.y - (10_000_000.0 - 0.9996 * MERIDIAN_ARC_45)).abs() < 0.01);
+    }
+
+    #[test]
+    fn forward_and_inverse_round_trip() {
+        let zones: [MetricZone; 3] = [
+            MetricZone::Utm {
+                zone: 18,
+                north: true,
+            },
+            MetricZone::Utm {
+                zone: 56,
+                north: false,
+            },
+            MetricZone::Mtm8,
+        ];
+        let mut rng: SeededRng = SeededRng::new(5);
+
+        for zone in zones {
+            let projection: Projection = Projection::new(zone);
+            let (min_lat, max_lat): (f64, f64) = match zone {
+                MetricZone::Utm { north: false, .. } => (-80.0, 0.0),
+                _ => (0.0, 84.0),
+            };
+
+            for _ in 0..1000 {
+                let lon: f64 = zone.central_meridian() + rng.uniform(-3.0, 3.0);
+                let point: Point = lon_lat(lon, rng.uniform(min_lat, max_lat));
+
+                let projected: Point = projection.forward(&point);
+                let back: Point = projection.inverse(&projected);
+                // 1e-9° is about 0.1 mm
+                assert!((back.x - point.x).abs() < 1e-9, "{:?} -> {:?}", point, back);
+                assert!((back.y - point.y).abs() < 1e-9, "{:?} -> {:?}", point, back);
+
+                let again: Point = projection.forward(&back);
+                assert!((again.x - projected.x).abs() < 1e-4);
+                assert!((again.y - projected.y).abs() < 1e-4);
+            }
+        }
+    }
+}
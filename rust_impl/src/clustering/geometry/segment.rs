@@ -5,15 +5,21 @@ pub struct Segment {
     pub id: usize,
     pub start: Point,
     pub middle: Point,
+    pub angle: f64, // angle of the leg the segment belongs to
 }
 
 impl Segment {
-    pub fn new(id: usize, start: Point, end: Point) -> Self {
+    pub fn new(id: usize, start: Point, end: Point, angle: f64) -> Self {
         let middle: Point = Point {
             x: (start.x + end.x) / 2.0,
             y: (start.y + end.y) / 2.0,
         };
-        Self { id, start, middle }
+        Self {
+            id,
+            start,
+            middle,
+            angle,
+        }
     }
 
     pub fn get_end(&self) -> Point {
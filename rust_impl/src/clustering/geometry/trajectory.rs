@@ -1,34 +1,95 @@
 use std::f64::consts::PI;
+use std::ops::Range;
 
 use super::input_od_line::InputODLine;
+use super::mdl_partition::characteristic_points;
 use super::point::Point;
 use super::segment::Segment;
 
+/// Straight part of a trajectory between two consecutive vertices, or between two
+/// characteristic points with the MDL partitioning.
+/// A straight origin–destination line has a single leg.
+#[derive(Debug)]
+pub struct Leg {
+    pub index: usize,
+    pub start: Point,
+    pub end: Point,
+    pub angle: f64,
+    segments: Range<usize>, // indexes in the segments of the trajectory
+}
+
+impl Leg {
+    pub fn get_spatial_length(&self) -> f64 {
+        let dx: f64 = self.end.x - self.start.x;
+        let dy: f64 = self.end.y - self.start.y;
+        (dx * dx + dy * dy).sqrt()
+    }
+}
+
+/// Smallest difference in degrees between the angles `a` and `b`, across 0/360, or across
+/// 0/180 when `undirected`: opposite directions are then the same axis.
+pub fn angle_difference(a: f64, b: f64, undirected: bool) -> f64 {
+    let period: f64 = if undirected { 180.0 } else { 360.0 };
+    let diff: f64 = (a - b).abs() % period;
+    diff.min(period - diff)
+}
+
 #[derive(Debug)]
 pub struct Trajectory {
     pub id: usize,
     pub start: Point,
     pub end: Point,
     pub weight: u32,
-    pub angle: f64,
+    pub angle: f64, // start→end direction; the clustering uses the angle of each leg
+    legs: Vec<Leg>,
     segments: Vec<Segment>,
 }
 
 impl Trajectory {
+    /// Trajectory whose legs are cut into segments of `seg_size`.
     pub fn new(input: InputODLine, seg_size: f64) -> Self {
+        let mut traj: Trajectory = Self::from_vertices(input);
+        traj.make_segments(seg_size);
+        traj
+    }
+
+    /// Trajectory whose legs join its MDL characteristic points, one segment per leg.
+    pub fn new_mdl(input: InputODLine, cost_weight: f64, min_segment_length: f64) -> Self {
+        let mut traj: Trajectory = Self::from_vertices(input);
+        traj.make_mdl_segments(cost_weight, min_segment_length);
+        traj
+    }
+
+    // One leg per pair of consecutive vertices, no segment yet
+    fn from_vertices(input: InputODLine) -> Self {
         let angle: f64 = Self::get_spatial_angle(&input.start, &input.end);
 
-        let mut traj: Trajectory = Self {
+        // Repeated vertices would make legs without length nor direction
+        let mut legs: Vec<Leg> = Vec::new();
+        let mut previous: &Point = &input.start;
+        for point in input.points().skip(1) {
+            if point.x == previous.x && point.y == previous.y {
+                continue;
+            }
+            legs.push(Leg {
+                index: legs.len(),
+                start: *previous,
+                end: *point,
+                angle: Self::get_spatial_angle(previous, point),
+                segments: 0..0,
+            });
+            previous = point;
+        }
+
+        Self {
             id: input.line_id,
             start: input.start,
             end: input.end,
             weight: input.weight,
             angle,
+            legs,
             segments: Vec::new(),
-        };
-
-        traj.make_segments(seg_size);
-        traj
+        }
     }
 
     fn get_spatial_angle(start: &Point, end: &Point) -> f64 {
@@ -44,23 +105,26 @@ impl Trajectory {
         angle
     }
 
-    fn get_spatial_length(&self) -> f64 {
-        let dx: f64 = self.end.x - self.start.x;
-        let dy: f64 = self.end.y - self.start.y;
-        (dx * dx + dy * dy).sqrt()
+    /// Length along all the legs.
+    pub fn get_spatial_length(&self) -> f64 {
+        self.legs.iter().map(Leg::get_spatial_length).sum()
     }
 
-    pub fn distance_to_point(&self, point: &Point) -> (f64, usize) {
+    /// Distance from `point` to `leg`, and the segment of the leg holding the closest point.
+    /// The segment is None only for a leg too short to be cut into one.
+    pub fn distance_to_point(&self, leg: &Leg, point: &Point) -> (f64, Option<&Segment>) {
         let px: f64 = point.x;
         let py: f64 = point.y;
-        let x1: f64 = self.start.x;
-        let y1: f64 = self.start.y;
-        let dx: f64 = self.end.x - self.start.x;
-        let dy: f64 = self.end.y - self.start.y;
+        let x1: f64 = leg.start.x;
+        let y1: f64 = leg.start.y;
+        let dx: f64 = leg.end.x - leg.start.x;
+        let dy: f64 = leg.end.y - leg.start.y;
+
+        let leg_segments: &[Segment] = self.leg_segments(leg);
 
         if dx == 0.0 && dy == 0.0 {
             let min_distance: f64 = ((px - x1).powi(2) + (py - y1).powi(2)).sqrt();
-            return (min_distance, 0);
+            return (min_distance, leg_segments.first());
         }
 
         let t: f64 = ((px - x1) * dx + (py - y1) * dy) / (dx * dx + dy * dy);
@@ -72,58 +136,242 @@ impl Trajectory {
         };
 
         let min_distance: f64 = ((px - near.x).powi(2) + (py - near.y).powi(2)).sqrt();
-        let traj_length: f64 = self.get_spatial_length();
-        let seg_length: f64 = self.segments.first().unwrap().get_length();
+        let Some(first_segment) = leg_segments.first() else {
+            return (min_distance, None);
+        };
+        let leg_length: f64 = leg.get_spatial_length();
+        let seg_length: f64 = first_segment.get_length();
 
-        let index_seg: usize = (t * (traj_length / seg_length)) as usize;
+        // t = 1 on a length multiple of seg_length would point past the last segment
+        let index_seg: usize =
+            ((t * (leg_length / seg_length)) as usize).min(leg_segments.len() - 1);
 
-        (min_distance, index_seg)
+        (min_distance, Some(&leg_segments[index_seg]))
     }
 
+    /// Cuts every leg into segments of `segment_length`, numbered along the whole trajectory.
     pub fn make_segments(&mut self, segment_length: f64) {
         self.segments.clear();
 
-        let angle_rad: f64 = self.angle * PI / 180.0;
-        let xstep: f64 = segment_length * angle_rad.cos();
-        let ystep: f64 = segment_length * angle_rad.sin();
+        for leg in &mut self.legs {
+            let first_segment: usize = self.segments.len();
+
+            let angle_rad: f64 = leg.angle * PI / 180.0;
+            let xstep: f64 = segment_length * angle_rad.cos();
+            let ystep: f64 = segment_length * angle_rad.sin();
+
+            let length: f64 = leg.get_spatial_length();
 
-        let length: f64 = self.get_spatial_length();
+            let nsegs: usize = Self::segment_count(length, segment_length);
+            let base_x: f64 = leg.start.x;
+            let base_y: f64 = leg.start.y;
 
-        let nsegs: usize = ((length / segment_length) - 1e-9).ceil() as usize;
-        let base_x: f64 = self.start.x;
-        let base_y: f64 = self.start.y;
+            for i in 0..nsegs {
+                let start_x: f64 = base_x + (i as f64) * xstep;
+                let start_y: f64 = base_y + (i as f64) * ystep;
 
-        for i in 0..nsegs {
-            let start_x: f64 = base_x + (i as f64) * xstep;
-            let start_y: f64 = base_y + (i as f64) * ystep;
+                let end_x: f64 = base_x + ((i + 1) as f64) * xstep;
+                let end_y: f64 = base_y + ((i + 1) as f64) * ystep;
 
-            let end_x: f64 = base_x + ((i + 1) as f64) * xstep;
-            let end_y: f64 = base_y + ((i + 1) as f64) * ystep;
+                let seg_start: Point = Point {
+                    x: start_x,
+                    y: start_y,
+                };
+                let seg_end: Point = Point { x: end_x, y: end_y };
 
-            let seg_start: Point = Point {
-                x: start_x,
-                y: start_y,
-            };
-            let seg_end: Point = Point { x: end_x, y: end_y };
+                let segment: Segment =
+                    Segment::new(first_segment + i, seg_start, seg_end, leg.angle);
+                self.segments.push(segment);
+            }
 
-            let segment: Segment = Segment::new(i, seg_start, seg_end);
-            self.segments.push(segment);
+            leg.segments = first_segment..self.segments.len();
         }
     }
 
-    pub fn segments_iter(&self) -> impl Iterator<Item = &Segment> {
-        self.segments.iter()
+    /// Replaces the legs by straight legs between the MDL characteristic points of the
+    /// vertices, each leg being a single segment of its own length.
+    pub fn make_mdl_segments(&mut self, cost_weight: f64, min_segment_length: f64) {
+        self.segments.clear();
+        if self.legs.is_empty() {
+            return;
+        }
+
+        let points: Vec<Point> = self.points();
+        let cps: Vec<usize> = characteristic_points(&points, cost_weight, min_segment_length);
+
+        self.legs = cps
+            .windows(2)
+            .enumerate()
+            .map(|(index, pair)| {
+                let (start, end): (Point, Point) = (points[pair[0]], points[pair[1]]);
+                Leg {
+                    index,
+                    start,
+                    end,
+                    angle: Self::get_spatial_angle(&start, &end),
+                    segments: index..index + 1,
+                }
+            })
+            .collect();
+        self.segments = self
+            .legs
+            .iter()
+            .map(|leg| Segment::new(leg.index, leg.start, leg.end, leg.angle))
+            .collect();
+    }
+
+    /// Number of segments a line of `length` is cut into; 0 for a zero-length line.
+    pub fn segment_count(length: f64, segment_length: f64) -> usize {
+        ((length / segment_length) - 1e-9).ceil() as usize
+    }
+
+    pub fn legs(&self) -> &[Leg] {
+        &self.legs
+    }
+
+    /// Vertices of the line, repeated vertices left out; start and end for a zero-length line.
+    pub fn points(&self) -> Vec<Point> {
+        if self.legs.is_empty() {
+            return vec![self.start, self.end];
+        }
+        std::iter::once(self.start)
+            .chain(self.legs.iter().map(|leg| leg.end))
+            .collect()
+    }
+
+    pub fn leg_segments(&self, leg: &Leg) -> &[Segment] {
+        &self.segments[leg.segments.clone()]
     }
 
-    pub fn segment(&self, index: usize) -> Option<&Segment> {
-        self.segments.get(index)
+    /// Bytes allocated on the heap for the legs and segments.
+    pub fn heap_bytes(&self) -> usize {
+        self.legs.capacity() * size_of::<Leg>() + self.segments.capacity() * size_of::<Segment>()
     }
 
     #[allow(unused)]
     pub fn print_info(&self) -> String {
         format!(
-            "Trajectory ID: {}, Start: ({}, {}), End: ({}, {}), Weight: {}, Angle: {}",
-            self.id, self.start.x, self.start.y, self.end.x, self.end.y, self.weight, self.angle
+            "Trajectory ID: {}, Start: ({}, {}), End: ({}, {}), Weight: {}, Angle: {}, Legs: {}",
+            self.id,
+            self.start.x,
+            self.start.y,
+            self.end.x,
+            self.end.y,
+            self.weight,
+            self.angle,
+            self.legs.len()
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn od_line(vertices: &[(f64, f64)]) -> InputODLine {
+        let points: Vec<Point> = vertices.iter().map(|&(x, y)| Point { x, y }).collect();
+        InputODLine {
+            line_id: 0,
+            weight: 1,
+            start: points[0],
+            end: points[points.len() - 1],
+            via: points[1..points.len() - 1].to_vec(),
+        }
+    }
+
+    // (distance, segment id) from `point` to the first leg of `traj`
+    fn closest(traj: &Trajectory, x: f64, y: f64) -> (f64, Option<usize>) {
+        let (distance, segment) = traj.distance_to_point(&traj.legs()[0], &Point { x, y });
+        (distance, segment.map(|segment| segment.id))
+    }
+
+    #[test]
+    fn segment_count_at_exact_multiples() {
+        assert_eq!(Trajectory::segment_count(300.0, 100.0), 3);
+        assert_eq!(Trajectory::segment_count(299.0, 100.0), 3);
+        assert_eq!(Trajectory::segment_count(301.0, 100.0), 4);
+        assert_eq!(Trajectory::segment_count(0.0, 100.0), 0);
+        // 0.1 + 0.1 + 0.1 / 0.1 and 0.7 / 0.1 fall just above and below the multiple
+        assert_eq!(Trajectory::segment_count(0.1 + 0.1 + 0.1, 0.1), 3);
+        assert_eq!(Trajectory::segment_count(0.7, 0.1), 7);
+    }
+
+    #[test]
+    fn leg_of_a_length_multiple_is_cut_into_whole_segments() {
+        let traj: Trajectory = Trajectory::new(od_line(&[(0.0, 0.0), (300.0, 0.0)]), 100.0);
+        assert_eq!(traj.leg_segments(&traj.legs()[0]).len(), 3);
+
+        let traj: Trajectory = Trajectory::new(od_line(&[(0.0, 0.0), (250.0, 0.0)]), 100.0);
+        assert_eq!(traj.leg_segments(&traj.legs()[0]).len(), 3);
+    }
+
+    #[test]
+    fn distance_to_point_along_the_leg() {
+        let traj: Trajectory = Trajectory::new(od_line(&[(0.0, 0.0), (300.0, 0.0)]), 100.0);
+        assert_eq!(closest(&traj, 50.0, 4.0), (4.0, Some(0)));
+        assert_eq!(closest(&traj, 150.0, -3.0), (3.0, Some(1)));
+        assert_eq!(closest(&traj, 299.0, 0.0), (0.0, Some(2)));
+    }
+
+    #[test]
+    fn distance_to_point_clamps_past_the_ends() {
+        let traj: Trajectory = Trajectory::new(od_line(&[(0.0, 0.0), (300.0, 0.0)]), 100.0);
+        assert_eq!(closest(&traj, -50.0, 0.0), (50.0, Some(0)));
+        // t = 1 on a length multiple: the last segment, not one past it
+        assert_eq!(closest(&traj, 300.0, 5.0), (5.0, Some(2)));
+        assert_eq!(closest(&traj, 400.0, 0.0), (100.0, Some(2)));
+
+        let traj: Trajectory = Trajectory::new(od_line(&[(0.0, 0.0), (0.3, 0.0)]), 0.1);
+        assert_eq!(closest(&traj, 0.3, 0.0).1, Some(2));
+    }
+
+    #[test]
+    fn distance_to_point_on_later_legs_uses_their_segments() {
+        let traj: Trajectory =
+            Trajectory::new(od_line(&[(0.0, 0.0), (200.0, 0.0), (200.0, 200.0)]), 100.0);
+        let leg: &Leg = &traj.legs()[1];
+        let (distance, segment) = traj.distance_to_point(leg, &Point { x: 210.0, y: 500.0 });
+        assert_eq!(distance, 10.0_f64.hypot(300.0));
+        assert_eq!(segment.map(|segment| segment.id), Some(3));
+    }
+
+    #[test]
+    fn leg_too_short_for_a_segment_has_none() {
+        let traj: Trajectory = Trajectory::new(od_line(&[(0.0, 0.0), (1e-12, 0.0)]), 100.0);
+        assert_eq!(closest(&traj, 0.0, 2.0), (2.0, None));
+    }
+
+    #[test]
+    fn angle_difference_across_zero() {
+        assert_eq!(angle_difference(10.0, 350.0, false), 20.0);
+        assert_eq!(angle_difference(350.0, 10.0, false), 20.0);
+        assert_eq!(angle_difference(90.0, 90.0, false), 0.0);
+        assert_eq!(angle_difference(0.0, 180.0, false), 180.0);
+    }
+
+    #[test]
+    fn undirected_angle_difference_ignores_the_direction() {
+        // Opposite directions are the same axis
+        assert_eq!(angle_difference(0.0, 180.0, true), 0.0);
+        assert_eq!(angle_difference(45.0, 225.0, true), 0.0);
+        assert_eq!(angle_difference(10.0, 200.0, true), 10.0);
+        assert_eq!(angle_difference(170.0, 10.0, true), 20.0);
+        // Never more than a right angle
+        assert_eq!(angle_difference(0.0, 90.0, true), 90.0);
+        assert_eq!(angle_difference(0.0, 100.0, true), 80.0);
+    }
+
+    #[test]
+    fn undirected_angle_difference_is_at_most_the_directed_one() {
+        for a in (0..360).step_by(7) {
+            for b in (0..360).step_by(11) {
+                let (a, b): (f64, f64) = (a as f64, b as f64);
+                let directed: f64 = angle_difference(a, b, false);
+                let undirected: f64 = angle_difference(a, b, true);
+                assert!(undirected <= directed);
+                assert!((0.0..=90.0).contains(&undirected));
+                assert_eq!(undirected, directed.min(180.0 - directed));
+            }
+        }
+    }
+}
@@ -1,25 +1,78 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
 use rayon::ThreadPool;
 
+use super::geometry::projection::Projection;
 use super::storage::clustered_trajectories::ClusteredTrajectories;
 use super::storage::raw_trajectories::RawTrajectories;
-use crate::gui::app_events::{AppError, AppEvent, ComputationEvent};
+use super::storage::session_store::{
+    ClusteringRun, Dataset, DatasetId, LoadParams, RunId, RunKey, SessionStore,
+};
+use crate::gui::app_events::{AppError, AppEvent, ComputationEvent, SessionChange};
 
 use crate::io::args::{ExecutionMode, TraclusArgs};
-use crate::io::input_loader::parse_input_data;
+use crate::io::geopackage_writer::generate_geopackage;
+use crate::io::input_loader::{SanitationReport, parse_input_data};
+use crate::io::io_error::IoError;
 use crate::io::output_writer::{
-    SegmentOutputFormat, generate_corridor_file, generate_segment_file,
+    OutputKind, SegmentOutputFormat, generate_corridor_file, generate_corridor_geojson,
+    generate_segment_file, generate_segment_geojson,
 };
+use crate::io::shapefile_writer::{generate_corridor_shapefile, generate_segment_shapefile};
+use crate::utils::cancellation_token::{CancellationToken, Cancelled};
+use crate::utils::correlation::correlation_percent;
+use crate::utils::progress_reporter::ProgressReporter;
+use crate::utils::run_timings::RunTimings;
+use crate::utils::time_estimator::estimate_pre_run;
 
 use super::algorithms::base_traclusdl::TraclusAlgorithm;
 use super::algorithms::parallel_rayon_traclusdl::ParallelRayonTraclusDL;
 use super::algorithms::serial_traclusdl::SerialTraclusDL;
 
+/// Why a command-line run ended without writing its output files.
+#[derive(Debug)]
+pub enum RunError {
+    Io(IoError),
+    Cancelled, // the cancellation token stopped the clustering
+}
+
+impl RunError {
+    /// Exit code of the process: those of IoError, 6 for a cancelled run.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            RunError::Io(err) => err.exit_code(),
+            RunError::Cancelled => 6,
+        }
+    }
+}
+
+impl From<IoError> for RunError {
+    fn from(err: IoError) -> Self {
+        RunError::Io(err)
+    }
+}
+
+impl From<Cancelled> for RunError {
+    fn from(_: Cancelled) -> Self {
+        RunError::Cancelled
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Io(err) => write!(f, "{}", err),
+            RunError::Cancelled => write!(f, "Clustering cancelled, no output written"),
+        }
+    }
+}
 
 pub struct MainTraclusDL {
-    raw_storage: Option<RawTrajectories>,
-    clust_storage: Option<ClusteredTrajectories>,
+    sessions: SessionStore,
 
     rayon_pool: ThreadPool,
+    cancel_token: CancellationToken,
     pub event: ComputationEvent,
 }
 
@@ -31,71 +84,512 @@ impl MainTraclusDL {
             .expect("failed to build Rayon thread pool");
 
         Self {
-            raw_storage: None,
-            clust_storage: None,
+            sessions: SessionStore::new(),
 
             rayon_pool,
+            cancel_token: CancellationToken::new(),
             event: ComputationEvent::new(),
         }
     }
 
-    // Loads raw trajectories from disk and stores them.
-    pub fn load_raw_storage(&mut self, args: &TraclusArgs) {
-        self.raw_storage = Some(parse_input_data(&args));
+    /// Loaded datasets and their runs, for listing and comparing them.
+    pub fn sessions(&self) -> &SessionStore {
+        &self.sessions
+    }
+
+    pub fn raw_storage(&self, dataset: DatasetId) -> Option<&RawTrajectories> {
+        self.sessions.dataset(dataset).map(|d| &d.raw_storage)
+    }
+
+    pub fn clust_storage(&self, key: RunKey) -> Option<&ClusteredTrajectories> {
+        self.sessions.run(key).map(|run| &run.clust_storage)
+    }
+
+    /// Token checked by the running computation; share it with whoever may stop it.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel_token.clone()
+    }
+
+    // Loads raw trajectories from disk into a new dataset of the session store and returns its id.
+    // Datasets loaded before are kept. On failure AppError::LoadFailed is emitted.
+    pub fn load_dataset(&mut self, args: &TraclusArgs) -> Option<DatasetId> {
+        let load_start: Instant = Instant::now();
+        let (raw_storage, sanitation): (RawTrajectories, SanitationReport) =
+            match parse_input_data(args) {
+                Ok(loaded) => loaded,
+                Err(err) => {
+                    self.event.emit(AppEvent::Error(AppError::LoadFailed(err)));
+                    return None;
+                }
+            };
+        let traj_count: usize = raw_storage.get_total_trajectories();
+        let dataset: DatasetId =
+            self.sessions
+                .insert_dataset(raw_storage, LoadParams::of(args), load_start.elapsed());
+        self.emit_session_changed(dataset, None, SessionChange::Loaded);
 
         // Emit information about the loaded data
         self.event.emit(AppEvent::LoadComplete {
-            traj_count: self.raw_storage.as_ref().unwrap().get_total_trajectories(),
-            correlation_percent: 10.0, // TODO: compute actual correlation
+            traj_count,
+            sanitation,
         });
+        self.estimate_run_time(dataset, args);
+        Some(dataset)
     }
 
-    // Runs the clustering algorithm on the currently loaded raw storage and stores the clustered result.
-    pub fn run_clustering(&mut self, args: &TraclusArgs) {
-        self.event
-            .emit(AppEvent::Error(AppError::IoError((format!("{:?}", args)))));
+    /// Drops a dataset and all its runs.
+    pub fn drop_dataset(&mut self, dataset: DatasetId) {
+        if self.sessions.drop_dataset(dataset) {
+            self.emit_session_changed(dataset, None, SessionChange::Dropped);
+        } else {
+            self.event.emit(AppEvent::Error(AppError::UnknownSession));
+        }
+    }
 
-        if self.raw_storage.is_none() {
-            self.event.emit(AppEvent::Error(AppError::NoRawStorage));
+    /// Drops one run, e.g. the result of a removed GUI row.
+    pub fn drop_run(&mut self, key: RunKey) {
+        if self.sessions.drop_run(key) {
+            self.emit_session_changed(key.dataset, Some(key.run), SessionChange::Dropped);
+        } else {
+            self.event.emit(AppEvent::Error(AppError::UnknownSession));
+        }
+    }
+
+    // Compares the run `b` against the run `a`; the result comes back in AppEvent::ComparisonComplete.
+    pub fn compare_runs(&mut self, a: RunKey, b: RunKey) {
+        match self.sessions.compare(a, b) {
+            Some(comparison) => self
+                .event
+                .emit(AppEvent::ComparisonComplete(Box::new(comparison))),
+            None => self.event.emit(AppEvent::Error(AppError::UnknownSession)),
+        }
+    }
+
+    // Emits a rough duration of run_clustering with `args` on the dataset, and the share
+    // of its weight that could cluster with these parameters.
+    // The spatial index they need is kept for the run. Stops when cancelled.
+    pub fn estimate_run_time(&mut self, dataset: DatasetId, args: &TraclusArgs) {
+        let Some(dataset) = Self::dataset_for(&mut self.sessions, &mut self.event, dataset, args)
+        else {
             return;
+        };
+
+        Self::emit_pre_run_estimates(
+            &mut self.event,
+            &mut dataset.raw_storage,
+            args,
+            &self.rayon_pool,
+            &self.cancel_token,
+        );
+    }
+
+    // The dataset with its storage loaded for `args`. The storage is reloaded when `args` cut
+    // the lines into other buckets or segments than those it was loaded with, as the
+    // benchmark does. Emits an error and returns None when the dataset is unknown or was
+    // loaded from another file, format or CRS.
+    fn dataset_for<'a>(
+        sessions: &'a mut SessionStore,
+        event: &mut ComputationEvent,
+        dataset: DatasetId,
+        args: &TraclusArgs,
+    ) -> Option<&'a mut Dataset> {
+        let Some(dataset) = sessions.dataset_mut(dataset) else {
+            event.emit(AppEvent::Error(AppError::NoRawStorage));
+            return None;
+        };
+        let load_params: LoadParams = LoadParams::of(args);
+        if dataset.load_params == load_params {
+            return Some(dataset);
+        }
+        if !dataset.load_params.same_lines(&load_params) {
+            event.emit(AppEvent::Error(AppError::DatasetMismatch));
+            return None;
         }
 
-        let raw_storage: &RawTrajectories = self.raw_storage.as_ref().unwrap();
-        let mut clust_storage: ClusteredTrajectories = ClusteredTrajectories::new();
+        let load_start: Instant = Instant::now();
+        match parse_input_data(args) {
+            Ok((raw_storage, _)) => {
+                dataset.reload(raw_storage, load_params, load_start.elapsed());
+                Some(dataset)
+            }
+            Err(err) => {
+                event.emit(AppEvent::Error(AppError::LoadFailed(err)));
+                None
+            }
+        }
+    }
 
-        let clustering_algorithm: Box<dyn TraclusAlgorithm> = Self::get_proper_algorithm(args);
-        clustering_algorithm.db_scan_clustering(raw_storage, &mut clust_storage);
+    // Runs the clustering algorithm on the dataset of `key` and stores the clustered
+    // result under `key`, replacing the previous run of that key.
+    // A cancelled run emits AppEvent::Cancelled and keeps the previous run.
+    pub fn run_clustering(&mut self, args: &TraclusArgs, key: RunKey) {
+        let Some(dataset) =
+            Self::dataset_for(&mut self.sessions, &mut self.event, key.dataset, args)
+        else {
+            return;
+        };
+
+        let raw_storage: &mut RawTrajectories = &mut dataset.raw_storage;
+        let progress: ProgressReporter = Self::progress_reporter(&self.event, raw_storage);
+        let result: Result<(ClusteredTrajectories, RunTimings), Cancelled> = Self::cluster_phases(
+            &self.rayon_pool,
+            &self.cancel_token,
+            progress,
+            raw_storage,
+            args,
+        );
 
-        self.clust_storage = Some(clust_storage);
+        match result {
+            Ok((clust_storage, timings)) => {
+                Self::emit_computation_complete(&mut self.event, &clust_storage);
+                self.sessions.insert_run(
+                    key,
+                    ClusteringRun {
+                        clust_storage,
+                        args: args.clone(),
+                        clustering_time: timings.clustering,
+                        finalization_time: timings.finalization,
+                    },
+                );
+                self.emit_session_changed(key.dataset, Some(key.run), SessionChange::RunStored);
+            }
+            Err(Cancelled) => self.event.emit(AppEvent::Cancelled),
+        }
     }
 
-    // Writes corridor and segment output files from the current clustered storage.
-    pub fn generate_outputs(&self, args: &TraclusArgs) {
-        if let Some(clust) = &self.clust_storage {
-            generate_corridor_file(args, clust);
-            generate_segment_file(args, clust, SegmentOutputFormat::NewTraclus);
-            generate_segment_file(args, clust, SegmentOutputFormat::OldTraclus);
+    // Writes the selected outputs of the run `key` into `output_dir`
+    // (the input directory when None). File names carry the parameters of that clustering.
+    pub fn generate_outputs(
+        &mut self,
+        key: RunKey,
+        output_dir: Option<String>,
+        kinds: &[OutputKind],
+    ) {
+        let Some(dataset) = self.sessions.dataset(key.dataset) else {
+            self.event.emit(AppEvent::Error(AppError::NoRawStorage));
+            return;
+        };
+        let Some(run) = self.sessions.run(key) else {
+            self.event.emit(AppEvent::Error(AppError::NoClustStorage));
+            return;
+        };
+
+        let args: TraclusArgs = TraclusArgs {
+            output_dir,
+            ..run.args.clone()
+        };
+        let timings: RunTimings = RunTimings {
+            loading: dataset.loading_time,
+            clustering: run.clustering_time,
+            finalization: run.finalization_time,
+        };
+        let raw: &RawTrajectories = &dataset.raw_storage;
+        match Self::write_output_files(&args, raw, &run.clust_storage, &timings, kinds) {
+            Ok(files) => self.event.emit(AppEvent::OutputsWritten { files }),
+            Err(err) => self
+                .event
+                .emit(AppEvent::Error(AppError::OutputFailed(err))),
         }
     }
 
     /// Commmand line entry point for running the full TraclusDL algorithm
     /// No GUI involved, No overhead of statistics, just pure algorithm execution
-    pub fn run_full_traclus(&self, args: TraclusArgs) {
-        let raw_storage: RawTrajectories = parse_input_data(&args);
-        let mut clust_storage: ClusteredTrajectories = ClusteredTrajectories::new();
+    /// Progress events are only produced when a subscriber listens (Logger mode)
+    /// I/O errors and cancellation are returned to the caller, which turns them into exit codes
+    pub fn run_full_traclus(&mut self, args: TraclusArgs) -> Result<(), RunError> {
+        let load_start: Instant = Instant::now();
+        let (mut raw_storage, sanitation): (RawTrajectories, SanitationReport) =
+            parse_input_data(&args)?;
+        let loading: Duration = load_start.elapsed();
+        for warning in &sanitation.warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        if sanitation.dropped() > 0 {
+            println!("Input sanitation: {}", sanitation);
+        }
+        if let Some(projection) = &raw_storage.projection {
+            println!("Input projected to {}", projection.zone);
+        }
+        if self.event.has_subscribers() {
+            Self::emit_pre_run_estimates(
+                &mut self.event,
+                &mut raw_storage,
+                &args,
+                &self.rayon_pool,
+                &self.cancel_token,
+            );
+        }
+        let progress: ProgressReporter = Self::progress_reporter(&self.event, &raw_storage);
+
+        let (clust_storage, timings): (ClusteredTrajectories, RunTimings) = Self::cluster_phases(
+            &self.rayon_pool,
+            &self.cancel_token,
+            progress,
+            &mut raw_storage,
+            &args,
+        )?;
+        let timings: RunTimings = RunTimings { loading, ..timings };
+        Self::emit_computation_complete(&mut self.event, &clust_storage);
+
+        let kinds: &[OutputKind] = OutputKind::for_format(args.output_format);
+        Self::write_output_files(&args, &raw_storage, &clust_storage, &timings, kinds)?;
+        Ok(())
+    }
+
+    /// Clusters an already loaded input without events, progress or session store,
+    /// for the benchmark. The timings hold the clustering and finalization phases.
+    pub fn cluster_for_bench(
+        &self,
+        raw_storage: &mut RawTrajectories,
+        args: &TraclusArgs,
+    ) -> Result<(ClusteredTrajectories, RunTimings), Cancelled> {
+        let progress: ProgressReporter = ProgressReporter::disabled();
+        Self::cluster_phases(
+            &self.rayon_pool,
+            &self.cancel_token,
+            progress,
+            raw_storage,
+            args,
+        )
+    }
+
+    /// Builds the spatial index a run with `args` uses, so that the benchmark does not
+    /// charge it to the clustering phase of its first run.
+    pub fn build_index_for_bench(&self, raw_storage: &mut RawTrajectories, args: &TraclusArgs) {
+        self.rayon_pool
+            .install(|| raw_storage.build_spatial_index(Self::spatial_cell_size(args)));
+    }
+
+    /// Returns the written files, in the order of `kinds`
+    pub fn write_output_files(
+        args: &TraclusArgs,
+        raw_storage: &RawTrajectories,
+        clust_storage: &ClusteredTrajectories,
+        timings: &RunTimings,
+        kinds: &[OutputKind],
+    ) -> Result<Vec<String>, IoError> {
+        let projection: Option<&Projection> = raw_storage.projection.as_ref();
+        let mut files: Vec<String> = Vec::new();
+        for kind in kinds {
+            match kind {
+                OutputKind::CorridorList => {
+                    files.push(generate_corridor_file(args, clust_storage, projection)?);
+                }
+                OutputKind::SegmentListNew => files.push(generate_segment_file(
+                    args,
+                    clust_storage,
+                    SegmentOutputFormat::NewTraclus,
+                    projection,
+                )?),
+                OutputKind::SegmentListOld => files.push(generate_segment_file(
+                    args,
+                    clust_storage,
+                    SegmentOutputFormat::OldTraclus,
+                    projection,
+                )?),
+                OutputKind::GeoJson => {
+                    files.push(generate_corridor_geojson(args, clust_storage, projection)?);
+                    files.push(generate_segment_geojson(args, clust_storage, projection)?);
+                }
+                OutputKind::GeoPackage => {
+                    files.push(generate_geopackage(
+                        args,
+                        raw_storage,
+                        clust_storage,
+                        timings,
+                    )?);
+                }
+                OutputKind::Shapefile => {
+                    files.push(generate_corridor_shapefile(
+                        args,
+                        clust_storage,
+                        projection,
+                    )?);
+                    files.push(generate_segment_shapefile(args, clust_storage, projection)?);
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    // Spatial index and cluster discovery, then corridor finalization, on the Rayon pool.
+    // The returned timings hold those two phases; loading is left at zero.
+    fn cluster_phases(
+        rayon_pool: &ThreadPool,
+        cancel_token: &CancellationToken,
+        progress: ProgressReporter,
+        raw_storage: &mut RawTrajectories,
+        args: &TraclusArgs,
+    ) -> Result<(ClusteredTrajectories, RunTimings), Cancelled> {
+        rayon_pool.install(|| {
+            cancel_token.check()?;
+            let clustering_algorithm: Box<dyn TraclusAlgorithm> =
+                Self::get_proper_algorithm(args, cancel_token, progress);
+            let mut clust_storage: ClusteredTrajectories = ClusteredTrajectories::new();
+
+            let clustering_start: Instant = Instant::now();
+            raw_storage.build_spatial_index(Self::spatial_cell_size(args));
+            cancel_token.check()?;
+            clustering_algorithm.discover_clusters(raw_storage, &mut clust_storage)?;
+            let clustering: Duration = clustering_start.elapsed();
+
+            let finalization_start: Instant = Instant::now();
+            clustering_algorithm.create_corridors(&mut clust_storage)?;
+            let timings: RunTimings = RunTimings {
+                loading: Duration::ZERO,
+                clustering,
+                finalization: finalization_start.elapsed(),
+            };
+            Ok((clust_storage, timings))
+        })
+    }
+
+    // Grid cells of about max_dist keep each query to a few cells; never smaller than
+    // a segment so long lines are not spread over thousands of cells.
+    fn spatial_cell_size(args: &TraclusArgs) -> f64 {
+        args.max_dist.max(args.segment_size)
+    }
 
-        let clustering_algorithm: Box<dyn TraclusAlgorithm> = Self::get_proper_algorithm(&args);
-        clustering_algorithm.db_scan_clustering(&raw_storage, &mut clust_storage);
+    // Without subscriber (Performance mode) the reporter is disabled and costs nothing.
+    fn progress_reporter(
+        event: &ComputationEvent,
+        raw_storage: &RawTrajectories,
+    ) -> ProgressReporter {
+        if event.has_subscribers() {
+            ProgressReporter::new(event.sender(), raw_storage.get_total_legs())
+        } else {
+            ProgressReporter::disabled()
+        }
+    }
+
+    // The time estimate reads the buckets only; the correlation needs the spatial index,
+    // built here and kept for the run, and stops at the cancellation token.
+    fn emit_pre_run_estimates(
+        event: &mut ComputationEvent,
+        raw_storage: &mut RawTrajectories,
+        args: &TraclusArgs,
+        rayon_pool: &ThreadPool,
+        cancel_token: &CancellationToken,
+    ) {
+        let estimate: Duration =
+            estimate_pre_run(raw_storage, args, rayon_pool.current_num_threads());
+        event.emit(AppEvent::PreRunEstimate {
+            estimated_secs: estimate.as_secs_f64(),
+        });
+
+        let correlation: Result<f64, Cancelled> = rayon_pool.install(|| {
+            cancel_token.check()?;
+            raw_storage.build_spatial_index(Self::spatial_cell_size(args));
+            correlation_percent(raw_storage, args.max_dist, args.max_angle, cancel_token)
+        });
+        if let Ok(correlation_percent) = correlation {
+            event.emit(AppEvent::CorrelationEstimate {
+                correlation_percent,
+            });
+        }
+    }
+
+    fn emit_session_changed(
+        &mut self,
+        dataset: DatasetId,
+        run: Option<RunId>,
+        change: SessionChange,
+    ) {
+        let memory_bytes: usize = self.sessions.memory_bytes();
+        self.event.emit(AppEvent::SessionChanged {
+            dataset,
+            run,
+            change,
+            memory_bytes,
+        });
+    }
 
-        generate_corridor_file(&args, &clust_storage);
-        generate_segment_file(&args, &clust_storage, SegmentOutputFormat::NewTraclus);
-        generate_segment_file(&args, &clust_storage, SegmentOutputFormat::OldTraclus);
+    fn emit_computation_complete(
+        event: &mut ComputationEvent,
+        clust_storage: &ClusteredTrajectories,
+    ) {
+        event.emit(AppEvent::ComputationComplete {
+            total_corridors: clust_storage.corridors.len(),
+            total_seg: clust_storage.get_all_cluster_members_iter().count(),
+            total_seg_outside_corridor: clust_storage.non_clustered_segments.len(),
+        });
     }
 
-    fn get_proper_algorithm(args: &TraclusArgs) -> Box<dyn TraclusAlgorithm> {
+    fn get_proper_algorithm(
+        args: &TraclusArgs,
+        cancel_token: &CancellationToken,
+        progress: ProgressReporter,
+    ) -> Box<dyn TraclusAlgorithm> {
+        let cancel_token: CancellationToken = cancel_token.clone();
         match args.mode {
-            ExecutionMode::Serial => Box::new(SerialTraclusDL::new(args.clone())),
-            ExecutionMode::ParallelRayon => Box::new(ParallelRayonTraclusDL::new(args.clone())),
+            ExecutionMode::Serial => {
+                Box::new(SerialTraclusDL::new(args.clone(), cancel_token, progress))
+            }
+            ExecutionMode::ParallelRayon => Box::new(ParallelRayonTraclusDL::new(
+                args.clone(),
+                cancel_token,
+                progress,
+            )),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::comparison::RunSnapshot;
+    use crate::io::output_writer::build_output_filename;
+    use crate::io::run_reader::read_run_outputs;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn text_outputs_are_read_back_with_their_partition() {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("main_traclusdl_text_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input: PathBuf = dir.join("trips.txt");
+        fs::write(&input, "0\t1\t0\t0\t0\t1000\n1\t1\t20\t0\t20\t1000\n").unwrap();
+
+        let args: TraclusArgs = TraclusArgs {
+            file: input.to_string_lossy().into_owned(),
+            output_dir: Some(dir.to_string_lossy().into_owned()),
+            segment_size: 250.0,
+            ..TraclusArgs::default()
+        };
+        let segments_path: String = build_output_filename(&args, "segmentlist_new", "txt");
+        let corridors_path: String = build_output_filename(&args, "corridorlist", "txt");
+        MainTraclusDL::new(1).run_full_traclus(args).unwrap();
+        let run: Result<RunSnapshot, IoError> = read_run_outputs(&segments_path, &corridors_path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let run: RunSnapshot = run.unwrap();
+        assert_eq!(run.partition.as_deref(), Some("fixed segment_size=250"));
+        assert_eq!(run.segments.len(), 8);
+    }
+
+    #[test]
+    fn cancelled_run_has_its_own_exit_code() {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("main_traclusdl_cancel_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input: PathBuf = dir.join("trips.txt");
+        fs::write(&input, "0\t1\t0\t0\t0\t1000\n1\t1\t20\t0\t20\t1000\n").unwrap();
+
+        let mut main_traclusdl: MainTraclusDL = MainTraclusDL::new(1);
+        main_traclusdl.cancellation_token().cancel();
+        let args: TraclusArgs = TraclusArgs {
+            file: input.to_string_lossy().into_owned(),
+            output_dir: Some(dir.to_string_lossy().into_owned()),
+            ..TraclusArgs::default()
+        };
+        let result: Result<(), RunError> = main_traclusdl.run_full_traclus(args);
+
+        let written: usize = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+        let err: RunError = result.unwrap_err();
+        assert!(matches!(err, RunError::Cancelled));
+        assert_eq!(err.exit_code(), 6);
+        assert_eq!(written, 1); // the input only
+    }
+}
@@ -1,5 +1,8 @@
-mod algorithms;
+pub mod algorithms;
+pub mod comparison;
+pub mod dataset_stats;
 pub mod geometry;
 pub mod main_traclusdl;
 pub mod objects;
 pub mod storage;
+pub mod synthetic_dataset;
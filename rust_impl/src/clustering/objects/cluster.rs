@@ -67,6 +67,11 @@ impl Cluster {
         std::iter::once(&self.seed.cm).chain(self.members.iter())
     }
 
+    /// Bytes allocated on the heap for the candidates and members.
+    pub fn heap_bytes(&self) -> usize {
+        (self.candidates.capacity() + self.members.capacity()) * size_of::<ClusterMember>()
+    }
+
     #[allow(unused)]
     pub fn print_info(&self) {
         println!(
@@ -5,31 +5,54 @@ use super::cluster_member::ClusterMember;
 pub struct Corridor {
     pub id: usize,
     pub weight: u32,
+    pub forward_weight: u32,  // members running start→end, as the seed
+    pub backward_weight: u32, // members running end→start, only in the undirected mode
     pub start: Point,
     pub end: Point,
     pub cluster: Cluster,
 }
 
 impl Corridor {
-    pub fn new(cluster: Cluster, id: usize) -> Self {
-        let (start, end) = Self::weighted_average(&cluster);
+    /// Corridor of `cluster`; when `undirected`, the members running against the seed are
+    /// reversed before being averaged.
+    pub fn new(cluster: Cluster, id: usize, undirected: bool) -> Self {
+        let (start, end) = Self::weighted_average(&cluster, undirected);
         let weight: u32 = cluster.total_weight;
+        let backward_weight: u32 = if undirected {
+            cluster
+                .members
+                .iter()
+                .filter(|member| Self::runs_backward(&cluster.seed.cm, member))
+                .map(|member| member.weight)
+                .sum()
+        } else {
+            0
+        };
         Self {
             id,
             weight,
+            forward_weight: weight - backward_weight,
+            backward_weight,
             start,
             end,
             cluster,
         }
     }
 
-    pub fn weighted_average(cluster: &Cluster) -> (Point, Point) {
-        let mut weighted_start: Point = cluster.seed.cm.start * (cluster.seed.cm.weight as f64);
-        let mut weighted_end: Point = Self::get_weighted_end(&cluster.seed.cm);
+    pub fn weighted_average(cluster: &Cluster, undirected: bool) -> (Point, Point) {
+        let seed: &ClusterMember = &cluster.seed.cm;
+        let mut weighted_start: Point = seed.start * (seed.weight as f64);
+        let mut weighted_end: Point = Self::get_weighted_end(seed);
 
         for member in &cluster.members {
-            weighted_start = weighted_start + (member.start * (member.weight as f64));
-            weighted_end = weighted_end + Self::get_weighted_end(member);
+            if undirected && Self::runs_backward(seed, member) {
+                // Reversed, its end is on the side of the corridor start
+                weighted_start = weighted_start + (member.end_point() * (member.weight as f64));
+                weighted_end = weighted_end + (member.start * (member.weight as f64));
+            } else {
+                weighted_start = weighted_start + (member.start * (member.weight as f64));
+                weighted_end = weighted_end + Self::get_weighted_end(member);
+            }
         }
 
         let total_weight_divider: f64 = 1.0 / cluster.total_weight as f64;
@@ -46,4 +69,13 @@ impl Corridor {
             y: member.center.y + (member.center.y - member.start.y),
         } * (member.weight as f64)
     }
+
+    /// Whether `member` runs more than 90° away from the direction of `seed`.
+    pub fn runs_backward(seed: &ClusterMember, member: &ClusterMember) -> bool {
+        let seed_dx: f64 = seed.center.x - seed.start.x;
+        let seed_dy: f64 = seed.center.y - seed.start.y;
+        let member_dx: f64 = member.center.x - member.start.x;
+        let member_dy: f64 = member.center.y - member.start.y;
+        seed_dx * member_dx + seed_dy * member_dy < 0.0
+    }
 }
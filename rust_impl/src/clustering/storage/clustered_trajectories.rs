@@ -1,9 +1,11 @@
-use super::super::geometry::trajectory::Trajectory;
+use super::super::geometry::trajectory::{Leg, Trajectory};
 use super::super::objects::cluster::Cluster;
 use super::super::objects::cluster_member::ClusterMember;
 use super::super::objects::corridor::Corridor;
 use super::super::storage::priority_queue::PriorityQueueCluster;
 use crate::io::args::TraclusArgs;
+use crate::utils::cancellation_token::{CancellationToken, Cancelled};
+use crate::utils::progress_reporter::ProgressReporter;
 
 pub struct ClusteredTrajectories {
     clusters: PriorityQueueCluster,
@@ -30,23 +32,47 @@ impl ClusteredTrajectories {
         }
     }
 
-    pub fn finalize_corridors(&mut self, args: &TraclusArgs) {
+    // Pops the clusters into corridors; the token is checked and progress reported for each corridor.
+    pub fn finalize_corridors(
+        &mut self,
+        args: &TraclusArgs,
+        cancel_token: &CancellationToken,
+        progress: &ProgressReporter,
+    ) -> Result<(), Cancelled> {
+        progress.start_finalization(self.clusters.num_clusters());
+
         while let Some(completed_cluster) = self.clusters.pop_and_clean(args.min_density) {
+            cancel_token.check()?;
             let index_corridor: usize = self.corridors.len();
-            let corridor: Corridor = Corridor::new(*completed_cluster, index_corridor);
+            let corridor: Corridor =
+                Corridor::new(*completed_cluster, index_corridor, args.undirected);
             self.corridors.push(corridor);
+            progress.corridor_done(self.clusters.num_clusters());
         }
-        self.non_clustered_segments = std::mem::take(&mut self.clusters.non_clustered_segments);
+        self.non_clustered_segments = self.clusters.take_non_clustered_segments();
         self.clusters = PriorityQueueCluster::new();
+        Ok(())
     }
 
-    pub fn fill_non_clustered_segments(&mut self, trajectory: &Trajectory) {
-        for segment in trajectory.segments_iter() {
+    pub fn fill_non_clustered_segments(&mut self, trajectory: &Trajectory, leg: &Leg) {
+        for segment in trajectory.leg_segments(leg) {
             let cluster_member: ClusterMember = ClusterMember::new_from_traj(trajectory, segment);
-            self.clusters.non_clustered_segments.push(cluster_member);
+            self.clusters.push_non_clustered_segment(cluster_member);
         }
     }
 
+    /// Approximate bytes allocated on the heap by the corridors and non-clustered segments.
+    /// The cluster queue is empty once the corridors are finalized and is not counted.
+    pub fn heap_bytes(&self) -> usize {
+        self.corridors.capacity() * size_of::<Corridor>()
+            + self
+                .corridors
+                .iter()
+                .map(|corridor| corridor.cluster.heap_bytes())
+                .sum::<usize>()
+            + self.non_clustered_segments.capacity() * size_of::<ClusterMember>()
+    }
+
     // Provides an iterator over all cluster members in all corridors, along with their corridor index
     // Corridor index is -1 for non-clustered segments
     pub fn get_all_cluster_members_iter(&self) -> impl Iterator<Item = (i32, &ClusterMember)> {
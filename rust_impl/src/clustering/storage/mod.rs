@@ -1,3 +1,5 @@
 pub mod clustered_trajectories;
 pub mod priority_queue;
 pub mod raw_trajectories;
+pub mod session_store;
+pub mod spatial_grid;
@@ -1,91 +1,211 @@
 // TODO: the sum of distances could be calculated only when needed, to optimize performance
 // Now: it's calculated incrementally when members are added for all clusters (not for cluster in a tie)
+//
+// Binary heap with lazy invalidation: a cluster whose weight changes gets a new heap entry,
+// the stale one is skipped when it reaches the top. An inverted index from each
+// (traj_id, segment_id) to the clusters holding it limits the cleaning after a pop
+// to the clusters that actually share segments with the popped one.
 use super::super::objects::{cluster::Cluster, cluster_member::ClusterMember};
-use std::{cmp::Ordering, collections::HashSet};
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+type SegmentKey = (usize, usize);
+
+// One heap entry: a snapshot of the cluster sort key when it was pushed.
+// `stamp` breaks ties and identifies the entry: it is valid only while it matches
+// the stamp currently assigned to the cluster.
+struct HeapEntry {
+    total_weight: u32,
+    sum_distance: f64,
+    stamp: i64,
+    index: usize,
+}
+
+impl Ord for HeapEntry {
+    // Greater pops first: highest weight, then lowest distance, then lowest stamp
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.total_weight
+            .cmp(&other.total_weight)
+            .then_with(|| other.sum_distance.total_cmp(&self.sum_distance))
+            .then_with(|| other.stamp.cmp(&self.stamp))
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry {}
 
 pub struct PriorityQueueCluster {
-    pub elements: Vec<Box<Cluster>>,
-    pub non_clustered_segments: Vec<ClusterMember>,
-    is_sorted: bool,
+    // Slot per pushed cluster, None once popped or removed
+    clusters: Vec<Option<Box<Cluster>>>,
+    stamps: Vec<i64>,
+    heap: BinaryHeap<HeapEntry>,
+    segment_index: HashMap<SegmentKey, Vec<usize>>,
+
+    num_clusters: usize,
+
+    non_clustered_segments: Vec<ClusterMember>,
+    used_segments: HashSet<SegmentKey>,
+
+    // Pushed clusters go after every equal cluster, clusters whose weight dropped go before:
+    // this reproduces the order of a stable sort repeated after every pop
+    next_back_stamp: i64,
+    next_front_stamp: i64,
 }
 
 impl PriorityQueueCluster {
     pub fn new() -> Self {
         Self {
-            elements: Vec::new(),
+            clusters: Vec::new(),
+            stamps: Vec::new(),
+            heap: BinaryHeap::new(),
+            segment_index: HashMap::new(),
+            num_clusters: 0,
             non_clustered_segments: Vec::new(),
-            is_sorted: false,
+            used_segments: HashSet::new(),
+            next_back_stamp: 0,
+            next_front_stamp: 0,
         }
     }
 
     pub fn push(&mut self, cluster: Cluster) {
-        self.is_sorted = false;
-        self.elements.push(Box::new(cluster));
+        let index: usize = self.clusters.len();
+
+        for member in cluster.get_all_members_iter() {
+            self.segment_index
+                .entry((member.traj_id, member.segment_id))
+                .or_default()
+                .push(index);
+        }
+
+        let stamp: i64 = self.next_back_stamp;
+        self.next_back_stamp += 1;
+
+        self.heap.push(Self::entry_for(&cluster, stamp, index));
+        self.stamps.push(stamp);
+        self.clusters.push(Some(Box::new(cluster)));
+        self.num_clusters += 1;
     }
 
-    fn compare_clusters(a: &Cluster, b: &Cluster) -> Ordering {
-        b.total_weight
-            .cmp(&a.total_weight) // descending order for weight
-            .then_with(|| {
-                a.sum_distance
-                    .partial_cmp(&b.sum_distance) // ascending order for distance
-                    .unwrap_or(Ordering::Equal)
-            })
+    /// Number of clusters still waiting in the queue.
+    pub fn num_clusters(&self) -> usize {
+        self.num_clusters
     }
 
-    fn sort_by_weight_and_distance(&mut self) {
-        self.elements
-            .sort_by(|a: &Box<Cluster>, b: &Box<Cluster>| Self::compare_clusters(a, b));
-        self.is_sorted = true;
+    pub fn push_non_clustered_segment(&mut self, segment: ClusterMember) {
+        self.non_clustered_segments.push(segment);
     }
 
-    pub fn pop_and_clean(&mut self, threshold: u32) -> Option<Box<Cluster>> {
-        if self.elements.is_empty() {
-            return None;
-        }
-        if !self.is_sorted {
-            self.sort_by_weight_and_distance();
+    /// Removes the segments used by the popped clusters and returns the others,
+    /// in the order they were pushed.
+    pub fn take_non_clustered_segments(&mut self) -> Vec<ClusterMember> {
+        let used: &HashSet<SegmentKey> = &self.used_segments;
+        self.non_clustered_segments
+            .retain(|segment| !used.contains(&(segment.traj_id, segment.segment_id)));
+        std::mem::take(&mut self.non_clustered_segments)
+    }
+
+    #[inline]
+    fn entry_for(cluster: &Cluster, stamp: i64, index: usize) -> HeapEntry {
+        HeapEntry {
+            total_weight: cluster.total_weight,
+            sum_distance: cluster.sum_distance,
+            stamp,
+            index,
         }
+    }
+
+    #[inline]
+    fn is_entry_valid(&self, entry: &HeapEntry) -> bool {
+        self.clusters[entry.index].is_some() && self.stamps[entry.index] == entry.stamp
+    }
+
+    pub fn pop_and_clean(&mut self, threshold: u32) -> Option<Box<Cluster>> {
+        let index: usize = loop {
+            let entry: HeapEntry = self.heap.pop()?;
+            if self.is_entry_valid(&entry) {
+                break entry.index;
+            }
+        };
 
-        let first: Box<Cluster> = self.elements.remove(0); // TODO: optimize later with something not O(n)
-        let used_ids: HashSet<(usize, usize)> = Self::collect_used_traj_ids(&first);
+        let first: Box<Cluster> = self.clusters[index].take().unwrap();
+        self.num_clusters -= 1;
+        let used_ids: HashSet<SegmentKey> = Self::collect_used_traj_ids(&first);
 
         self.clean_remaining_clusters(&used_ids, threshold);
-        self.clean_non_clustered_segments(&used_ids);
-        self.sort_by_weight_and_distance();
+        self.used_segments.extend(used_ids);
 
         Some(first)
     }
 
-    fn collect_used_traj_ids(cluster: &Cluster) -> HashSet<(usize, usize)> {
-        let mut set: HashSet<(usize, usize)> = HashSet::new();
-
-        set.insert((cluster.seed.cm.traj_id, cluster.seed.cm.segment_id));
+    fn collect_used_traj_ids(cluster: &Cluster) -> HashSet<SegmentKey> {
+        cluster
+            .get_all_members_iter()
+            .map(|member| (member.traj_id, member.segment_id))
+            .collect()
+    }
 
-        for member in &cluster.members {
-            set.insert((member.traj_id, member.segment_id));
+    fn clean_remaining_clusters(&mut self, used: &HashSet<SegmentKey>, threshold: u32) {
+        // Only the clusters sharing a segment with the popped one can change
+        let mut affected: Vec<usize> = Vec::new();
+        for key in used {
+            if let Some(indexes) = self.segment_index.remove(key) {
+                affected.extend(indexes);
+            }
         }
+        affected.sort_unstable();
+        affected.dedup();
 
-        set
-    }
+        // (old weight, sum_distance, old stamp, index) of the clusters whose weight dropped
+        let mut lowered: Vec<(u32, f64, i64, usize)> = Vec::new();
 
-    fn clean_remaining_clusters(&mut self, used: &HashSet<(usize, usize)>, threshold: u32) {
-        let mut remove_indexes: Vec<usize> = Vec::new();
+        for index in affected {
+            let Some(cluster) = self.clusters[index].as_mut() else {
+                continue;
+            };
+            let old_weight: u32 = cluster.total_weight;
 
-        for (index, cluster) in self.elements.iter_mut().enumerate() {
             if Self::clean_individual_cluster(cluster, used, threshold) {
-                remove_indexes.push(index);
+                self.clusters[index] = None;
+                self.num_clusters -= 1;
+            } else if cluster.total_weight != old_weight {
+                lowered.push((old_weight, cluster.sum_distance, self.stamps[index], index));
             }
         }
 
-        // Remove clusters in reverse order to avoid index shifting
-        Self::remove_reversed_indexes(&mut self.elements, &remove_indexes);
+        // Lowered clusters keep their previous relative order, ahead of the clusters already at their new weight
+        lowered.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then_with(|| a.1.total_cmp(&b.1))
+                .then_with(|| a.2.cmp(&b.2))
+        });
+        let first_stamp: i64 = self.next_front_stamp - lowered.len() as i64;
+        self.next_front_stamp = first_stamp;
+
+        for (offset, &(_, _, _, index)) in lowered.iter().enumerate() {
+            let stamp: i64 = first_stamp + offset as i64;
+            self.stamps[index] = stamp;
+            let cluster: &Cluster = self.clusters[index].as_ref().unwrap();
+            self.heap.push(Self::entry_for(cluster, stamp, index));
+        }
     }
 
     #[inline]
     fn clean_individual_cluster(
         cluster: &mut Cluster,
-        used: &HashSet<(usize, usize)>,
+        used: &HashSet<SegmentKey>,
         threshold: u32,
     ) -> bool {
         // If the seed is now used, remove the entire cluster
@@ -110,42 +230,187 @@ impl PriorityQueueCluster {
 
         // Remove clusters members in reverse order to avoid index shifting
         Self::remove_reversed_indexes(&mut cluster.members, &remove_indexes);
-        return false;
+        false
     }
 
     #[inline]
-    fn clean_non_clustered_segments(&mut self, used: &HashSet<(usize, usize)>) {
-        let mut remove_indexes: Vec<usize> = Vec::new();
+    fn remove_reversed_indexes<T>(vec: &mut Vec<T>, indexes: &[usize]) {
+        for &index in indexes.iter().rev() {
+            vec.remove(index);
+        }
+    }
 
-        // Check each non-clustered segment is now used, remove if so
-        for (index, segment) in self.non_clustered_segments.iter_mut().enumerate() {
-            if used.contains(&(segment.traj_id, segment.segment_id)) {
-                remove_indexes.push(index);
+    #[allow(unused)]
+    pub fn print_info(&self) {
+        println!("PriorityQueueCluster info:");
+        for (i, cluster) in self.clusters.iter().enumerate() {
+            if let Some(cluster) = cluster {
+                println!(
+                    "Cluster {}: seed = {}, total_weight = {}, num_members = {}",
+                    i,
+                    cluster.seed.cm.traj_id,
+                    cluster.total_weight,
+                    cluster.members.len()
+                );
             }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::geometry::point::Point;
+    use crate::clustering::objects::cluster_member::ClusterSeed;
+
+    // xorshift64, reproducible from its seed
+    struct Rng(u64);
+
+    impl Rng {
+        fn new(seed: u64) -> Self {
+            Self(seed | 1) // a zero state would stay zero
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    const NUM_TRAJS: usize = 12;
+    const NUM_SEGMENTS: usize = 4;
 
-        // Remove segments in reverse order to avoid index shifting
-        Self::remove_reversed_indexes(&mut self.non_clustered_segments, &remove_indexes);
+    fn member(traj_id: usize, segment_id: usize, weight: u32) -> ClusterMember {
+        let origin: Point = Point { x: 0.0, y: 0.0 };
+        ClusterMember::new(traj_id, segment_id, weight, origin, origin)
     }
 
-    #[inline]
-    fn remove_reversed_indexes<T>(vec: &mut Vec<T>, indexes: &Vec<usize>) {
-        for &index in indexes.iter().rev() {
-            vec.remove(index);
+    // Clusters over a small pool of segments, with few distinct weights and distances
+    // so that many of them share segments and tie on the sort key. As in a run, every
+    // pushed cluster reaches the threshold.
+    fn random_clusters(seed: u64, count: usize, threshold: u32) -> Vec<Cluster> {
+        let mut rng: Rng = Rng::new(seed);
+        let weight_of = |traj_id: usize| -> u32 { 1 + (traj_id % 3) as u32 };
+        let pick = |rng: &mut Rng| -> SegmentKey {
+            let traj_id: usize = (rng.next_u64() % NUM_TRAJS as u64) as usize;
+            let segment_id: usize = (rng.next_u64() % NUM_SEGMENTS as u64) as usize;
+            (traj_id, segment_id)
+        };
+
+        (0..count)
+            .map(|_| {
+                let (traj_id, segment_id) = pick(&mut rng);
+                let seed: ClusterSeed =
+                    ClusterSeed::new(member(traj_id, segment_id, weight_of(traj_id)), 0.0);
+                let mut cluster: Cluster = Cluster::new(seed, Vec::new());
+
+                let num_members: u64 = 1 + rng.next_u64() % 5;
+                for _ in 0..num_members {
+                    let (traj_id, segment_id) = pick(&mut rng);
+                    if !cluster.contains_traj(traj_id) {
+                        cluster
+                            .members
+                            .push(member(traj_id, segment_id, weight_of(traj_id)));
+                        cluster.total_weight += weight_of(traj_id);
+                    }
+                }
+                cluster.sum_distance = (rng.next_u64() % 3) as f64;
+                cluster
+            })
+            .filter(|cluster| cluster.total_weight >= threshold)
+            .collect()
+    }
+
+    fn seed_key(cluster: &Cluster) -> SegmentKey {
+        (cluster.seed.cm.traj_id, cluster.seed.cm.segment_id)
+    }
+
+    // Pop order of the former implementation: stable sort of the whole list after every pop
+    fn resorted_pop_order(mut clusters: Vec<Cluster>, threshold: u32) -> Vec<(SegmentKey, u32)> {
+        let mut popped: Vec<(SegmentKey, u32)> = Vec::new();
+        while !clusters.is_empty() {
+            clusters.sort_by(|a, b| {
+                b.total_weight
+                    .cmp(&a.total_weight)
+                    .then_with(|| a.sum_distance.total_cmp(&b.sum_distance))
+            });
+            let first: Cluster = clusters.remove(0);
+            popped.push((seed_key(&first), first.total_weight));
+
+            let used: HashSet<SegmentKey> = PriorityQueueCluster::collect_used_traj_ids(&first);
+            clusters.retain_mut(|cluster| {
+                !PriorityQueueCluster::clean_individual_cluster(cluster, &used, threshold)
+            });
         }
+        popped
     }
 
-    #[allow(unused)]
-    pub fn print_info(&self) {
-        println!("PriorityQueueCluster info:");
-        for (i, cluster) in self.elements.iter().enumerate() {
-            println!(
-                "Cluster {}: seed = {}, total_weight = {}, num_members = {}",
-                i,
-                cluster.seed.cm.traj_id,
-                cluster.total_weight,
-                cluster.members.len()
-            );
+    fn heap_pop_order(clusters: Vec<Cluster>, threshold: u32) -> Vec<(SegmentKey, u32)> {
+        let mut queue: PriorityQueueCluster = PriorityQueueCluster::new();
+        for cluster in clusters {
+            queue.push(cluster);
         }
+
+        let mut popped: Vec<(SegmentKey, u32)> = Vec::new();
+        while let Some(cluster) = queue.pop_and_clean(threshold) {
+            popped.push((seed_key(&cluster), cluster.total_weight));
+        }
+        assert!(queue.clusters.iter().all(Option::is_none));
+        popped
+    }
+
+    #[test]
+    fn pops_in_the_order_of_a_stable_resort() {
+        let mut rng: Rng = Rng::new(42);
+        for threshold in [1, 3, 5] {
+            for _ in 0..50 {
+                let seed: u64 = rng.next_u64();
+                let count: usize = 1 + (rng.next_u64() % 40) as usize;
+                let expected: Vec<(SegmentKey, u32)> =
+                    resorted_pop_order(random_clusters(seed, count, threshold), threshold);
+                let popped: Vec<(SegmentKey, u32)> =
+                    heap_pop_order(random_clusters(seed, count, threshold), threshold);
+                assert_eq!(popped, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn equal_clusters_pop_in_push_order() {
+        let mut queue: PriorityQueueCluster = PriorityQueueCluster::new();
+        for traj_id in 0..5 {
+            queue.push(Cluster::new(
+                ClusterSeed::new(member(traj_id, 0, 1), 0.0),
+                Vec::new(),
+            ));
+        }
+
+        let popped: Vec<usize> = std::iter::from_fn(|| queue.pop_and_clean(1))
+            .map(|cluster| cluster.seed.cm.traj_id)
+            .collect();
+        assert_eq!(popped, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn non_clustered_segments_skip_the_used_ones() {
+        let mut queue: PriorityQueueCluster = PriorityQueueCluster::new();
+        let mut cluster: Cluster = Cluster::new(ClusterSeed::new(member(0, 0, 1), 0.0), Vec::new());
+        cluster.members.push(member(1, 2, 1));
+        cluster.total_weight += 1;
+        queue.push(cluster);
+
+        for (traj_id, segment_id) in [(0, 0), (0, 1), (1, 2), (2, 0)] {
+            queue.push_non_clustered_segment(member(traj_id, segment_id, 1));
+        }
+        assert!(queue.pop_and_clean(1).is_some());
+
+        let remaining: Vec<SegmentKey> = queue
+            .take_non_clustered_segments()
+            .iter()
+            .map(|segment| (segment.traj_id, segment.segment_id))
+            .collect();
+        assert_eq!(remaining, vec![(0, 1), (2, 0)]);
     }
 }
@@ -1,4 +1,11 @@
-use super::super::geometry::trajectory::Trajectory;
+use super::super::geometry::{
+    point::Point,
+    projection::Projection,
+    trajectory::{Leg, Trajectory},
+};
+use super::spatial_grid::SpatialGrid;
+
+use rayon::prelude::*;
 
 // TODO:
 // - bucket size should be a fraction of the max angle threshold used in clustering
@@ -6,44 +13,101 @@ use super::super::geometry::trajectory::Trajectory;
 //     - Change constructor accordingly (easy)
 //     - Change iter_nearby_angle accordingly (a bit more complex)
 
+/// Leg stored in a bucket: index of its trajectory in `RawTrajectories::trajectories`,
+/// then index of the leg in that trajectory.
+#[derive(Debug, Clone, Copy)]
+pub struct LegRef {
+    pub traj: usize,
+    pub leg: usize,
+}
+
+/// Legs whose angle falls in [angle_start, angle_end[; a trajectory with legs in
+/// several directions is referenced by several buckets.
 pub struct Bucket {
     pub angle_start: f64, // (inclusive)
     pub angle_end: f64,   // (exclusive)
-    pub trajectories: Vec<Trajectory>,
+    pub legs: Vec<LegRef>,
+    spatial_grid: Option<SpatialGrid>,
+}
+
+/// Trajectory legs of the angle buckets neighbouring a seed angle.
+/// Filters them further by distance when the spatial index has been built.
+pub struct NearbyTrajectories<'a> {
+    trajectories: &'a [Trajectory],
+    buckets: Vec<&'a Bucket>,
+}
+
+impl<'a> NearbyTrajectories<'a> {
+    /// Returns the legs that may pass within `radius` of `center`, with their trajectory,
+    /// in bucket order then insertion order (the order of a full scan of the buckets).
+    pub fn query(&self, center: &Point, radius: f64) -> Vec<(&'a Trajectory, &'a Leg)> {
+        let mut result: Vec<(&'a Trajectory, &'a Leg)> = Vec::new();
+        let mut indexes: Vec<usize> = Vec::new();
+        let trajectories: &'a [Trajectory] = self.trajectories;
+        let resolve = |leg_ref: &LegRef| -> (&'a Trajectory, &'a Leg) {
+            let traj: &'a Trajectory = &trajectories[leg_ref.traj];
+            (traj, &traj.legs()[leg_ref.leg])
+        };
+
+        for bucket in &self.buckets {
+            match &bucket.spatial_grid {
+                Some(grid) => {
+                    indexes.clear();
+                    grid.query(center, radius, &mut indexes);
+                    // Sorting keeps the bucket order, so the clustering output does not change
+                    indexes.sort_unstable();
+                    indexes.dedup();
+                    result.extend(indexes.iter().map(|&i| resolve(&bucket.legs[i])));
+                }
+                None => result.extend(bucket.legs.iter().map(resolve)),
+            }
+        }
+
+        result
+    }
 }
+
 pub struct RawTrajectories {
     pub bucket_size: f64,
+    pub undirected: bool, // buckets over 0–180°, a leg and its reverse share one
+    pub trajectories: Vec<Trajectory>,
     pub traj_buckets: Vec<Bucket>,
+    pub projection: Option<Projection>, // applied to longitude/latitude input, None otherwise
 }
 
 impl RawTrajectories {
-    pub fn new(bucket_size: f64) -> Self {
-        let buckets: Vec<Bucket> = Self::create_buckets(bucket_size);
+    pub fn new(bucket_size: f64, undirected: bool) -> Self {
+        let angle_range: f64 = if undirected { 180.0 } else { 360.0 };
+        let buckets: Vec<Bucket> = Self::create_buckets(bucket_size, angle_range);
 
         Self {
             bucket_size,
+            undirected,
+            trajectories: Vec::new(),
             traj_buckets: buckets,
+            projection: None,
         }
     }
 
-    fn create_buckets(bucket_size: f64) -> Vec<Bucket> {
-        assert!(bucket_size > 0.0 && bucket_size <= 360.0);
+    fn create_buckets(bucket_size: f64, angle_range: f64) -> Vec<Bucket> {
+        assert!(bucket_size > 0.0 && bucket_size <= angle_range);
 
-        let num_buckets: usize = (360.0 / bucket_size).ceil() as usize;
+        let num_buckets: usize = (angle_range / bucket_size).ceil() as usize;
         let mut buckets: Vec<Bucket> = Vec::with_capacity(num_buckets);
 
         for i in 0..num_buckets {
             let angle_start: f64 = i as f64 * bucket_size;
             let mut angle_end: f64 = angle_start + bucket_size;
 
-            if angle_end > 360.0 {
-                angle_end = 360.0;
+            if angle_end > angle_range {
+                angle_end = angle_range;
             }
 
             buckets.push(Bucket {
                 angle_start,
                 angle_end,
-                trajectories: Vec::new(),
+                legs: Vec::new(),
+                spatial_grid: None,
             });
         }
 
@@ -52,24 +116,76 @@ impl RawTrajectories {
 
     #[inline]
     fn angle_to_bucket(&self, angle: f64) -> usize {
-        let mut a: f64 = angle % 360.0;
+        let angle_range: f64 = self.angle_range();
+        let mut a: f64 = angle % angle_range;
         if a < 0.0 {
-            a += 360.0;
+            a += angle_range;
         }
         (a / self.bucket_size).floor() as usize
     }
 
+    /// Angles covered by the buckets: 360°, or 180° when undirected.
+    pub fn angle_range(&self) -> f64 {
+        if self.undirected { 180.0 } else { 360.0 }
+    }
+
+    /// Stores `traj` and adds each of its legs to the bucket of the leg angle.
     pub fn add_trajectory(&mut self, traj: Trajectory) {
-        let bucket_idx: usize = self.angle_to_bucket(traj.angle);
+        let traj_idx: usize = self.trajectories.len();
 
-        if let Some(bucket) = self.traj_buckets.get_mut(bucket_idx) {
-            bucket.trajectories.push(traj);
-        } else {
-            panic!("Bucket index {bucket_idx} does not exist");
+        for leg in traj.legs() {
+            let bucket_idx: usize = self.angle_to_bucket(leg.angle);
+
+            if let Some(bucket) = self.traj_buckets.get_mut(bucket_idx) {
+                bucket.legs.push(LegRef {
+                    traj: traj_idx,
+                    leg: leg.index,
+                });
+                bucket.spatial_grid = None; // index is stale, must be rebuilt
+            } else {
+                panic!("Bucket index {bucket_idx} does not exist");
+            }
         }
+
+        self.trajectories.push(traj);
+    }
+
+    pub fn leg(&self, leg_ref: LegRef) -> (&Trajectory, &Leg) {
+        let traj: &Trajectory = &self.trajectories[leg_ref.traj];
+        (traj, &traj.legs()[leg_ref.leg])
+    }
+
+    /// Legs of `bucket`, with their trajectory, in insertion order.
+    pub fn bucket_legs<'a>(
+        &'a self,
+        bucket: &'a Bucket,
+    ) -> impl Iterator<Item = (&'a Trajectory, &'a Leg)> {
+        bucket.legs.iter().map(|&leg_ref| self.leg(leg_ref))
+    }
+
+    /// Builds the spatial grid of every bucket, unless it already exists with this cell size.
+    /// A cell size close to the clustering max_dist keeps the queries to a few cells; the
+    /// grid of a bucket whose legs are long for it falls back to coarser cells.
+    pub fn build_spatial_index(&mut self, cell_size: f64) {
+        let trajectories: &[Trajectory] = &self.trajectories;
+        self.traj_buckets.par_iter_mut().for_each(|bucket| {
+            let is_up_to_date: bool = bucket
+                .spatial_grid
+                .as_ref()
+                .is_some_and(|grid| grid.requested_cell_size() == cell_size);
+
+            if !is_up_to_date {
+                let lines = bucket.legs.iter().map(|leg_ref| {
+                    let leg: &Leg = &trajectories[leg_ref.traj].legs()[leg_ref.leg];
+                    (&leg.start, &leg.end)
+                });
+                bucket.spatial_grid = Some(SpatialGrid::build(lines, cell_size));
+            }
+        });
     }
 
-    pub fn iter_nearby_angle(&self, angle: f64) -> impl Iterator<Item = &Trajectory> {
+    /// Indexes of the buckets that can hold trajectories within max_angle of `angle`.
+    pub fn nearby_bucket_indices(&self, angle: f64) -> Vec<usize> {
         let idx: usize = self.angle_to_bucket(angle);
         let last: usize = self.traj_buckets.len() - 1;
 
@@ -98,29 +214,67 @@ impl RawTrajectories {
         }
 
         indices
-            .into_iter()
-            .flat_map(move |i| self.traj_buckets[i].trajectories.iter())
+    }
+
+    pub fn nearby_angle_buckets(&self, angle: f64) -> NearbyTrajectories<'_> {
+        NearbyTrajectories {
+            trajectories: &self.trajectories,
+            buckets: self
+                .nearby_bucket_indices(angle)
+                .into_iter()
+                .map(|i| &self.traj_buckets[i])
+                .collect(),
+        }
     }
 
     pub fn get_total_trajectories(&self) -> usize {
-        self.traj_buckets.iter().map(|b| b.trajectories.len()).sum()
+        self.trajectories.len()
+    }
+
+    /// Number of legs in the buckets, the seeds of a clustering run.
+    pub fn get_total_legs(&self) -> usize {
+        self.traj_buckets.iter().map(|b| b.legs.len()).sum()
+    }
+
+    /// Approximate bytes allocated on the heap: trajectories, their legs and segments,
+    /// the bucket references and the spatial index, whose size is bounded by SpatialGrid.
+    pub fn heap_bytes(&self) -> usize {
+        let trajectories: usize = self.trajectories.capacity() * size_of::<Trajectory>()
+            + self
+                .trajectories
+                .iter()
+                .map(Trajectory::heap_bytes)
+                .sum::<usize>();
+        let buckets: usize = self.traj_buckets.capacity() * size_of::<Bucket>();
+        let contents: usize = self
+            .traj_buckets
+            .iter()
+            .map(|bucket| {
+                bucket.legs.capacity() * size_of::<LegRef>()
+                    + bucket
+                        .spatial_grid
+                        .as_ref()
+                        .map_or(0, SpatialGrid::heap_bytes)
+            })
+            .sum();
+        trajectories + buckets + contents
     }
     #[allow(unused)]
     pub fn print_info(&self) {
         for (i, bucket) in self.traj_buckets.iter().enumerate() {
-            // if bucket.trajectories.len() == 0 {
+            // if bucket.legs.len() == 0 {
             //     continue;
             // }
             println!(
-                "Bucket {}: Angle [{:.2}, {:.2}[ - {} trajectories",
+                "Bucket {}: Angle [{:.2}, {:.2}[ - {} legs",
                 i,
                 bucket.angle_start,
                 bucket.angle_end,
-                bucket.trajectories.len()
+                bucket.legs.len()
             );
 
-            for traj in &bucket.trajectories {
-                println!("  {}", traj.print_info());
+            for (traj, leg) in self.bucket_legs(bucket) {
+                println!("  leg {} of {}", leg.index, traj.print_info());
             }
         }
     }
@@ -0,0 +1,460 @@
+// session_store.rs - Loaded datasets and the clustering runs computed on each of them
+//
+// Every loaded input is a dataset, keyed by the DatasetId the store assigns on insertion.
+// Every clustering of a dataset is a run, keyed by a RunId chosen by the caller (the GUI
+// uses its parameter row ids), so rerunning a row replaces its previous run.
+// Dropping a dataset drops its runs. Memory figures are estimates of the heap held by an entry.
+// A dataset remembers the parameters it was loaded with: the bucket size and the segments
+// of its storage only fit a run with the same ones.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::clustered_trajectories::ClusteredTrajectories;
+use super::raw_trajectories::RawTrajectories;
+use crate::clustering::comparison::{ComparisonReport, RunSnapshot, compare_runs};
+use crate::io::args::{InputArgs, PartitionOptions, TraclusArgs};
+
+pub type DatasetId = usize;
+pub type RunId = usize;
+
+/// A run of one dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RunKey {
+    pub dataset: DatasetId,
+    pub run: RunId,
+}
+
+// ─────────────────────────────────────────────
+// Entries
+// ─────────────────────────────────────────────
+
+/// Parameters of a run that shape the RawTrajectories it clusters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadParams {
+    pub file: String,
+    pub input: InputArgs, // format, columns, sanitation and CRS
+    pub segment_size: f64,
+    pub max_angle: f64, // bucket size
+    pub undirected: bool,
+    pub partition: PartitionOptions,
+}
+
+impl LoadParams {
+    pub fn of(args: &TraclusArgs) -> Self {
+        Self {
+            file: args.file.clone(),
+            input: args.input.clone(),
+            segment_size: args.segment_size,
+            max_angle: args.max_angle,
+            undirected: args.undirected,
+            partition: args.partition.clone(),
+        }
+    }
+
+    /// Same input lines in the same coordinates: only the buckets and segments may differ.
+    pub fn same_lines(&self, other: &LoadParams) -> bool {
+        self.file == other.file && self.input == other.input
+    }
+}
+
+pub struct Dataset {
+    pub raw_storage: RawTrajectories,
+    pub source: String, // file the trajectories were loaded from
+    pub load_params: LoadParams,
+    pub loading_time: Duration,
+    runs: BTreeMap<RunId, ClusteringRun>,
+}
+
+impl Dataset {
+    pub fn memory_bytes(&self) -> usize {
+        size_of::<RawTrajectories>() + self.raw_storage.heap_bytes()
+    }
+
+    /// Replaces the storage by the same lines loaded with other buckets or segments.
+    /// The runs are kept: each holds its own segments, and the lines they were cut from
+    /// are unchanged.
+    pub fn reload(
+        &mut self,
+        raw_storage: RawTrajectories,
+        load_params: LoadParams,
+        loading_time: Duration,
+    ) {
+        debug_assert!(self.load_params.same_lines(&load_params));
+        self.raw_storage = raw_storage;
+        self.load_params = load_params;
+        self.loading_time = loading_time;
+    }
+}
+
+// One clustering of a dataset, with the parameters it was computed with
+pub struct ClusteringRun {
+    pub clust_storage: ClusteredTrajectories,
+    pub args: TraclusArgs,
+    pub clustering_time: Duration, // spatial index and cluster discovery
+    pub finalization_time: Duration, // corridors and non-clustered segments
+}
+
+impl ClusteringRun {
+    pub fn memory_bytes(&self) -> usize {
+        size_of::<ClusteredTrajectories>() + self.clust_storage.heap_bytes()
+    }
+}
+
+// ─────────────────────────────────────────────
+// Listing and comparison
+// ─────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+pub struct DatasetInfo {
+    pub id: DatasetId,
+    pub source: String,
+    pub traj_count: usize,
+    pub loading_time: Duration,
+    pub memory_bytes: usize, // raw storage only, the runs count their own
+    pub runs: Vec<RunInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RunInfo {
+    pub key: RunKey,
+    pub args: TraclusArgs,
+    pub total_corridors: usize,
+    pub total_seg: usize,
+    pub total_seg_outside_corridor: usize,
+    pub clustering_time: Duration, // discovery and finalization
+    pub memory_bytes: usize,
+}
+
+impl RunInfo {
+    fn new(key: RunKey, run: &ClusteringRun) -> Self {
+        Self {
+            key,
+            args: run.args.clone(),
+            total_corridors: run.clust_storage.corridors.len(),
+            total_seg: run.clust_storage.get_all_cluster_members_iter().count(),
+            total_seg_outside_corridor: run.clust_storage.non_clustered_segments.len(),
+            clustering_time: run.clustering_time + run.finalization_time,
+            memory_bytes: run.memory_bytes(),
+        }
+    }
+}
+
+/// Two runs side by side, with the segment and corridor comparison of B against A.
+#[derive(Debug, Clone)]
+pub struct RunComparison {
+    pub a: RunInfo,
+    pub b: RunInfo,
+    pub same_dataset: bool, // segments of both runs cut the same way from the same trajectories
+    pub report: ComparisonReport,
+}
+
+// ─────────────────────────────────────────────
+// SessionStore
+// ─────────────────────────────────────────────
+
+#[derive(Default)]
+pub struct SessionStore {
+    datasets: BTreeMap<DatasetId, Dataset>,
+    next_dataset_id: DatasetId,
+}
+
+impl SessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a loaded input and returns its new id; ids are never reused.
+    pub fn insert_dataset(
+        &mut self,
+        raw_storage: RawTrajectories,
+        load_params: LoadParams,
+        loading_time: Duration,
+    ) -> DatasetId {
+        let id: DatasetId = self.next_dataset_id;
+        self.next_dataset_id += 1;
+        self.datasets.insert(
+            id,
+            Dataset {
+                raw_storage,
+                source: load_params.file.clone(),
+                load_params,
+                loading_time,
+                runs: BTreeMap::new(),
+            },
+        );
+        id
+    }
+
+    /// Stores `run` under `key`, replacing the previous run of that key.
+    /// Returns false, dropping `run`, when the dataset does not exist.
+    pub fn insert_run(&mut self, key: RunKey, run: ClusteringRun) -> bool {
+        match self.datasets.get_mut(&key.dataset) {
+            Some(dataset) => {
+                dataset.runs.insert(key.run, run);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn dataset(&self, id: DatasetId) -> Option<&Dataset> {
+        self.datasets.get(&id)
+    }
+
+    pub fn dataset_mut(&mut self, id: DatasetId) -> Option<&mut Dataset> {
+        self.datasets.get_mut(&id)
+    }
+
+    pub fn run(&self, key: RunKey) -> Option<&ClusteringRun> {
+        self.datasets.get(&key.dataset)?.runs.get(&key.run)
+    }
+
+    /// Drops a dataset and all its runs. Returns false if it did not exist.
+    pub fn drop_dataset(&mut self, id: DatasetId) -> bool {
+        self.datasets.remove(&id).is_some()
+    }
+
+    /// Returns false if the run did not exist.
+    pub fn drop_run(&mut self, key: RunKey) -> bool {
+        self.datasets
+            .get_mut(&key.dataset)
+            .is_some_and(|dataset| dataset.runs.remove(&key.run).is_some())
+    }
+
+    /// Every dataset with its runs, by increasing id.
+    pub fn list(&self) -> Vec<DatasetInfo> {
+        self.datasets
+            .iter()
+            .map(|(&id, dataset)| DatasetInfo {
+                id,
+                source: dataset.source.clone(),
+                traj_count: dataset.raw_storage.get_total_trajectories(),
+                loading_time: dataset.loading_time,
+                memory_bytes: dataset.memory_bytes(),
+                runs: dataset
+                    .runs
+                    .iter()
+                    .map(|(&run, clustering)| RunInfo::new(RunKey { dataset: id, run }, clustering))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// None if either run does not exist.
+    pub fn compare(&self, a: RunKey, b: RunKey) -> Option<RunComparison> {
+        let (run_a, run_b) = (self.run(a)?, self.run(b)?);
+        let report: ComparisonReport = compare_runs(
+            &RunSnapshot::from_clust_storage(&run_a.clust_storage),
+            &RunSnapshot::from_clust_storage(&run_b.clust_storage),
+        );
+
+        Some(RunComparison {
+            a: RunInfo::new(a, run_a),
+            b: RunInfo::new(b, run_b),
+            same_dataset: a.dataset == b.dataset
+                && LoadParams::of(&run_a.args) == LoadParams::of(&run_b.args),
+            report,
+        })
+    }
+
+    /// Estimated heap held by every dataset and run.
+    pub fn memory_bytes(&self) -> usize {
+        self.datasets
+            .values()
+            .map(|dataset| {
+                dataset.memory_bytes()
+                    + dataset
+                        .runs
+                        .values()
+                        .map(ClusteringRun::memory_bytes)
+                        .sum::<usize>()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::args::InputCrs;
+
+    fn raw_storage() -> RawTrajectories {
+        RawTrajectories::new(30.0, false)
+    }
+
+    fn loaded(file: &str) -> LoadParams {
+        LoadParams::of(&TraclusArgs {
+            file: file.to_string(),
+            ..TraclusArgs::default()
+        })
+    }
+
+    fn clustering_run(max_dist: f64) -> ClusteringRun {
+        ClusteringRun {
+            clust_storage: ClusteredTrajectories::new(),
+            args: TraclusArgs {
+                max_dist,
+                ..TraclusArgs::default()
+            },
+            clustering_time: Duration::from_secs(2),
+            finalization_time: Duration::from_secs(1),
+        }
+    }
+
+    fn key(dataset: DatasetId, run: RunId) -> RunKey {
+        RunKey { dataset, run }
+    }
+
+    #[test]
+    fn dataset_ids_are_never_reused() {
+        let mut store: SessionStore = SessionStore::new();
+        let first: DatasetId = store.insert_dataset(raw_storage(), loaded("a.txt"), Duration::ZERO);
+        assert!(store.drop_dataset(first));
+        let second: DatasetId =
+            store.insert_dataset(raw_storage(), loaded("b.txt"), Duration::ZERO);
+
+        assert_ne!(first, second);
+        assert!(store.dataset(first).is_none());
+        assert_eq!(store.dataset(second).unwrap().source, "b.txt");
+        assert!(!store.drop_dataset(first));
+    }
+
+    #[test]
+    fn rerun_replaces_the_previous_run() {
+        let mut store: SessionStore = SessionStore::new();
+        let id: DatasetId = store.insert_dataset(raw_storage(), loaded("a.txt"), Duration::ZERO);
+
+        assert!(store.insert_run(key(id, 7), clustering_run(50.0)));
+        assert!(store.insert_run(key(id, 7), clustering_run(80.0)));
+        assert_eq!(store.run(key(id, 7)).unwrap().args.max_dist, 80.0);
+        assert_eq!(store.list()[0].runs.len(), 1);
+    }
+
+    #[test]
+    fn runs_need_their_dataset() {
+        let mut store: SessionStore = SessionStore::new();
+        assert!(!store.insert_run(key(3, 0), clustering_run(50.0)));
+        assert!(store.run(key(3, 0)).is_none());
+        assert!(!store.drop_run(key(3, 0)));
+    }
+
+    #[test]
+    fn dropping_a_dataset_drops_its_runs() {
+        let mut store: SessionStore = SessionStore::new();
+        let a: DatasetId = store.insert_dataset(raw_storage(), loaded("a.txt"), Duration::ZERO);
+        let b: DatasetId = store.insert_dataset(raw_storage(), loaded("b.txt"), Duration::ZERO);
+        store.insert_run(key(a, 0), clustering_run(50.0));
+        store.insert_run(key(b, 0), clustering_run(50.0));
+
+        store.drop_dataset(a);
+        assert!(store.run(key(a, 0)).is_none());
+        assert!(store.run(key(b, 0)).is_some());
+
+        let listed: Vec<DatasetId> = store.list().iter().map(|info| info.id).collect();
+        assert_eq!(listed, vec![b]);
+    }
+
+    #[test]
+    fn list_reports_runs_in_key_order() {
+        let mut store: SessionStore = SessionStore::new();
+        let id: DatasetId = store.insert_dataset(raw_storage(), loaded("a.txt"), Duration::ZERO);
+        for run in [4, 1, 9] {
+            store.insert_run(key(id, run), clustering_run(50.0));
+        }
+
+        let info: &DatasetInfo = &store.list()[0];
+        let runs: Vec<RunId> = info.runs.iter().map(|run| run.key.run).collect();
+        assert_eq!(runs, vec![1, 4, 9]);
+        assert_eq!(info.runs[0].clustering_time, Duration::from_secs(3));
+        assert_eq!(info.traj_count, 0);
+    }
+
+    #[test]
+    fn memory_grows_with_every_entry() {
+        let mut store: SessionStore = SessionStore::new();
+        assert_eq!(store.memory_bytes(), 0);
+        let id: DatasetId = store.insert_dataset(raw_storage(), loaded("a.txt"), Duration::ZERO);
+        let with_dataset: usize = store.memory_bytes();
+        store.insert_run(key(id, 0), clustering_run(50.0));
+        assert!(with_dataset > 0 && store.memory_bytes() > with_dataset);
+    }
+
+    #[test]
+    fn compare_needs_both_runs() {
+        let mut store: SessionStore = SessionStore::new();
+        let a: DatasetId = store.insert_dataset(raw_storage(), loaded("a.txt"), Duration::ZERO);
+        let b: DatasetId = store.insert_dataset(raw_storage(), loaded("b.txt"), Duration::ZERO);
+        store.insert_run(key(a, 0), clustering_run(50.0));
+        store.insert_run(key(a, 1), clustering_run(80.0));
+        store.insert_run(key(b, 0), clustering_run(50.0));
+
+        assert!(store.compare(key(a, 0), key(a, 5)).is_none());
+        assert!(store.compare(key(a, 0), key(a, 1)).unwrap().same_dataset);
+        let across: RunComparison = store.compare(key(a, 0), key(b, 0)).unwrap();
+        assert!(!across.same_dataset);
+        assert_eq!(across.b.args.max_dist, 50.0);
+    }
+
+    #[test]
+    fn segmentation_parameters_change_the_load_but_not_the_lines() {
+        let base: TraclusArgs = TraclusArgs::default();
+        let resegmented: TraclusArgs = TraclusArgs {
+            segment_size: base.segment_size * 2.0,
+            max_angle: base.max_angle / 2.0,
+            ..base.clone()
+        };
+        let (a, b): (LoadParams, LoadParams) =
+            (LoadParams::of(&base), LoadParams::of(&resegmented));
+        assert_ne!(a, b);
+        assert!(a.same_lines(&b));
+
+        // Other clustering parameters leave the storage as it is
+        let denser: TraclusArgs = TraclusArgs {
+            max_dist: base.max_dist * 2.0,
+            min_density: base.min_density + 1,
+            ..base.clone()
+        };
+        assert_eq!(LoadParams::of(&denser), a);
+
+        let mut projected: TraclusArgs = base.clone();
+        projected.input.crs.input_crs = InputCrs::Wgs84;
+        assert!(!LoadParams::of(&projected).same_lines(&a));
+        assert!(!loaded("other.txt").same_lines(&a));
+    }
+
+    #[test]
+    fn reload_keeps_the_runs_of_the_dataset() {
+        let mut store: SessionStore = SessionStore::new();
+        let id: DatasetId = store.insert_dataset(raw_storage(), loaded("a.txt"), Duration::ZERO);
+        store.insert_run(key(id, 0), clustering_run(50.0));
+
+        let params: LoadParams = LoadParams {
+            segment_size: 10.0,
+            ..loaded("a.txt")
+        };
+        let dataset: &mut Dataset = store.dataset_mut(id).unwrap();
+        dataset.reload(
+            RawTrajectories::new(10.0, false),
+            params.clone(),
+            Duration::from_secs(4),
+        );
+
+        let dataset: &Dataset = store.dataset(id).unwrap();
+        assert_eq!(dataset.load_params, params);
+        assert_eq!(dataset.loading_time, Duration::from_secs(4));
+        assert!(store.run(key(id, 0)).is_some());
+    }
+
+    #[test]
+    fn runs_cut_differently_are_not_the_same_dataset() {
+        let mut store: SessionStore = SessionStore::new();
+        let id: DatasetId = store.insert_dataset(raw_storage(), loaded("a.txt"), Duration::ZERO);
+        let mut resegmented: ClusteringRun = clustering_run(50.0);
+        resegmented.args.segment_size *= 2.0;
+        store.insert_run(key(id, 0), clustering_run(50.0));
+        store.insert_run(key(id, 1), resegmented);
+
+        assert!(!store.compare(key(id, 0), key(id, 1)).unwrap().same_dataset);
+    }
+}
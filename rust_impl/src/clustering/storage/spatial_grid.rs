@@ -0,0 +1,357 @@
+// spatial_grid.rs - Uniform grid over trajectory legs, second index level inside each angle bucket
+//
+// Every leg is registered in all the cells its start→end line crosses.
+// A query with a radius around a point only visits the cells overlapping that
+// circle's bounding square, so legs kilometres away are never returned.
+//
+// One entry per crossed cell makes the memory grow with the total length of the lines
+// over the cell size. Past MAX_CELLS_PER_LINE entries per line on average, the cells are
+// doubled until the lines fit: queries then return more false positives, never fewer legs.
+
+use std::collections::HashMap;
+
+use super::super::geometry::point::Point;
+
+type CellKey = (i64, i64);
+
+// Average cell entries per line beyond which the grid falls back to coarser cells
+const MAX_CELLS_PER_LINE: f64 = 64.0;
+
+pub struct SpatialGrid {
+    requested_cell_size: f64,
+    cell_size: f64, // requested_cell_size, or a coarser one for long lines
+    cells: HashMap<CellKey, Vec<usize>>,
+    // Lines with non-finite coordinates cannot be placed in a cell:
+    // they are returned by every query so the distance test decides for them
+    unplaced: Vec<usize>,
+}
+
+impl SpatialGrid {
+    /// Builds the grid over the start→end `lines`; stored values are positions in that sequence.
+    /// The cells are those of `cell_size`, doubled as often as needed to keep the lines
+    /// within MAX_CELLS_PER_LINE cells on average.
+    pub fn build<'a>(lines: impl Iterator<Item = (&'a Point, &'a Point)>, cell_size: f64) -> Self {
+        assert!(cell_size > 0.0);
+        let lines: Vec<(&Point, &Point)> = lines.collect();
+
+        let max_cells: f64 = MAX_CELLS_PER_LINE * lines.len() as f64;
+        let mut coarse_cell_size: f64 = cell_size;
+        while total_cells_crossed(&lines, coarse_cell_size) > max_cells {
+            coarse_cell_size *= 2.0;
+        }
+
+        let mut grid: SpatialGrid = Self {
+            requested_cell_size: cell_size,
+            cell_size: coarse_cell_size,
+            cells: HashMap::new(),
+            unplaced: Vec::new(),
+        };
+
+        for (index, (start, end)) in lines.into_iter().enumerate() {
+            grid.insert_line(index, start, end);
+        }
+
+        grid
+    }
+
+    /// Cell size the grid was built for, whatever the cells it fell back to.
+    pub fn requested_cell_size(&self) -> f64 {
+        self.requested_cell_size
+    }
+
+    /// Approximate bytes allocated on the heap: cell table, then the index lists.
+    /// The lists hold one entry per cell a line crosses, so they grow with the length of
+    /// the lines over the cell size, up to MAX_CELLS_PER_LINE entries per line on average.
+    pub fn heap_bytes(&self) -> usize {
+        let table: usize = self.cells.capacity() * size_of::<(CellKey, Vec<usize>)>();
+        let lists: usize = self
+            .cells
+            .values()
+            .map(|list| list.capacity())
+            .sum::<usize>()
+            + self.unplaced.capacity();
+        table + lists * size_of::<usize>()
+    }
+
+    #[inline]
+    fn cell_of(&self, point: &Point) -> CellKey {
+        (
+            (point.x / self.cell_size).floor() as i64,
+            (point.y / self.cell_size).floor() as i64,
+        )
+    }
+
+    // Registers the line in every cell it crosses (Amanatides & Woo voxel traversal).
+    fn insert_line(&mut self, index: usize, start: &Point, end: &Point) {
+        let finite: bool =
+            start.x.is_finite() && start.y.is_finite() && end.x.is_finite() && end.y.is_finite();
+        if !finite {
+            self.unplaced.push(index);
+            return;
+        }
+
+        let (mut cx, mut cy) = self.cell_of(start);
+        let (ex, ey) = self.cell_of(end);
+
+        let dx: f64 = end.x - start.x;
+        let dy: f64 = end.y - start.y;
+        let step_x: i64 = if dx >= 0.0 { 1 } else { -1 };
+        let step_y: i64 = if dy >= 0.0 { 1 } else { -1 };
+
+        let t_delta_x: f64 = if dx != 0.0 {
+            self.cell_size / dx.abs()
+        } else {
+            f64::INFINITY
+        };
+        let t_delta_y: f64 = if dy != 0.0 {
+            self.cell_size / dy.abs()
+        } else {
+            f64::INFINITY
+        };
+
+        let next_border = |c: i64, step: i64| -> f64 {
+            if step > 0 {
+                (c + 1) as f64 * self.cell_size
+            } else {
+                c as f64 * self.cell_size
+            }
+        };
+        let mut t_max_x: f64 = if dx != 0.0 {
+            (next_border(cx, step_x) - start.x) / dx
+        } else {
+            f64::INFINITY
+        };
+        let mut t_max_y: f64 = if dy != 0.0 {
+            (next_border(cy, step_y) - start.y) / dy
+        } else {
+            f64::INFINITY
+        };
+
+        // The number of steps is fixed by the end cell, which keeps the walk
+        // finite even when rounding makes t_max disagree with the end cell
+        let num_steps: i64 = (ex - cx).abs() + (ey - cy).abs();
+        self.cells.entry((cx, cy)).or_default().push(index);
+
+        for _ in 0..num_steps {
+            let must_step_y: bool = cx == ex;
+            let must_step_x: bool = cy == ey;
+
+            if must_step_x || (!must_step_y && t_max_x < t_max_y) {
+                cx += step_x;
+                t_max_x += t_delta_x;
+            } else {
+                cy += step_y;
+                t_max_y += t_delta_y;
+            }
+            self.cells.entry((cx, cy)).or_default().push(index);
+        }
+    }
+
+    /// Appends to `out` the index of every line that may lie within `radius` of `center`.
+    /// The result can contain duplicates and false positives, never false negatives.
+    pub fn query(&self, center: &Point, radius: f64, out: &mut Vec<usize>) {
+        out.extend_from_slice(&self.unplaced);
+
+        // Small padding so lines running exactly on a cell border are not missed
+        let radius: f64 = radius + self.cell_size * 1e-9 + 1e-9;
+        let min_x: f64 = ((center.x - radius) / self.cell_size).floor();
+        let max_x: f64 = ((center.x + radius) / self.cell_size).floor();
+        let min_y: f64 = ((center.y - radius) / self.cell_size).floor();
+        let max_y: f64 = ((center.y + radius) / self.cell_size).floor();
+
+        let num_query_cells: f64 = (max_x - min_x + 1.0) * (max_y - min_y + 1.0);
+
+        // Large radius compared to the cell size: scanning the occupied cells is cheaper
+        if !num_query_cells.is_finite() || num_query_cells > self.cells.len() as f64 {
+            for (&(x, y), indexes) in &self.cells {
+                let (x, y) = (x as f64, y as f64);
+                if x >= min_x && x <= max_x && y >= min_y && y <= max_y {
+                    out.extend_from_slice(indexes);
+                }
+            }
+            return;
+        }
+
+        for x in (min_x as i64)..=(max_x as i64) {
+            for y in (min_y as i64)..=(max_y as i64) {
+                if let Some(indexes) = self.cells.get(&(x, y)) {
+                    out.extend_from_slice(indexes);
+                }
+            }
+        }
+    }
+}
+
+// Entries the lines would take in cells of `cell_size`: the cells walked from the start
+// cell to the end cell of each finite line. Counted in f64 so far-off points cannot overflow.
+fn total_cells_crossed(lines: &[(&Point, &Point)], cell_size: f64) -> f64 {
+    let cell = |v: f64| -> f64 { (v / cell_size).floor() };
+    lines
+        .iter()
+        .filter(|(start, end)| {
+            start.x.is_finite() && start.y.is_finite() && end.x.is_finite() && end.y.is_finite()
+        })
+        .map(|(start, end)| {
+            (cell(end.x) - cell(start.x)).abs() + (cell(end.y) - cell(start.y)).abs() + 1.0
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    fn grid_of(lines: &[(Point, Point)], cell_size: f64) -> SpatialGrid {
+        SpatialGrid::build(lines.iter().map(|(start, end)| (start, end)), cell_size)
+    }
+
+    fn cells_of(grid: &SpatialGrid, index: usize) -> Vec<CellKey> {
+        let mut cells: Vec<CellKey> = grid
+            .cells
+            .iter()
+            .filter(|(_, indexes)| indexes.contains(&index))
+            .map(|(&cell, _)| cell)
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    fn distance_to_line(p: &Point, start: &Point, end: &Point) -> f64 {
+        let (dx, dy): (f64, f64) = (end.x - start.x, end.y - start.y);
+        let length_sq: f64 = dx * dx + dy * dy;
+        let t: f64 = if length_sq == 0.0 {
+            0.0
+        } else {
+            (((p.x - start.x) * dx + (p.y - start.y) * dy) / length_sq).clamp(0.0, 1.0)
+        };
+        (p.x - (start.x + t * dx)).hypot(p.y - (start.y + t * dy))
+    }
+
+    #[test]
+    fn horizontal_line_is_in_each_cell_it_crosses() {
+        let grid: SpatialGrid = grid_of(&[(point(0.5, 0.5), point(2.5, 0.5))], 1.0);
+        assert_eq!(cells_of(&grid, 0), vec![(0, 0), (1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn diagonal_line_steps_through_adjacent_cells() {
+        // Leaves (0, 0) through its right border at y = 0.7 and stays in the first row
+        let grid: SpatialGrid = grid_of(&[(point(0.5, 0.5), point(1.5, 0.9))], 1.0);
+        assert_eq!(cells_of(&grid, 0), vec![(0, 0), (1, 0)]);
+
+        // Crosses x = 1 at y = 0.8 before y = 1 at x ≈ 1.22: (0, 1) is never entered
+        let grid: SpatialGrid = grid_of(&[(point(0.2, 0.1), point(1.8, 1.5))], 1.0);
+        assert_eq!(cells_of(&grid, 0), vec![(0, 0), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn line_in_negative_coordinates_walks_backwards() {
+        let grid: SpatialGrid = grid_of(&[(point(-0.5, -0.5), point(-3.5, -0.5))], 1.0);
+        assert_eq!(
+            cells_of(&grid, 0),
+            vec![(-4, -1), (-3, -1), (-2, -1), (-1, -1)]
+        );
+    }
+
+    #[test]
+    fn point_line_is_in_one_cell() {
+        let grid: SpatialGrid = grid_of(&[(point(3.2, 4.7), point(3.2, 4.7))], 2.0);
+        assert_eq!(cells_of(&grid, 0), vec![(1, 2)]);
+    }
+
+    #[test]
+    fn long_lines_fall_back_to_coarser_cells() {
+        let lines: Vec<(Point, Point)> = vec![
+            (point(0.0, 0.0), point(100_000.0, 0.0)),
+            (point(0.0, 10.0), point(0.0, 100_000.0)),
+        ];
+        let grid: SpatialGrid = grid_of(&lines, 1.0);
+
+        assert_eq!(grid.requested_cell_size(), 1.0);
+        assert!(grid.cell_size >= 100_000.0 / MAX_CELLS_PER_LINE);
+        let entries: usize = grid.cells.values().map(Vec::len).sum();
+        assert!(entries as f64 <= MAX_CELLS_PER_LINE * lines.len() as f64);
+
+        let mut out: Vec<usize> = Vec::new();
+        grid.query(&point(50_000.0, 0.5), 1.0, &mut out);
+        assert!(out.contains(&0));
+    }
+
+    #[test]
+    fn short_lines_keep_the_requested_cells() {
+        let grid: SpatialGrid = grid_of(&[(point(0.0, 0.0), point(30.0, 0.0))], 1.0);
+        assert_eq!(grid.cell_size, 1.0);
+    }
+
+    #[test]
+    fn query_skips_lines_far_from_the_circle() {
+        let lines: Vec<(Point, Point)> = vec![
+            (point(0.0, 0.0), point(10.0, 0.0)),
+            (point(0.0, 100.0), point(10.0, 100.0)),
+        ];
+        let grid: SpatialGrid = grid_of(&lines, 5.0);
+
+        let mut out: Vec<usize> = Vec::new();
+        grid.query(&point(5.0, 3.0), 4.0, &mut out);
+        out.sort_unstable();
+        out.dedup();
+        assert_eq!(out, vec![0]);
+    }
+
+    #[test]
+    fn non_finite_lines_are_returned_by_every_query() {
+        let lines: Vec<(Point, Point)> = vec![
+            (point(0.0, 0.0), point(1.0, 0.0)),
+            (point(f64::NAN, 0.0), point(1.0, 1.0)),
+        ];
+        let grid: SpatialGrid = grid_of(&lines, 1.0);
+
+        let mut out: Vec<usize> = Vec::new();
+        grid.query(&point(1000.0, 1000.0), 1.0, &mut out);
+        assert_eq!(out, vec![1]);
+    }
+
+    #[test]
+    fn query_never_misses_a_line_within_the_radius() {
+        // Lines of every direction and length, spread over [-500, 500]²
+        let spread = |i: usize, step: usize, range: f64| -> f64 {
+            ((i * step) % 1000) as f64 / 1000.0 * range - range / 2.0
+        };
+        let lines: Vec<(Point, Point)> = (0..300)
+            .map(|i| {
+                let start: Point = point(spread(i, 37, 1000.0), spread(i, 91, 1000.0));
+                let end: Point = point(
+                    start.x + spread(i, 53, 400.0),
+                    start.y + spread(i, 29, 400.0),
+                );
+                (start, end)
+            })
+            .collect();
+        let grid: SpatialGrid = grid_of(&lines, 37.0);
+
+        let mut out: Vec<usize> = Vec::new();
+        // Radii below the cell size walk the cells, the largest scans the occupied ones
+        for radius in [5.0, 37.0, 120.0, 5000.0] {
+            for i in 0..200 {
+                let center: Point = point(spread(i, 61, 1200.0), spread(i, 83, 1200.0));
+                out.clear();
+                grid.query(&center, radius, &mut out);
+
+                for (index, (start, end)) in lines.iter().enumerate() {
+                    if distance_to_line(&center, start, end) <= radius {
+                        assert!(
+                            out.contains(&index),
+                            "line {} missed at radius {}",
+                            index,
+                            radius
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,395 @@
+// synthetic_dataset.rs - Synthetic desire lines with their known corridors
+//
+// Port of inputs/dataset_factory.py and random_shapes.py: desire lines between random
+// points of start and end shapes, radial lines around a center and parallel vertical lines.
+// Lines are drawn in groups; a group whose lines all follow one axis records that axis as a
+// ground-truth corridor, weighted by the lines of the group.
+
+use super::geometry::input_od_line::InputODLine;
+use super::geometry::point::Point;
+use crate::utils::seeded_rng::SeededRng;
+
+// ─────────────────────────────────────────────
+// Shapes
+// ─────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy)]
+pub enum Shape {
+    Circle { center: Point, radius: f64 },
+    Quadrilateral([Point; 4]), // convex, vertices in order
+}
+
+// Shapes of dataset_factory.py, in MTM zone 8 coordinates (meters)
+pub const MONTREAL_QUAD: Shape = Shape::Quadrilateral([
+    Point {
+        x: 287175.0,
+        y: 5039383.0,
+    },
+    Point {
+        x: 297225.0,
+        y: 5055425.0,
+    },
+    Point {
+        x: 303942.0,
+        y: 5053921.0,
+    },
+    Point {
+        x: 297464.0,
+        y: 5035132.0,
+    },
+]);
+pub const SMALL_RADIUS_1: Shape = Shape::Circle {
+    center: Point {
+        x: 293680.0,
+        y: 5040290.0,
+    },
+    radius: 500.0,
+};
+pub const SMALL_RADIUS_2: Shape = Shape::Circle {
+    center: Point {
+        x: 299157.0,
+        y: 5049817.0,
+    },
+    radius: 500.0,
+};
+pub const RIVE_SUD_POS: Shape = Shape::Circle {
+    center: Point {
+        x: 305156.0,
+        y: 5042535.0,
+    },
+    radius: 30.0,
+};
+
+impl Shape {
+    /// Uniform random point inside the shape.
+    /// random_shapes.py draws a circle point from a rounded square; this one is uniform on the disc.
+    pub fn random_inside(&self, rng: &mut SeededRng) -> Point {
+        match self {
+            Shape::Circle { center, radius } => {
+                let angle: f64 = rng.uniform(0.0, std::f64::consts::TAU);
+                let r: f64 = radius * rng.next_f64().sqrt(); // sqrt for a uniform density
+                Point {
+                    x: center.x + r * angle.cos(),
+                    y: center.y + r * angle.sin(),
+                }
+            }
+            // Two triangles split on the 0-2 diagonal, each picked half of the time
+            Shape::Quadrilateral(p) => {
+                if rng.next_f64() < 0.5 {
+                    random_in_triangle(rng, p[0], p[1], p[2])
+                } else {
+                    random_in_triangle(rng, p[0], p[2], p[3])
+                }
+            }
+        }
+    }
+
+    pub fn center(&self) -> Point {
+        match self {
+            Shape::Circle { center, .. } => *center,
+            Shape::Quadrilateral(p) => (p[0] + p[1] + p[2] + p[3]) * 0.25,
+        }
+    }
+
+    /// Largest distance from the center to a point of the shape.
+    pub fn radius(&self) -> f64 {
+        match self {
+            Shape::Circle { radius, .. } => *radius,
+            Shape::Quadrilateral(p) => {
+                let center: Point = self.center();
+                p.iter().map(|v| distance(&center, v)).fold(0.0, f64::max)
+            }
+        }
+    }
+}
+
+fn random_in_triangle(rng: &mut SeededRng, a: Point, b: Point, c: Point) -> Point {
+    let (mut r1, mut r2): (f64, f64) = (rng.next_f64(), rng.next_f64());
+    if r1 + r2 > 1.0 {
+        (r1, r2) = (1.0 - r1, 1.0 - r2);
+    }
+    Point {
+        x: a.x + r1 * (b.x - a.x) + r2 * (c.x - a.x),
+        y: a.y + r1 * (b.y - a.y) + r2 * (c.y - a.y),
+    }
+}
+
+fn distance(p: &Point, q: &Point) -> f64 {
+    (p.x - q.x).hypot(p.y - q.y)
+}
+
+// ─────────────────────────────────────────────
+// SyntheticDataset
+// ─────────────────────────────────────────────
+
+/// Axis a group of generated lines was built around.
+#[derive(Debug, Clone)]
+pub struct TruthCorridor {
+    pub id: usize,
+    pub weight: u32,
+    pub start: Point,
+    pub end: Point,
+    pub line_ids: Vec<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct SyntheticDataset {
+    pub lines: Vec<InputODLine>, // ids from 1, as in dataset_factory.py
+    pub corridors: Vec<TruthCorridor>,
+}
+
+impl SyntheticDataset {
+    /// Ground-truth corridor of every line, None for the lines outside any corridor.
+    pub fn line_corridors(&self) -> Vec<(usize, Option<usize>)> {
+        let mut corridor_of: Vec<Option<usize>> = vec![None; self.lines.len() + 1];
+        for corridor in &self.corridors {
+            for &line_id in &corridor.line_ids {
+                corridor_of[line_id] = Some(corridor.id);
+            }
+        }
+        self.lines
+            .iter()
+            .map(|line| (line.line_id, corridor_of[line.line_id]))
+            .collect()
+    }
+
+    fn push_line(&mut self, start: Point, end: Point) -> usize {
+        let line_id: usize = self.lines.len() + 1;
+        self.lines.push(InputODLine {
+            line_id,
+            weight: 1,
+            start,
+            end,
+            via: Vec::new(),
+        });
+        line_id
+    }
+}
+
+// ─────────────────────────────────────────────
+// Generators
+// ─────────────────────────────────────────────
+
+/// `lines` desire lines, line i going from start_shapes[i % n] to end_shapes[i % m].
+/// A pair of shapes is a corridor when they lie further apart than their radii,
+/// so that its lines cannot fan out in every direction.
+pub fn desire_lines_between_shapes(
+    lines: usize,
+    start_shapes: &[Shape],
+    end_shapes: &[Shape],
+    seed: u64,
+) -> SyntheticDataset {
+    let mut rng: SeededRng = SeededRng::new(seed);
+    let mut dataset: SyntheticDataset = SyntheticDataset::default();
+    let mut group_lines: Vec<Vec<usize>> = vec![Vec::new(); start_shapes.len() * end_shapes.len()];
+
+    for i in 1..=lines {
+        let (s, e): (usize, usize) = (i % start_shapes.len(), i % end_shapes.len());
+        let start: Point = start_shapes[s].random_inside(&mut rng);
+        let end: Point = end_shapes[e].random_inside(&mut rng);
+        let line_id: usize = dataset.push_line(start, end);
+        group_lines[s * end_shapes.len() + e].push(line_id);
+    }
+
+    for (group, line_ids) in group_lines.into_iter().enumerate() {
+        let start_shape: &Shape = &start_shapes[group / end_shapes.len()];
+        let end_shape: &Shape = &end_shapes[group % end_shapes.len()];
+        let (start, end): (Point, Point) = (start_shape.center(), end_shape.center());
+        let is_corridor: bool = distance(&start, &end) > start_shape.radius() + end_shape.radius();
+
+        if is_corridor && !line_ids.is_empty() {
+            dataset.corridors.push(TruthCorridor {
+                id: dataset.corridors.len(),
+                weight: line_ids.len() as u32,
+                start,
+                end,
+                line_ids,
+            });
+        }
+    }
+    dataset
+}
+
+/// Lines from `center` to a circle of `radius`, every `angle_interval` degrees.
+/// Each line has its own direction, so there is no corridor to find.
+pub fn radial_lines(angle_interval: f64, center: Point, radius: f64) -> SyntheticDataset {
+    let mut dataset: SyntheticDataset = SyntheticDataset::default();
+    if angle_interval <= 0.0 {
+        return dataset;
+    }
+
+    let num_lines: usize = (360.0 / angle_interval) as usize;
+    for i in 1..=num_lines {
+        let angle: f64 = (i as f64 * angle_interval).to_radians();
+        let end: Point = Point {
+            x: center.x + radius * angle.cos(),
+            y: center.y + radius * angle.sin(),
+        };
+        dataset.push_line(center, end);
+    }
+    dataset
+}
+
+/// `num_lines` vertical lines of `height`, `spacing` apart and centered on `center`;
+/// together they form one corridor along the middle line.
+/// dataset_factory.py shifts the set one spacing to the right; here it is centered.
+pub fn parallel_lines(
+    spacing: f64,
+    center: Point,
+    height: f64,
+    num_lines: usize,
+) -> SyntheticDataset {
+    let mut dataset: SyntheticDataset = SyntheticDataset::default();
+    if spacing <= 0.0 || num_lines == 0 {
+        return dataset;
+    }
+
+    let half_height: f64 = height / 2.0;
+    let start_x: f64 = center.x - (num_lines - 1) as f64 * spacing / 2.0;
+    let line_ids: Vec<usize> = (0..num_lines)
+        .map(|i| {
+            let x: f64 = start_x + i as f64 * spacing;
+            dataset.push_line(
+                Point {
+                    x,
+                    y: center.y - half_height,
+                },
+                Point {
+                    x,
+                    y: center.y + half_height,
+                },
+            )
+        })
+        .collect();
+
+    dataset.corridors.push(TruthCorridor {
+        id: 0,
+        weight: num_lines as u32,
+        start: Point {
+            x: center.x,
+            y: center.y - half_height,
+        },
+        end: Point {
+            x: center.x,
+            y: center.y + half_height,
+        },
+        line_ids,
+    });
+    dataset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_lines() {
+        let shapes: [Shape; 2] = [SMALL_RADIUS_1, RIVE_SUD_POS];
+        let a: SyntheticDataset = desire_lines_between_shapes(50, &shapes, &[MONTREAL_QUAD], 3);
+        let b: SyntheticDataset = desire_lines_between_shapes(50, &shapes, &[MONTREAL_QUAD], 3);
+        let c: SyntheticDataset = desire_lines_between_shapes(50, &shapes, &[MONTREAL_QUAD], 4);
+
+        let coords = |d: &SyntheticDataset| -> Vec<(f64, f64, f64, f64)> {
+            d.lines
+                .iter()
+                .map(|l| (l.start.x, l.start.y, l.end.x, l.end.y))
+                .collect()
+        };
+        assert_eq!(coords(&a), coords(&b));
+        assert_ne!(coords(&a), coords(&c));
+    }
+
+    #[test]
+    fn desire_lines_start_and_end_in_their_shapes() {
+        let start: Shape = Shape::Circle {
+            center: point(0.0, 0.0),
+            radius: 10.0,
+        };
+        let end: Shape = Shape::Quadrilateral([
+            point(100.0, 0.0),
+            point(120.0, 0.0),
+            point(120.0, 20.0),
+            point(100.0, 20.0),
+        ]);
+        let dataset: SyntheticDataset = desire_lines_between_shapes(200, &[start], &[end], 11);
+
+        assert_eq!(dataset.lines.len(), 200);
+        for line in &dataset.lines {
+            assert!(line.start.x.hypot(line.start.y) <= 10.0);
+            assert!((100.0..=120.0).contains(&line.end.x));
+            assert!((0.0..=20.0).contains(&line.end.y));
+        }
+        assert_eq!(dataset.corridors.len(), 1);
+        assert_eq!(dataset.corridors[0].weight, 200);
+        assert_eq!(
+            (dataset.corridors[0].end.x, dataset.corridors[0].end.y),
+            (110.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn overlapping_shapes_make_no_corridor() {
+        let shape: Shape = Shape::Circle {
+            center: point(0.0, 0.0),
+            radius: 50.0,
+        };
+        let dataset: SyntheticDataset = desire_lines_between_shapes(20, &[shape], &[shape], 1);
+        assert!(dataset.corridors.is_empty());
+        assert!(
+            dataset
+                .line_corridors()
+                .iter()
+                .all(|(_, corridor)| corridor.is_none())
+        );
+    }
+
+    #[test]
+    fn lines_alternate_between_shape_pairs() {
+        let near: Shape = Shape::Circle {
+            center: point(0.0, 0.0),
+            radius: 1.0,
+        };
+        let far: Shape = Shape::Circle {
+            center: point(1000.0, 0.0),
+            radius: 1.0,
+        };
+        let dataset: SyntheticDataset = desire_lines_between_shapes(6, &[near, far], &[far], 5);
+
+        // Line i starts in start_shapes[i % 2]: line 1 from far (no corridor), line 2 from near
+        let corridors: Vec<(usize, Option<usize>)> = dataset.line_corridors();
+        assert_eq!(corridors[0], (1, None));
+        assert_eq!(corridors[1], (2, Some(0)));
+        assert_eq!(dataset.corridors[0].line_ids, vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn radial_lines_cover_the_circle() {
+        let dataset: SyntheticDataset = radial_lines(90.0, point(10.0, 10.0), 5.0);
+        assert_eq!(dataset.lines.len(), 4);
+        assert!(dataset.corridors.is_empty());
+        let last: &Point = &dataset.lines[3].end;
+        assert!((last.x - 15.0).abs() < 1e-9 && (last.y - 10.0).abs() < 1e-9);
+
+        assert!(radial_lines(0.0, point(0.0, 0.0), 5.0).lines.is_empty());
+    }
+
+    #[test]
+    fn parallel_lines_are_centered_on_their_corridor() {
+        let dataset: SyntheticDataset = parallel_lines(2.0, point(0.0, 0.0), 10.0, 3);
+        let xs: Vec<f64> = dataset.lines.iter().map(|line| line.start.x).collect();
+        assert_eq!(xs, vec![-2.0, 0.0, 2.0]);
+        assert_eq!(
+            (dataset.lines[0].start.y, dataset.lines[0].end.y),
+            (-5.0, 5.0)
+        );
+
+        let corridor: &TruthCorridor = &dataset.corridors[0];
+        assert_eq!((corridor.start.x, corridor.start.y), (0.0, -5.0));
+        assert_eq!(corridor.line_ids, vec![1, 2, 3]);
+        assert_eq!(corridor.weight, 3);
+    }
+}
@@ -0,0 +1,465 @@
+// bench.rs - `bench` subcommand: phase timings of the clustering over a parameter grid
+//
+// Replaces benchmarks/runner_unit_tests.py without its process start-ups and folder copies.
+// An input is loaded once per (max_angle, segment_size), the two parameters that shape the
+// loaded trajectories; every parameter set then runs its warm-up and timed runs in each mode
+// on that same storage. The spatial index is built before the runs and kept between them.
+// The outputs are written once, after the last timed run: the output phase has one sample.
+
+use std::fs;
+use std::path::Path;
+use std::thread::available_parallelism;
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+
+use crate::clustering::main_traclusdl::MainTraclusDL;
+use crate::clustering::storage::clustered_trajectories::ClusteredTrajectories;
+use crate::clustering::storage::raw_trajectories::RawTrajectories;
+use crate::io::args::{BenchArgs, ExecutionMode, InterfaceMode, TraclusArgs};
+use crate::io::input_loader::{SanitationReport, parse_input_data};
+use crate::io::io_error::IoError;
+use crate::io::output_writer::OutputKind;
+use crate::utils::run_timings::RunTimings;
+
+// ─────────────────────────────────────────────
+// Parameter grid
+// ─────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy)]
+struct ParamSet {
+    max_dist: f64,
+    min_density: u32,
+    max_angle: f64,
+    segment_size: f64,
+}
+
+// Zips the lists as ArgumentsTraclus does: the shorter ones repeat their last value
+fn param_grid(args: &BenchArgs) -> Vec<ParamSet> {
+    let len: usize = args
+        .max_dist
+        .len()
+        .max(args.min_density.len())
+        .max(args.max_angle.len())
+        .max(args.segment_size.len());
+
+    (0..len)
+        .map(|i| ParamSet {
+            max_dist: nth_or_last(&args.max_dist, i),
+            min_density: nth_or_last(&args.min_density, i),
+            max_angle: nth_or_last(&args.max_angle, i),
+            segment_size: nth_or_last(&args.segment_size, i),
+        })
+        .collect()
+}
+
+// clap fills every list with at least its default value
+fn nth_or_last<T: Copy>(values: &[T], i: usize) -> T {
+    values[i.min(values.len() - 1)]
+}
+
+// ─────────────────────────────────────────────
+// Statistics
+// ─────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    Load,
+    Clustering,
+    Finalization,
+    Output,
+}
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Load => "load",
+            Phase::Clustering => "clustering",
+            Phase::Finalization => "finalization",
+            Phase::Output => "output",
+        }
+    }
+}
+
+/// Timings of one phase; the load has a single sample per loaded input.
+struct PhaseStats {
+    path: String,
+    params: ParamSet,
+    mode: Option<ExecutionMode>, // None for the load, shared by every mode
+    phase: Phase,
+    samples: usize,
+    mean: f64, // seconds
+    median: f64,
+    min: f64,
+    stddev: f64, // sample standard deviation, 0 with a single sample
+}
+
+impl PhaseStats {
+    fn new(
+        path: &str,
+        params: ParamSet,
+        mode: Option<ExecutionMode>,
+        phase: Phase,
+        samples: &[Duration],
+    ) -> Self {
+        let mut secs: Vec<f64> = samples.iter().map(Duration::as_secs_f64).collect();
+        secs.sort_by(f64::total_cmp);
+
+        let n: usize = secs.len();
+        let mean: f64 = secs.iter().sum::<f64>() / n as f64;
+        let median: f64 = if n.is_multiple_of(2) {
+            (secs[n / 2 - 1] + secs[n / 2]) / 2.0
+        } else {
+            secs[n / 2]
+        };
+        let stddev: f64 = if n < 2 {
+            0.0
+        } else {
+            let variance: f64 =
+                secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+            variance.sqrt()
+        };
+
+        Self {
+            path: path.to_string(),
+            params,
+            mode,
+            phase,
+            samples: n,
+            mean,
+            median,
+            min: secs[0],
+            stddev,
+        }
+    }
+
+    fn mode_name(&self) -> String {
+        self.mode.map(|mode| mode.to_string()).unwrap_or_default()
+    }
+}
+
+// ─────────────────────────────────────────────
+// Benchmark
+// ─────────────────────────────────────────────
+
+pub fn run_bench(args: &BenchArgs) -> Result<(), IoError> {
+    let threads: usize = available_parallelism().map(|n| n.get()).unwrap_or(2).max(1);
+    let main_traclusdl: MainTraclusDL = MainTraclusDL::new(threads);
+    let params: Vec<ParamSet> = param_grid(args);
+    let kinds: &[OutputKind] = OutputKind::for_format(args.output_format);
+
+    println!(
+        "Benchmark: {} input(s) x {} parameter set(s) x {} mode(s), {} warm-up + {} timed run(s), {} threads",
+        args.paths.len(),
+        params.len(),
+        args.modes.len(),
+        args.warmup,
+        args.runs,
+        threads
+    );
+    print_stats_header();
+
+    let mut stats: Vec<PhaseStats> = Vec::new();
+    for path in &args.paths {
+        let file: String = match &args.data_dir {
+            Some(dir) => Path::new(dir).join(path).to_string_lossy().into_owned(),
+            None => path.clone(),
+        };
+
+        let mut loaded: Option<(f64, f64, RawTrajectories)> = None; // max_angle, segment_size
+        for &param_set in &params {
+            let mut run_args: TraclusArgs = run_args(args, &file, param_set);
+
+            let is_loaded: bool = loaded.as_ref().is_some_and(|(max_angle, segment_size, _)| {
+                *max_angle == param_set.max_angle && *segment_size == param_set.segment_size
+            });
+            if !is_loaded {
+                let load_start: Instant = Instant::now();
+                let (raw_storage, sanitation): (RawTrajectories, SanitationReport) =
+                    parse_input_data(&run_args)?;
+                for warning in &sanitation.warnings {
+                    eprintln!("Warning: {}", warning);
+                }
+                let load: PhaseStats =
+                    PhaseStats::new(path, param_set, None, Phase::Load, &[load_start.elapsed()]);
+                print_stats(&load);
+                stats.push(load);
+                loaded = Some((param_set.max_angle, param_set.segment_size, raw_storage));
+            }
+            let Some((_, _, raw_storage)) = loaded.as_mut() else {
+                unreachable!("the input was loaded above");
+            };
+            main_traclusdl.build_index_for_bench(raw_storage, &run_args);
+
+            for &mode in &args.modes {
+                run_args.mode = mode;
+                let samples: RunSamples =
+                    time_runs(&main_traclusdl, raw_storage, &run_args, kinds, args)?;
+                for phase in [Phase::Clustering, Phase::Finalization, Phase::Output] {
+                    let phase_stats: PhaseStats = PhaseStats::new(
+                        path,
+                        param_set,
+                        Some(mode),
+                        phase,
+                        samples.durations(phase),
+                    );
+                    print_stats(&phase_stats);
+                    stats.push(phase_stats);
+                }
+            }
+        }
+    }
+
+    write_report(&args.report, &stats)?;
+    println!("Report written to {}", args.report);
+    Ok(())
+}
+
+fn run_args(args: &BenchArgs, file: &str, params: ParamSet) -> TraclusArgs {
+    TraclusArgs {
+        file: file.to_string(),
+        max_dist: params.max_dist,
+        min_density: params.min_density,
+        max_angle: params.max_angle,
+        segment_size: params.segment_size,
+        undirected: args.undirected,
+        partition: args.partition.clone(),
+        distance: args.distance.clone(),
+        interface_mode: InterfaceMode::Performance,
+        input: args.input.clone(),
+        output_format: args.output_format,
+        output_dir: args.output_dir.clone(),
+        ..TraclusArgs::default()
+    }
+}
+
+// Phases of the timed runs of one parameter set and mode; the input is not reloaded
+// between runs, and only the last one writes the outputs
+struct RunSamples {
+    clustering: Vec<Duration>,
+    finalization: Vec<Duration>,
+    output: [Duration; 1],
+}
+
+impl RunSamples {
+    fn durations(&self, phase: Phase) -> &[Duration] {
+        match phase {
+            Phase::Load => &[],
+            Phase::Clustering => &self.clustering,
+            Phase::Finalization => &self.finalization,
+            Phase::Output => &self.output,
+        }
+    }
+}
+
+// Warm-up runs, then the timed runs of one parameter set and mode
+fn time_runs(
+    main_traclusdl: &MainTraclusDL,
+    raw_storage: &mut RawTrajectories,
+    run_args: &TraclusArgs,
+    kinds: &[OutputKind],
+    args: &BenchArgs,
+) -> Result<RunSamples, IoError> {
+    let mut clustering: Vec<Duration> = Vec::new();
+    let mut finalization: Vec<Duration> = Vec::new();
+    let mut last_run: Option<(ClusteredTrajectories, RunTimings)> = None;
+    for run in 0..args.warmup + args.runs {
+        let (clust_storage, timings): (ClusteredTrajectories, RunTimings) = main_traclusdl
+            .cluster_for_bench(raw_storage, run_args)
+            .expect("benchmark runs are never cancelled");
+
+        if run >= args.warmup {
+            clustering.push(timings.clustering);
+            finalization.push(timings.finalization);
+            last_run = Some((clust_storage, timings));
+        }
+    }
+
+    let Some((clust_storage, timings)) = last_run else {
+        unreachable!("clap requires at least one timed run");
+    };
+    let output_start: Instant = Instant::now();
+    MainTraclusDL::write_output_files(run_args, raw_storage, &clust_storage, &timings, kinds)?;
+
+    Ok(RunSamples {
+        clustering,
+        finalization,
+        output: [output_start.elapsed()],
+    })
+}
+
+// ─────────────────────────────────────────────
+// Report
+// ─────────────────────────────────────────────
+
+fn print_stats_header() {
+    println!(
+        "{:<32} {:>8} {:>6} {:>6} {:>8} {:<14} {:<13} {:>4} {:>10} {:>10} {:>10} {:>10}",
+        "path",
+        "max_dist",
+        "min_d",
+        "angle",
+        "seg_size",
+        "mode",
+        "phase",
+        "n",
+        "mean s",
+        "median s",
+        "min s",
+        "stddev s"
+    );
+}
+
+fn print_stats(stats: &PhaseStats) {
+    println!(
+        "{:<32} {:>8} {:>6} {:>6} {:>8} {:<14} {:<13} {:>4} {:>10.4} {:>10.4} {:>10.4} {:>10.4}",
+        stats.path,
+        stats.params.max_dist,
+        stats.params.min_density,
+        stats.params.max_angle,
+        stats.params.segment_size,
+        stats.mode_name(),
+        stats.phase.name(),
+        stats.samples,
+        stats.mean,
+        stats.median,
+        stats.min,
+        stats.stddev
+    );
+}
+
+// JSON when the path ends in .json, CSV otherwise
+fn write_report(report_path: &str, stats: &[PhaseStats]) -> Result<(), IoError> {
+    let is_json: bool = Path::new(report_path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let content: String = if is_json {
+        let rows: Vec<Value> = stats.iter().map(stats_json).collect();
+        format!("{:#}\n", Value::Array(rows))
+    } else {
+        report_csv(stats)
+    };
+    fs::write(report_path, content).map_err(|err| IoError::write(report_path, err))
+}
+
+fn report_csv(stats: &[PhaseStats]) -> String {
+    let mut csv: String = String::from(
+        "path,max_dist,min_density,max_angle,segment_size,mode,phase,samples,mean_s,median_s,min_s,stddev_s\n",
+    );
+    for s in stats {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            csv_field(&s.path),
+            s.params.max_dist,
+            s.params.min_density,
+            s.params.max_angle,
+            s.params.segment_size,
+            s.mode_name(),
+            s.phase.name(),
+            s.samples,
+            s.mean,
+            s.median,
+            s.min,
+            s.stddev
+        ));
+    }
+    csv
+}
+
+// Quotes a field holding a delimiter, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn stats_json(s: &PhaseStats) -> Value {
+    json!({
+        "path": s.path,
+        "max_dist": s.params.max_dist,
+        "min_density": s.params.min_density,
+        "max_angle": s.params.max_angle,
+        "segment_size": s.params.segment_size,
+        "mode": s.mode.map(|mode| mode.to_string()),
+        "phase": s.phase.name(),
+        "samples": s.samples,
+        "mean_s": s.mean,
+        "median_s": s.median,
+        "min_s": s.min,
+        "stddev_s": s.stddev,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAMS: ParamSet = ParamSet {
+        max_dist: 250.0,
+        min_density: 8,
+        max_angle: 10.0,
+        segment_size: 100.0,
+    };
+
+    fn stats_of(millis: &[u64]) -> PhaseStats {
+        let samples: Vec<Duration> = millis.iter().map(|&ms| Duration::from_millis(ms)).collect();
+        PhaseStats::new("trips.txt", PARAMS, None, Phase::Clustering, &samples)
+    }
+
+    #[test]
+    fn shorter_lists_repeat_their_last_value() {
+        let values: [f64; 2] = [1.0, 2.0];
+        let zipped: Vec<f64> = (0..4).map(|i| nth_or_last(&values, i)).collect();
+        assert_eq!(zipped, vec![1.0, 2.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn statistics_of_an_odd_sample_count() {
+        let stats: PhaseStats = stats_of(&[300, 100, 200]);
+        assert_eq!(stats.samples, 3);
+        assert!((stats.mean - 0.2).abs() < 1e-12);
+        assert!((stats.median - 0.2).abs() < 1e-12);
+        assert!((stats.min - 0.1).abs() < 1e-12);
+        // Sample standard deviation: sqrt((0.01 + 0 + 0.01) / 2)
+        assert!((stats.stddev - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn median_of_an_even_sample_count_is_the_middle_mean() {
+        let stats: PhaseStats = stats_of(&[400, 100, 300, 200]);
+        assert!((stats.median - 0.25).abs() < 1e-12);
+        assert!((stats.min - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn single_sample_has_no_deviation() {
+        let stats: PhaseStats = stats_of(&[1500]);
+        assert_eq!(stats.stddev, 0.0);
+        assert_eq!(stats.mean, 1.5);
+        assert_eq!(stats.median, 1.5);
+    }
+
+    #[test]
+    fn csv_report_quotes_paths_when_needed() {
+        assert_eq!(csv_field("data/trips.txt"), "data/trips.txt");
+        assert_eq!(csv_field("a,b \"c\""), "\"a,b \"\"c\"\"\"");
+
+        let mut stats: PhaseStats = stats_of(&[1000]);
+        stats.path = "x,y.txt".to_string();
+        let csv: String = report_csv(&[stats]);
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1], "\"x,y.txt\",250,8,10,100,,clustering,1,1,1,1,0");
+    }
+
+    #[test]
+    fn json_report_leaves_the_mode_of_the_load_null() {
+        let stats: PhaseStats = stats_of(&[1000]);
+        let row: Value = stats_json(&stats);
+        assert!(row["mode"].is_null());
+        assert_eq!(row["phase"], "clustering");
+        assert_eq!(row["min_density"], 8);
+    }
+}
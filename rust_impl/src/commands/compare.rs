@@ -0,0 +1,185 @@
+// compare.rs - `compare` subcommand: report of two runs read from their text outputs
+
+use std::io;
+
+use serde_json::{Value, json};
+
+use crate::clustering::comparison::{ComparisonReport, RunSnapshot, compare_runs};
+use crate::io::args::CompareArgs;
+use crate::io::io_error::IoError;
+use crate::io::run_reader::{corridor_list_beside, read_run_outputs};
+
+pub fn run_compare(args: &CompareArgs) -> Result<(), IoError> {
+    let run_a: RunSnapshot = read_run(&args.segments_a, args.corridors_a.as_deref())?;
+    let run_b: RunSnapshot = read_run(&args.segments_b, args.corridors_b.as_deref())?;
+    let report: ComparisonReport = compare_runs(&run_a, &run_b);
+
+    if same_partition(&run_a, &run_b) == Some(false) {
+        eprintln!(
+            "Warning: the runs cut the lines differently ({} / {}), equal segment ids are not the same pieces",
+            run_a.partition.as_deref().unwrap_or_default(),
+            run_b.partition.as_deref().unwrap_or_default()
+        );
+    }
+    if args.json {
+        println!("{}", report_json(args, &run_a, &run_b, &report));
+    } else {
+        print_report(args, &run_a, &run_b, &report);
+    }
+    Ok(())
+}
+
+fn read_run(segments_path: &str, corridors_path: Option<&str>) -> Result<RunSnapshot, IoError> {
+    let corridors_path: String = match corridors_path {
+        Some(path) => path.to_string(),
+        None => corridor_list_beside(segments_path).ok_or_else(|| {
+            let message: &str =
+                "no corridorlist file beside it, pass --corridors_a / --corridors_b";
+            IoError::read(segments_path, io::Error::other(message))
+        })?,
+    };
+    read_run_outputs(segments_path, &corridors_path)
+}
+
+// None when a segment list does not record its partition
+fn same_partition(a: &RunSnapshot, b: &RunSnapshot) -> Option<bool> {
+    Some(a.partition.as_ref()? == b.partition.as_ref()?)
+}
+
+fn print_report(args: &CompareArgs, a: &RunSnapshot, b: &RunSnapshot, report: &ComparisonReport) {
+    println!(
+        "Run A: {} ({} segments, {} corridors)",
+        args.segments_a,
+        report.segments_a,
+        a.corridors.len()
+    );
+    println!(
+        "Run B: {} ({} segments, {} corridors)",
+        args.segments_b,
+        report.segments_b,
+        b.corridors.len()
+    );
+    println!();
+    println!("Matched segments:         {}", report.matched_segments);
+    println!("  clustered in both:      {}", report.both_clustered);
+    println!("  clustered in neither:   {}", report.both_non_clustered);
+    println!("  clustered only in A:    {}", report.only_clustered_a);
+    println!("  clustered only in B:    {}", report.only_clustered_b);
+    println!(
+        "Consistently assigned:    {:.2}% ({} segments)",
+        report.consistency_share() * 100.0,
+        report.consistent_segments
+    );
+    println!(
+        "Clustered similarity:     {:.4}",
+        report.clustered_similarity()
+    );
+    println!(
+        "Status similarity:        {:.4}",
+        report.status_similarity()
+    );
+    println!();
+
+    println!("Corridor matches (A -> B):");
+    println!(
+        "{:>8} {:>8} {:>8} {:>10} {:>10} {:>8} {:>12} {:>12}",
+        "A", "B", "shared", "weight A", "weight B", "Δweight", "start disp", "end disp"
+    );
+    for m in &report.corridor_matches {
+        println!(
+            "{:>8} {:>8} {:>8} {:>10} {:>10} {:>+8} {:>12.2} {:>12.2}",
+            m.corridor_a,
+            m.corridor_b,
+            m.shared_segments,
+            m.weight_a,
+            m.weight_b,
+            m.weight_difference(),
+            m.start_displacement,
+            m.end_displacement
+        );
+    }
+    println!();
+    println!(
+        "Unmatched corridors of A: {}",
+        id_list(&report.unmatched_corridors_a)
+    );
+    println!(
+        "Unmatched corridors of B: {}",
+        id_list(&report.unmatched_corridors_b)
+    );
+}
+
+fn id_list(ids: &[usize]) -> String {
+    if ids.is_empty() {
+        return String::from("-");
+    }
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn report_json(
+    args: &CompareArgs,
+    a: &RunSnapshot,
+    b: &RunSnapshot,
+    report: &ComparisonReport,
+) -> Value {
+    let matches: Vec<Value> = report
+        .corridor_matches
+        .iter()
+        .map(|m| {
+            json!({
+                "corridor_a": m.corridor_a,
+                "corridor_b": m.corridor_b,
+                "shared_segments": m.shared_segments,
+                "weight_a": m.weight_a,
+                "weight_b": m.weight_b,
+                "weight_difference": m.weight_difference(),
+                "start_displacement": m.start_displacement,
+                "end_displacement": m.end_displacement,
+            })
+        })
+        .collect();
+
+    json!({
+        "run_a": { "segments_file": args.segments_a, "segments": report.segments_a, "corridors": a.corridors.len(), "partition": a.partition },
+        "run_b": { "segments_file": args.segments_b, "segments": report.segments_b, "corridors": b.corridors.len(), "partition": b.partition },
+        "same_partition": same_partition(a, b),
+        "matched_segments": report.matched_segments,
+        "both_clustered": report.both_clustered,
+        "both_non_clustered": report.both_non_clustered,
+        "only_clustered_a": report.only_clustered_a,
+        "only_clustered_b": report.only_clustered_b,
+        "consistent_segments": report.consistent_segments,
+        "consistency_share": report.consistency_share(),
+        "clustered_similarity": report.clustered_similarity(),
+        "status_similarity": report.status_similarity(),
+        "corridor_matches": matches,
+        "unmatched_corridors_a": report.unmatched_corridors_a,
+        "unmatched_corridors_b": report.unmatched_corridors_b,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_cut_by(partition: Option<&str>) -> RunSnapshot {
+        RunSnapshot {
+            partition: partition.map(str::to_string),
+            ..RunSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn partitions_are_compared_when_both_are_recorded() {
+        let fixed: RunSnapshot = run_cut_by(Some("fixed segment_size=100"));
+        let mdl: RunSnapshot = run_cut_by(Some("mdl mdl_cost_weight=1 min_segment_length=0"));
+        let unknown: RunSnapshot = run_cut_by(None);
+
+        assert_eq!(same_partition(&fixed, &fixed), Some(true));
+        assert_eq!(same_partition(&fixed, &mdl), Some(false));
+        assert_eq!(same_partition(&fixed, &unknown), None);
+    }
+}
@@ -0,0 +1,73 @@
+// generate.rs - `generate` subcommand: synthetic dataset and its ground-truth corridors
+
+use crate::clustering::geometry::point::Point;
+use crate::clustering::synthetic_dataset::{
+    MONTREAL_QUAD, RIVE_SUD_POS, SMALL_RADIUS_1, SMALL_RADIUS_2, SyntheticDataset,
+    desire_lines_between_shapes, parallel_lines, radial_lines,
+};
+use crate::io::args::{GenerateArgs, InputArgs, InputFormat, Scenario, TraclusArgs};
+use crate::io::dataset_writer::{write_dataset, write_ground_truth};
+use crate::io::input_loader::resolve_input_format;
+use crate::io::io_error::IoError;
+
+const RADIAL_LENGTH: f64 = 1000.0;
+const PARALLEL_HEIGHT: f64 = 1000.0;
+
+pub fn run_generate(args: &GenerateArgs) -> Result<(), IoError> {
+    let dataset: SyntheticDataset = generate(args);
+
+    // Auto is resolved from the extension, as when the file is read back
+    let format: InputFormat = resolve_input_format(&TraclusArgs {
+        file: args.output.clone(),
+        input: InputArgs {
+            input_format: args.format,
+            ..InputArgs::default()
+        },
+        ..TraclusArgs::default()
+    });
+    write_dataset(&args.output, format, &dataset)?;
+    println!(
+        "Dataset written to: {} ({} lines, {:?})",
+        args.output,
+        dataset.lines.len(),
+        format
+    );
+
+    for file in write_ground_truth(&args.output, &dataset)? {
+        println!("Ground truth written to: {}", file);
+    }
+    println!("Ground-truth corridors: {}", dataset.corridors.len());
+    Ok(())
+}
+
+fn generate(args: &GenerateArgs) -> SyntheticDataset {
+    // dataset_factory.py centers the radial and parallel lines on the first small circle
+    let center: Point = SMALL_RADIUS_1.center();
+    match args.scenario {
+        Scenario::MontrealToMontreal => desire_lines_between_shapes(
+            args.lines.unwrap_or(500),
+            &[MONTREAL_QUAD],
+            &[MONTREAL_QUAD],
+            args.seed,
+        ),
+        Scenario::SmallRadiusToSmallRadius => desire_lines_between_shapes(
+            args.lines.unwrap_or(150),
+            &[SMALL_RADIUS_1],
+            &[SMALL_RADIUS_2],
+            args.seed,
+        ),
+        Scenario::UpTheBridges => desire_lines_between_shapes(
+            args.lines.unwrap_or(5000),
+            &[RIVE_SUD_POS],
+            &[MONTREAL_QUAD],
+            args.seed,
+        ),
+        Scenario::CircleAround => radial_lines(args.angle_interval, center, RADIAL_LENGTH),
+        Scenario::Parallels => parallel_lines(
+            args.spacing,
+            center,
+            PARALLEL_HEIGHT,
+            args.lines.unwrap_or(10),
+        ),
+    }
+}
@@ -0,0 +1,181 @@
+// inspect.rs - `inspect` subcommand: statistics of an input file, loaded as for a run
+
+use serde_json::{Value, json};
+
+use crate::clustering::dataset_stats::DatasetStats;
+use crate::clustering::geometry::projection::MetricZone;
+use crate::clustering::storage::raw_trajectories::RawTrajectories;
+use crate::io::args::{InputFormat, InspectArgs, TraclusArgs};
+use crate::io::input_loader::{SanitationReport, parse_input_data, resolve_input_format};
+use crate::io::io_error::IoError;
+
+const HISTOGRAM_WIDTH: usize = 40;
+
+pub fn run_inspect(args: &InspectArgs) -> Result<(), IoError> {
+    let load_args: TraclusArgs = TraclusArgs {
+        file: args.file.clone(),
+        max_angle: args.max_angle,
+        segment_size: args.segment_size[0],
+        undirected: args.undirected,
+        input: args.input.clone(),
+        ..TraclusArgs::default()
+    };
+    let format: InputFormat = resolve_input_format(&load_args);
+    let (raw_storage, sanitation): (RawTrajectories, SanitationReport) =
+        parse_input_data(&load_args)?;
+    let stats: DatasetStats = DatasetStats::from_raw_storage(&raw_storage, &args.segment_size);
+    let zone: Option<MetricZone> = raw_storage.projection.map(|projection| projection.zone);
+    for warning in &sanitation.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    if args.json {
+        println!("{:#}", stats_json(args, format, zone, &stats, &sanitation));
+    } else {
+        print_stats(args, format, zone, &stats, &sanitation);
+    }
+    Ok(())
+}
+
+fn print_stats(
+    args: &InspectArgs,
+    format: InputFormat,
+    zone: Option<MetricZone>,
+    stats: &DatasetStats,
+    sanitation: &SanitationReport,
+) {
+    println!("File:           {} ({:?})", args.file, format);
+    if let Some(zone) = zone {
+        println!("Projected to:   {} (lengths in metres)", zone);
+    }
+    println!("Lines:          {}", stats.lines);
+    println!("Total weight:   {}", stats.total_weight);
+    match &stats.bounding_box {
+        Some(bbox) => println!(
+            "Bounding box:   ({}, {}) - ({}, {}), {:.1} x {:.1}",
+            bbox.min.x,
+            bbox.min.y,
+            bbox.max.x,
+            bbox.max.y,
+            bbox.max.x - bbox.min.x,
+            bbox.max.y - bbox.min.y
+        ),
+        None => println!("Bounding box:   -"),
+    }
+    println!();
+
+    let l = &stats.lengths;
+    println!("Line length:");
+    println!(
+        "  min {:.1}  p25 {:.1}  median {:.1}  p75 {:.1}  p90 {:.1}  max {:.1}  mean {:.1}",
+        l.min, l.p25, l.median, l.p75, l.p90, l.max, l.mean
+    );
+    println!("Segments:");
+    for count in &stats.segment_counts {
+        println!(
+            "  segment_size {:>8}: {} segments",
+            count.segment_size, count.segments
+        );
+    }
+    println!();
+
+    println!("Sanitation:     {}", sanitation);
+    println!("Problem lines kept:");
+    println!("  zero length:          {}", stats.zero_length_lines);
+    println!("  non-finite:           {}", stats.non_finite_lines);
+    println!(
+        "  duplicate OD pairs:   {} ({} repeated lines)",
+        stats.duplicate_pairs, stats.duplicate_lines
+    );
+    println!();
+
+    let direction: &str = if args.undirected { ", undirected" } else { "" };
+    println!("Angle buckets of {}°{}:", args.max_angle, direction);
+    let most_lines: usize = stats.buckets.iter().map(|b| b.lines).max().unwrap_or(0);
+    for bucket in &stats.buckets {
+        let bar: usize = (bucket.lines * HISTOGRAM_WIDTH).div_ceil(most_lines.max(1));
+        println!(
+            "  {:>3} [{:>6.2}, {:>6.2}[ {:>8} lines {:>10} weight  {}",
+            bucket.index,
+            bucket.angle_start,
+            bucket.angle_end,
+            bucket.lines,
+            bucket.weight,
+            "#".repeat(bar)
+        );
+    }
+    match stats.largest_bucket() {
+        Some(bucket) => println!(
+            "Largest bucket: {} [{:.2}, {:.2}[ with {:.1}% of the lines",
+            bucket.index,
+            bucket.angle_start,
+            bucket.angle_end,
+            stats.largest_bucket_share() * 100.0
+        ),
+        None => println!("Largest bucket: -"),
+    }
+}
+
+fn stats_json(
+    args: &InspectArgs,
+    format: InputFormat,
+    zone: Option<MetricZone>,
+    stats: &DatasetStats,
+    sanitation: &SanitationReport,
+) -> Value {
+    let l = &stats.lengths;
+    let buckets: Vec<Value> = stats
+        .buckets
+        .iter()
+        .map(|b| {
+            json!({
+                "index": b.index,
+                "angle_start": b.angle_start,
+                "angle_end": b.angle_end,
+                "lines": b.lines,
+                "weight": b.weight,
+            })
+        })
+        .collect();
+    let segment_counts: Vec<Value> = stats
+        .segment_counts
+        .iter()
+        .map(|c| json!({ "segment_size": c.segment_size, "segments": c.segments }))
+        .collect();
+    let bounding_box: Value = match &stats.bounding_box {
+        Some(bbox) => json!([bbox.min.x, bbox.min.y, bbox.max.x, bbox.max.y]),
+        None => Value::Null,
+    };
+    let largest_bucket: Value = match stats.largest_bucket() {
+        Some(bucket) => json!({ "index": bucket.index, "share": stats.largest_bucket_share() }),
+        None => Value::Null,
+    };
+
+    json!({
+        "file": args.file,
+        "format": format!("{:?}", format),
+        "projection": zone.map(|zone| zone.to_string()),
+        "lines": stats.lines,
+        "total_weight": stats.total_weight,
+        "bounding_box": bounding_box,
+        "length": {
+            "min": l.min, "p25": l.p25, "median": l.median, "p75": l.p75,
+            "p90": l.p90, "max": l.max, "mean": l.mean,
+        },
+        "segment_counts": segment_counts,
+        "sanitation": {
+            "dropped": sanitation.dropped(),
+            "non_finite": sanitation.non_finite,
+            "zero_length": sanitation.zero_length,
+            "zero_weight": sanitation.zero_weight,
+        },
+        "zero_length_lines": stats.zero_length_lines,
+        "non_finite_lines": stats.non_finite_lines,
+        "duplicate_pairs": stats.duplicate_pairs,
+        "duplicate_lines": stats.duplicate_lines,
+        "max_angle": args.max_angle,
+        "undirected": args.undirected,
+        "buckets": buckets,
+        "largest_bucket": largest_bucket,
+    })
+}
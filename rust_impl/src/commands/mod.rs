@@ -0,0 +1,29 @@
+// commands - Tool subcommands of the command line, run instead of a clustering
+
+use std::process::ExitCode;
+
+use crate::io::args::Command;
+use crate::io::io_error::IoError;
+
+mod bench;
+mod compare;
+mod generate;
+mod inspect;
+
+/// Runs a subcommand; I/O errors are printed and turned into the exit codes of main.
+pub fn run_command(command: Command) -> ExitCode {
+    let result: Result<(), IoError> = match command {
+        Command::Bench(args) => bench::run_bench(&args),
+        Command::Compare(args) => compare::run_compare(&args),
+        Command::Generate(args) => generate::run_generate(&args),
+        Command::Inspect(args) => inspect::run_inspect(&args),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
@@ -2,6 +2,9 @@
 
 use std::sync::mpsc::{self, Receiver, Sender};
 
+use crate::clustering::storage::session_store::{DatasetId, RunComparison, RunId};
+use crate::io::input_loader::SanitationReport;
+
 // ─────────────────────────────────────────────
 // AppEvent enum : events emitted by MainTraclusDL to report progress and results
 // ─────────────────────────────────────────────
@@ -9,11 +12,27 @@ use std::sync::mpsc::{self, Receiver, Sender};
 pub enum AppEvent {
     LoadComplete {
         traj_count: usize,
-        correlation_percent: f64,
+        sanitation: SanitationReport, // lines left out of traj_count
     },
 
+    /// Share of the loaded weight with a neighbour within max_angle and max_dist,
+    /// recomputed with the pre-run estimate
+    CorrelationEstimate { correlation_percent: f64 },
+
+    /// Rough duration of a run with the current data and parameters, before pressing Start
+    PreRunEstimate { estimated_secs: f64 },
+
+    /// Live remaining time of the running computation
+    TimeEstimate { remaining_secs: f64 },
+
     ComputationClusteringProgress {
         num_traj_done: usize,
+        total_traj: usize,
+    },
+
+    CorridorFinalizationProgress {
+        num_corridors_done: usize,
+        num_clusters_left: usize,
     },
 
     ComputationComplete {
@@ -22,31 +41,82 @@ pub enum AppEvent {
         total_seg_outside_corridor: usize,
     },
 
+    /// Emitted when a computation stopped on a cancellation request; previous results are kept
+    Cancelled,
+
+    /// Output files written on request (GUI Create output), in the order they were written
+    OutputsWritten { files: Vec<String> },
+
+    /// A dataset or run was added to or removed from the session store
+    SessionChanged {
+        dataset: DatasetId,
+        run: Option<RunId>, // None when the change is about the dataset itself
+        change: SessionChange,
+        memory_bytes: usize, // estimated heap of the whole store after the change
+    },
+
+    /// Comparison of two runs, requested from the GUI comparison view
+    ComparisonComplete(Box<RunComparison>),
+
     /// Emitted on any unrecoverable error inside a task
     Error(AppError),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionChange {
+    Loaded,
+    RunStored, // a new run, or a rerun replacing the previous one
+    Dropped,
+}
+
+impl fmt::Display for SessionChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionChange::Loaded => write!(f, "loaded"),
+            SessionChange::RunStored => write!(f, "run stored"),
+            SessionChange::Dropped => write!(f, "dropped"),
+        }
+    }
+}
+
 use std::fmt;
 
+use crate::io::io_error::IoError;
+
 #[derive(Debug, Clone)]
 pub enum AppError {
     NoRawStorage,
     NoClustStorage,
-    IoError(String), // variants can still carry dynamic data when needed
+    UnknownSession,        // the dataset or run was dropped from the session store
+    DatasetMismatch,       // the row reads another file, format or CRS than the dataset
+    LoadFailed(IoError),   // input file unreadable or malformed, nothing was loaded
+    OutputFailed(IoError), // an output file could not be written
+    TaskAborted,           // the worker stopped without reporting the end of the clustering
 }
 
 impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let msg = match self {
-            AppError::NoRawStorage => {
+        match self {
+            AppError::NoRawStorage => write!(
+                f,
                 "No raw storage loaded. Please load data before running clustering."
-            }
-            AppError::NoClustStorage => {
+            ),
+            AppError::NoClustStorage => write!(
+                f,
                 "No clustered storage available. Please run clustering first."
-            }
-            AppError::IoError(msg) => msg,
-        };
-        write!(f, "{}", msg)
+            ),
+            AppError::UnknownSession => write!(f, "No such dataset or run in the session store."),
+            AppError::DatasetMismatch => write!(
+                f,
+                "The parameters read another input than the selected dataset. Please load it again."
+            ),
+            AppError::LoadFailed(err) => write!(f, "Loading failed: {}", err),
+            AppError::OutputFailed(err) => write!(f, "Output failed: {}", err),
+            AppError::TaskAborted => write!(
+                f,
+                "The clustering stopped without a result. See the console for the cause."
+            ),
+        }
     }
 }
 // ─────────────────────────────────────────────
@@ -78,4 +148,29 @@ impl ComputationEvent {
     pub fn has_subscribers(&self) -> bool {
         !self.subscribers.is_empty()
     }
+
+    /// Cloneable handle on the current subscribers, usable from worker threads.
+    pub fn sender(&self) -> EventSender {
+        EventSender {
+            subscribers: self.subscribers.clone(),
+        }
+    }
+}
+
+// ─────────────────────────────────────────────
+// EventSender : emits to a snapshot of the subscribers, through a shared reference
+// ─────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct EventSender {
+    subscribers: Vec<Sender<AppEvent>>,
+}
+
+impl EventSender {
+    pub fn emit(&self, event: AppEvent) {
+        // A closed subscriber is simply skipped; ComputationEvent drops it on its next emit
+        for tx in &self.subscribers {
+            let _ = tx.send(event.clone());
+        }
+    }
 }
@@ -0,0 +1,275 @@
+// map_view.rs - Pannable, zoomable map of the inputs and the clustering result
+//
+// The geometry is copied out of MainTraclusDL into MapLayers once per load or
+// computation (the storages sit behind the mutex the worker thread holds while running),
+// so drawing a frame never waits on a task. Drag pans, the scroll wheel zooms around
+// the pointer and a double-click fits the view back to the data.
+
+use eframe::egui::{self, Color32, Pos2, Rect, RichText, Sense, Stroke, Vec2};
+
+use crate::clustering::geometry::point::Point;
+use crate::clustering::storage::clustered_trajectories::ClusteredTrajectories;
+use crate::clustering::storage::raw_trajectories::RawTrajectories;
+use crate::gui::style::*;
+
+// World coordinates of a line: start and end
+type WorldLine = [Point; 2];
+
+// ─────────────────────────────────────────────
+// MapLayers — snapshot of the drawable geometry
+// ─────────────────────────────────────────────
+
+#[derive(Default)]
+pub struct MapLayers {
+    inputs: Vec<WorldLine>,
+    segments: Vec<(usize, WorldLine)>, // corridor index, segment
+    non_clustered: Vec<WorldLine>,
+    corridors: Vec<(u32, WorldLine)>, // weight, corridor
+    max_corridor_weight: u32,
+    bounds: Option<[Point; 2]>, // min, max
+}
+
+impl MapLayers {
+    pub fn from_storage(
+        raw_storage: Option<&RawTrajectories>,
+        clust_storage: Option<&ClusteredTrajectories>,
+    ) -> Self {
+        let mut layers: MapLayers = MapLayers::default();
+
+        if let Some(raw_storage) = raw_storage {
+            layers.inputs = raw_storage
+                .trajectories
+                .iter()
+                .flat_map(|traj| traj.legs().iter())
+                .map(|leg| [leg.start, leg.end])
+                .collect();
+        }
+
+        if let Some(clust_storage) = clust_storage {
+            for (corridor_id, cluster_member) in clust_storage.get_all_cluster_members_iter() {
+                let line: WorldLine = [cluster_member.start, cluster_member.end_point()];
+                if corridor_id < 0 {
+                    layers.non_clustered.push(line);
+                } else {
+                    layers.segments.push((corridor_id as usize, line));
+                }
+            }
+            layers.corridors = clust_storage
+                .corridors
+                .iter()
+                .map(|corridor| (corridor.weight, [corridor.start, corridor.end]))
+                .collect();
+            layers.max_corridor_weight =
+                layers.corridors.iter().map(|(w, _)| *w).max().unwrap_or(1);
+        }
+
+        layers.bounds = layers.compute_bounds();
+        layers
+    }
+
+    fn compute_bounds(&self) -> Option<[Point; 2]> {
+        let all_lines = self
+            .inputs
+            .iter()
+            .chain(self.segments.iter().map(|(_, line)| line))
+            .chain(self.non_clustered.iter())
+            .chain(self.corridors.iter().map(|(_, line)| line));
+
+        let mut bounds: Option<[Point; 2]> = None;
+        for point in all_lines.flatten() {
+            let [min, max] = bounds.get_or_insert([*point, *point]);
+            min.x = min.x.min(point.x);
+            min.y = min.y.min(point.y);
+            max.x = max.x.max(point.x);
+            max.y = max.y.max(point.y);
+        }
+        bounds
+    }
+}
+
+// ─────────────────────────────────────────────
+// MapView — layers, toggles and camera
+// ─────────────────────────────────────────────
+
+pub struct MapView {
+    pub layers: MapLayers,
+    pub needs_refresh: bool, // the storages changed since `layers` was taken
+
+    pub show_inputs: bool,
+    pub show_segments: bool,
+    pub show_non_clustered: bool,
+    pub show_corridors: bool,
+
+    zoom: f32, // 1.0 fits the data in the canvas
+    pan: Vec2, // screen offset in points
+}
+
+impl Default for MapView {
+    fn default() -> Self {
+        Self {
+            layers: MapLayers::default(),
+            needs_refresh: false,
+
+            show_inputs: true,
+            show_segments: true,
+            show_non_clustered: false,
+            show_corridors: true,
+
+            zoom: 1.0,
+            pan: Vec2::ZERO,
+        }
+    }
+}
+
+impl MapView {
+    /// Replaces the drawn geometry and fits the view to it.
+    pub fn set_layers(&mut self, layers: MapLayers) {
+        self.layers = layers;
+        self.needs_refresh = false;
+        self.reset_camera();
+    }
+
+    fn reset_camera(&mut self) {
+        self.zoom = 1.0;
+        self.pan = Vec2::ZERO;
+    }
+}
+
+// World → screen: fit the bounds in the canvas, then apply zoom and pan (y points up)
+struct Projection {
+    center: Pos2,
+    world_center: Point,
+    scale: f64,
+}
+
+impl Projection {
+    fn new(view: &MapView, rect: Rect) -> Option<Self> {
+        let [min, max] = view.layers.bounds?;
+        let width: f64 = (max.x - min.x).max(f64::EPSILON);
+        let height: f64 = (max.y - min.y).max(f64::EPSILON);
+        let fit: f64 = (rect.width() as f64 / width).min(rect.height() as f64 / height);
+
+        Some(Self {
+            center: rect.center() + view.pan,
+            world_center: Point {
+                x: (min.x + max.x) / 2.0,
+                y: (min.y + max.y) / 2.0,
+            },
+            scale: fit * MAP_FIT_MARGIN * view.zoom as f64,
+        })
+    }
+
+    fn to_screen(&self, point: &Point) -> Pos2 {
+        Pos2::new(
+            self.center.x + ((point.x - self.world_center.x) * self.scale) as f32,
+            self.center.y - ((point.y - self.world_center.y) * self.scale) as f32,
+        )
+    }
+
+    fn line(&self, line: &WorldLine) -> [Pos2; 2] {
+        [self.to_screen(&line[0]), self.to_screen(&line[1])]
+    }
+}
+
+// ─────────────────────────────────────────────
+// Rendering
+// ─────────────────────────────────────────────
+
+pub fn render_map(ui: &mut egui::Ui, view: &mut MapView) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut view.show_inputs, "Input");
+        ui.checkbox(&mut view.show_segments, "Segments");
+        ui.checkbox(&mut view.show_non_clustered, "Non-clustered");
+        ui.checkbox(&mut view.show_corridors, "Corridors");
+    });
+    ui.add_space(WIDGET_SPACING);
+
+    let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
+    let rect: Rect = response.rect;
+    painter.rect_filled(rect, CONTAINER_ROUNDING, COLOR_OUTPUT_BG);
+    painter.rect_stroke(rect, CONTAINER_ROUNDING, Stroke::new(1.0, COLOR_BORDER));
+
+    // Camera: drag to pan, wheel to zoom around the pointer, double-click to fit
+    if response.dragged() {
+        view.pan += response.drag_delta();
+    }
+    if let Some(pointer) = response.hover_pos() {
+        let scroll: f32 = ui.input(|i| i.smooth_scroll_delta.y);
+        if scroll != 0.0 {
+            let factor: f32 = (scroll * MAP_ZOOM_SPEED).exp();
+            let new_zoom: f32 = (view.zoom * factor).clamp(MAP_MIN_ZOOM, MAP_MAX_ZOOM);
+            let applied: f32 = new_zoom / view.zoom;
+            // Keep the world point under the pointer in place
+            let from_center: Vec2 = pointer - (rect.center() + view.pan);
+            view.pan -= from_center * (applied - 1.0);
+            view.zoom = new_zoom;
+        }
+    }
+    if response.double_clicked() {
+        view.reset_camera();
+    }
+
+    let Some(projection) = Projection::new(view, rect) else {
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "No data loaded.",
+            egui::FontId::proportional(14.0),
+            Color32::GRAY,
+        );
+        return;
+    };
+
+    let painter = painter.with_clip_rect(rect.shrink(1.0));
+    let layers: &MapLayers = &view.layers;
+    let visible = |line: &[Pos2; 2]| rect.intersects(Rect::from_two_pos(line[0], line[1]));
+
+    if view.show_inputs {
+        let stroke: Stroke = Stroke::new(1.0, MAP_INPUT_COLOR);
+        for line in layers.inputs.iter().map(|l| projection.line(l)) {
+            if visible(&line) {
+                painter.line_segment(line, stroke);
+            }
+        }
+    }
+
+    if view.show_non_clustered {
+        let stroke: Stroke = Stroke::new(1.0, MAP_NON_CLUSTERED_COLOR);
+        for line in layers.non_clustered.iter().map(|l| projection.line(l)) {
+            if visible(&line) {
+                painter.line_segment(line, stroke);
+            }
+        }
+    }
+
+    if view.show_segments {
+        for (corridor_id, segment) in &layers.segments {
+            let line: [Pos2; 2] = projection.line(segment);
+            if visible(&line) {
+                painter.line_segment(line, Stroke::new(1.5, corridor_color(*corridor_id)));
+            }
+        }
+    }
+
+    // Width proportional to weight, the heaviest corridor at MAP_CORRIDOR_MAX_WIDTH
+    if view.show_corridors {
+        for (weight, corridor) in &layers.corridors {
+            let line: [Pos2; 2] = projection.line(corridor);
+            if visible(&line) {
+                let share: f32 = *weight as f32 / layers.max_corridor_weight.max(1) as f32;
+                let width: f32 = 1.0 + share * (MAP_CORRIDOR_MAX_WIDTH - 1.0);
+                painter.line_segment(line, Stroke::new(width, MAP_CORRIDOR_COLOR));
+            }
+        }
+    }
+
+    if response.hovered() {
+        response.on_hover_text_at_pointer(
+            RichText::new("Drag to pan, scroll to zoom, double-click to fit").small(),
+        );
+    }
+}
+
+fn corridor_color(corridor_id: usize) -> Color32 {
+    MAP_CORRIDOR_PALETTE[corridor_id % MAP_CORRIDOR_PALETTE.len()]
+}
@@ -1,5 +1,6 @@
-pub mod traclusdl_app;
 pub mod app_events;
-mod view_model;
+mod map_view;
+mod style;
+pub mod traclusdl_app;
 mod traclusdl_gui;
-mod style;
\ No newline at end of file
+mod view_model;
@@ -3,7 +3,7 @@
 use eframe::egui::Color32;
 
 // ---------- Window ----------
-pub const WINDOW_WIDTH: f32 = 800.0;
+pub const WINDOW_WIDTH: f32 = 800.0 + MAP_PANEL_WIDTH;
 pub const WINDOW_HEIGHT: f32 = 500.0;
 
 // ---------- Spacing ----------
@@ -25,12 +25,30 @@ pub const SPACE_BETWEEN_FIELD: f32 = 8.0;
 // ---------- Buttons ----------
 pub const BROWSE_BTN_WIDTH: f32 = 100.0;
 pub const BROWSE_BTN_HEIGHT: f32 = 24.0;
+pub const BROWSE_MENU_WIDTH: f32 = 170.0;
+pub const DATASET_REMOVE_BTN_WIDTH: f32 = 24.0;
 pub const ACTION_BTN_WIDTH: f32 = 130.0;
 pub const ACTION_BTN_HEIGHT: f32 = 36.0;
 
+// ---------- Create output dialog ----------
+pub const OUTPUT_DIR_WIDTH: f32 = 320.0;
+
+// ---------- Compare runs window ----------
+pub const COMPARISON_WINDOW_WIDTH: f32 = 560.0;
+pub const COMPARISON_TABLE_HEIGHT: f32 = 240.0;
+
 // ---------- Output area ----------
 pub const OUTPUT_BOX_HEIGHT: f32 = 100.0;
 
+// ---------- Map panel ----------
+pub const MAP_PANEL_WIDTH: f32 = 520.0;
+pub const SESSIONS_LIST_HEIGHT: f32 = 120.0;
+pub const MAP_FIT_MARGIN: f64 = 0.95; // share of the canvas used by the data at zoom 1
+pub const MAP_ZOOM_SPEED: f32 = 0.005; // per scrolled point
+pub const MAP_MIN_ZOOM: f32 = 0.5;
+pub const MAP_MAX_ZOOM: f32 = 500.0;
+pub const MAP_CORRIDOR_MAX_WIDTH: f32 = 8.0;
+
 // ---------- Colors (dark theme) ----------
 pub const COLOR_BACKGROUND: Color32 = Color32::from_rgb(28, 28, 30);
 pub const COLOR_SECTION_BG: Color32 = Color32::from_rgb(40, 40, 44);
@@ -38,3 +56,18 @@ pub const COLOR_BORDER: Color32 = Color32::from_rgb(65, 65, 72);
 pub const COLOR_LABEL: Color32 = Color32::from_rgb(210, 210, 215);
 pub const COLOR_TEXT: Color32 = Color32::from_rgb(230, 230, 235);
 pub const COLOR_OUTPUT_BG: Color32 = Color32::from_rgb(22, 22, 24);
+
+// ---------- Map colors ----------
+pub const MAP_INPUT_COLOR: Color32 = Color32::from_rgba_premultiplied(90, 90, 100, 120);
+pub const MAP_NON_CLUSTERED_COLOR: Color32 = Color32::from_rgb(120, 70, 70);
+pub const MAP_CORRIDOR_COLOR: Color32 = Color32::from_rgb(245, 245, 250);
+pub const MAP_CORRIDOR_PALETTE: [Color32; 8] = [
+    Color32::from_rgb(230, 159, 0),
+    Color32::from_rgb(86, 180, 233),
+    Color32::from_rgb(0, 158, 115),
+    Color32::from_rgb(240, 228, 66),
+    Color32::from_rgb(0, 114, 178),
+    Color32::from_rgb(213, 94, 0),
+    Color32::from_rgb(204, 121, 167),
+    Color32::from_rgb(150, 200, 90),
+];
@@ -1,17 +1,28 @@
 // traclus_app.rs - Main application state and entry point
 
+use std::collections::{BTreeMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use eframe::egui;
 
 use crate::clustering::main_traclusdl::MainTraclusDL;
+use crate::clustering::storage::session_store::{
+    DatasetId, DatasetInfo, LoadParams, RunId, RunKey,
+};
+use crate::gui::app_events::{AppError, AppEvent, SessionChange};
+use crate::gui::map_view::MapLayers;
 use crate::gui::style::*;
-use crate::gui::view_model::ViewModel;
-use crate::gui::app_events::{AppEvent};
-use crate::io::args::TraclusArgs;
+use crate::gui::view_model::{
+    ComparisonView, EstimateParams, NO_ESTIMATE, OutputDialog, ViewModel,
+};
+use crate::io::args::{CsvOptions, CsvPreset, InputFormat, TraclusArgs};
+use crate::io::input_loader::SanitationReport;
+use crate::io::output_writer::OutputKind;
 use crate::utils::gui_parallel_runner::GuiParallelRunner;
+use crate::utils::time_estimator::format_duration;
 
 // ─────────────────────────────────────────────
 // Application State
@@ -27,11 +38,44 @@ pub struct TraclusDLApp {
     pub runner: GuiParallelRunner,
 
     event_rx: Receiver<AppEvent>,
+
+    // Session store dataset selected in the file section, shared by every row; a row's
+    // run is stored under (dataset, row id)
+    dataset: Option<DatasetId>,
+    inputs: BTreeMap<DatasetId, LoadedInput>, // every dataset of the store
+    pending_input: Option<LoadedInput>,       // browsed, its dataset id not known yet
+
+    // Snapshot of the session store for the Sessions list, taken like the map layers
+    pub sessions: Vec<DatasetInfo>,
+    pub sessions_memory: usize,
+    sessions_need_refresh: bool,
+
+    // "Compare runs" window, open while Some
+    pub comparison: Option<ComparisonView>,
+
+    // Row whose clustering events are being received, until its terminal event
+    // (complete, cancelled, error) is handled; the next one starts only after that
+    clustering_vm: Option<RunId>,
+    run_queue: VecDeque<RunId>,   // rows waiting for "Run all"
+    removed_results: Vec<RunKey>, // runs of removed rows, dropped at the next idle frame
+}
+
+// Input of a dataset, shown by every row while the dataset is selected
+#[derive(Clone)]
+struct LoadedInput {
+    file: String,
+    input_format: InputFormat,
+    csv: CsvOptions,
+    input_name: String,
+    num_dl: usize,
+    sanitation: SanitationReport,
+    runs: Vec<RunId>, // rows with a run of the dataset
 }
 
 impl TraclusDLApp {
     // TraclusDLApp::new is private — construction only via start_gui
     fn new(args: TraclusArgs, main_traclusdl: MainTraclusDL) -> Self {
+        let runner: GuiParallelRunner = GuiParallelRunner::new(main_traclusdl.cancellation_token());
         let main_traclus: Arc<Mutex<MainTraclusDL>> = Arc::new(Mutex::new(main_traclusdl));
         let event_rx: Receiver<AppEvent> = main_traclus.lock().unwrap().event.subscribe();
 
@@ -41,38 +85,321 @@ impl TraclusDLApp {
             detected_cpus: num_cpus_detected(),
 
             main_traclus,
-            runner: GuiParallelRunner::new(),
+            runner,
             event_rx,
+
+            dataset: None,
+            inputs: BTreeMap::new(),
+            pending_input: None,
+
+            sessions: Vec::new(),
+            sessions_memory: 0,
+            sessions_need_refresh: false,
+
+            comparison: None,
+
+            clustering_vm: None,
+            run_queue: VecDeque::new(),
+            removed_results: Vec::new(),
         }
     }
 
     // ─────────────────────────────────────────────
     // GUI button actions
     // ─────────────────────────────────────────────
-    pub fn on_browse_done(&mut self, path: PathBuf) {
-        let vm: &mut ViewModel = self.current_vm();
-        vm.args.file = path.display().to_string();
-        vm.input_name = path
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .into_owned();
+    // The loaded input is shared by every parameter row; datasets loaded before are kept
+    pub fn on_browse_done(
+        &mut self,
+        path: PathBuf,
+        input_format: InputFormat,
+        csv_preset: Option<CsvPreset>,
+    ) {
+        let input: LoadedInput = LoadedInput {
+            file: path.display().to_string(),
+            input_format,
+            csv: CsvOptions {
+                preset: csv_preset,
+                ..CsvOptions::default()
+            },
+            input_name: path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            num_dl: 0,
+            sanitation: SanitationReport::default(),
+            runs: Vec::new(),
+        };
+        self.show_input(Some(&input));
+        self.pending_input = Some(input);
 
-        vm.num_dl = 0;
-        vm.percent_correlation = 0.0;
+        // load_raw_storage emits the pre-run estimate for these parameters
+        let vm: &mut ViewModel = self.current_vm();
+        vm.estimated_params = Some(vm.estimate_params());
 
+        // The new dataset id comes back with SessionChanged, which selects it
         let args: TraclusArgs = vm.args.clone();
         self.launch(move |t| {
-            t.load_raw_storage(&args);
+            t.load_dataset(&args);
         });
     }
 
+    /// Shows an earlier loaded dataset in every row, with the runs the rows have of it.
+    pub fn on_select_dataset(&mut self, dataset: DatasetId) {
+        if self.is_busy() || self.dataset == Some(dataset) {
+            return;
+        }
+        self.dataset = Some(dataset);
+        let input: Option<LoadedInput> = self.inputs.get(&dataset).cloned();
+        self.show_input(input.as_ref());
+    }
+
+    /// Drops a dataset and all its runs from the session store.
+    pub fn on_remove_dataset(&mut self, dataset: DatasetId) {
+        if self.is_busy() {
+            return;
+        }
+        self.launch(move |t| t.drop_dataset(dataset));
+    }
+
+    /// Loaded datasets by increasing id, with the file name they were loaded from.
+    pub fn loaded_datasets(&self) -> impl Iterator<Item = (DatasetId, &str)> {
+        self.inputs
+            .iter()
+            .map(|(&id, input)| (id, input.input_name.as_str()))
+    }
+
+    pub fn selected_dataset(&self) -> Option<DatasetId> {
+        self.dataset
+    }
+
     pub fn on_start_computation(&mut self) {
+        let id: RunId = self.current_vm().id;
+        self.start_clustering(id);
+    }
+
+    /// Queues every parameter row; run_pending_tasks starts them one after the other.
+    /// Rows cutting the input the same way are queued together, so that the dataset is
+    /// reloaded only when the next row needs other buckets or segments.
+    pub fn on_start_all(&mut self) {
+        if self.is_busy() {
+            return;
+        }
+        let params: Vec<LoadParams> = self.vm.iter().map(|vm| LoadParams::of(&vm.args)).collect();
+        let mut order: Vec<usize> = (0..self.vm.len()).collect();
+        order.sort_by_key(|&i| params.iter().position(|p| *p == params[i]));
+        self.run_queue = order.into_iter().map(|i| self.vm[i].id).collect();
+    }
+
+    pub fn on_stop_computation(&mut self) {
+        self.run_queue.clear();
+        self.runner.cancel();
+    }
+
+    pub fn on_select_row(&mut self, idx: usize) {
+        self.current_selected_vm = idx;
+    }
+
+    pub fn on_add_row(&mut self) {
+        let row: ViewModel = ViewModel::new_row_like(self.current_vm());
+        self.vm.push(row);
+    }
+
+    pub fn on_remove_row(&mut self, idx: usize) {
+        let removed: ViewModel = self.vm.remove(idx);
+        self.run_queue.retain(|id| *id != removed.id);
+        let is_compared =
+            |view: &ComparisonView| view.run_a == removed.id || view.run_b == removed.id;
+        if self.comparison.as_ref().is_some_and(is_compared) {
+            self.comparison = None;
+        }
+        // Its runs of every dataset, not only of the selected one
+        for (&dataset, input) in self.inputs.iter_mut() {
+            if let Some(idx) = input.runs.iter().position(|run| *run == removed.id) {
+                input.runs.remove(idx);
+                self.removed_results.push(RunKey {
+                    dataset,
+                    run: removed.id,
+                });
+            }
+        }
+
+        // Keep current_selected_vm on the same row, and in bounds
+        if self.current_selected_vm > idx {
+            self.current_selected_vm -= 1;
+        }
+        self.current_selected_vm = self.current_selected_vm.min(self.vm.len() - 1);
+    }
+
+    pub fn on_create_output(&mut self) {
+        let vm: &mut ViewModel = self.current_vm();
+        vm.output_dialog = Some(OutputDialog::new(&vm.args.file));
+    }
+
+    /// Writes the outputs selected in the dialog; the dialog stays open if busy.
+    pub fn on_write_outputs(&mut self) {
+        let Some(dialog) = &self.current_vm().output_dialog else {
+            return;
+        };
+        let output_dir: Option<String> =
+            Some(dialog.output_dir.trim().to_string()).filter(|dir| !dir.is_empty());
+        let kinds: Vec<OutputKind> = dialog.selected_kinds();
+
+        let Some(dataset) = self.dataset else {
+            return;
+        };
+        let key: RunKey = RunKey {
+            dataset,
+            run: self.current_vm().id,
+        };
+        if self.launch(move |t| t.generate_outputs(key, output_dir, &kinds)) {
+            self.current_vm().output_dialog = None;
+        }
+    }
+
+    /// Ids of the rows holding a clustering result, in row order.
+    pub fn rows_with_results(&self) -> Vec<RunId> {
+        self.vm
+            .iter()
+            .filter(|vm| vm.has_results)
+            .map(|vm| vm.id)
+            .collect()
+    }
+
+    /// Opens the comparison window on the first two rows with results.
+    pub fn on_open_comparison(&mut self) {
+        if let [run_a, run_b, ..] = self.rows_with_results()[..] {
+            self.comparison = Some(ComparisonView {
+                run_a,
+                run_b,
+                result: None,
+            });
+        }
+    }
+
+    pub fn on_compare_runs(&mut self) {
+        let (Some(view), Some(dataset)) = (&self.comparison, self.dataset) else {
+            return;
+        };
+        let a: RunKey = RunKey {
+            dataset,
+            run: view.run_a,
+        };
+        let b: RunKey = RunKey {
+            dataset,
+            run: view.run_b,
+        };
+        self.launch(move |t| t.compare_runs(a, b));
+    }
+
+    /// Recomputes the pre-run estimate once the parameters changed, when data is loaded
+    /// and no task is running. Called every frame, so it must stay cheap otherwise.
+    pub fn refresh_estimate(&mut self) {
+        if self.is_busy() || self.current_vm().num_dl == 0 {
+            return;
+        }
+        let Some(dataset) = self.dataset else {
+            return;
+        };
+
+        let params: EstimateParams = self.current_vm().estimate_params();
+        if self.current_vm().estimated_params == Some(params) {
+            return;
+        }
+
         let args: TraclusArgs = self.current_vm().args.clone();
+        if self.launch(move |t| t.estimate_run_time(dataset, &args)) {
+            self.current_vm().estimated_params = Some(params);
+        }
+    }
 
-        self.launch(move |t| {
-            t.run_clustering(&args);
-        });
+    /// Copies the geometry of MainTraclusDL into the map once it changed and no task
+    /// holds the lock. Called every frame, so it must stay cheap otherwise.
+    pub fn refresh_map(&mut self) {
+        if !self.current_vm().map.needs_refresh || self.runner.is_running() {
+            return;
+        }
+        let Ok(main_traclus) = self.main_traclus.try_lock() else {
+            return;
+        };
+
+        let run: RunId = self.vm[self.current_selected_vm].id;
+        let layers: MapLayers = match self.dataset {
+            Some(dataset) => MapLayers::from_storage(
+                main_traclus.raw_storage(dataset),
+                main_traclus.clust_storage(RunKey { dataset, run }),
+            ),
+            None => MapLayers::default(),
+        };
+        drop(main_traclus);
+        self.current_vm().map.set_layers(layers);
+    }
+
+    /// Lists the session store again once it changed and no task holds the lock.
+    pub fn refresh_sessions(&mut self) {
+        if !self.sessions_need_refresh || self.runner.is_running() {
+            return;
+        }
+        let Ok(main_traclus) = self.main_traclus.try_lock() else {
+            return;
+        };
+
+        self.sessions = main_traclus.sessions().list();
+        self.sessions_memory = main_traclus.sessions().memory_bytes();
+        self.sessions_need_refresh = false;
+    }
+
+    /// Frees the results of removed rows, then starts the next queued row.
+    /// Called every frame; at most one task is launched.
+    pub fn run_pending_tasks(&mut self) {
+        if self.runner.is_running() {
+            return;
+        }
+        // clustering_vm stays set until the last row's terminal event is handled. Once the
+        // runner is idle every event of the task has been sent: without a terminal one among
+        // them (the task panicked), the row and the queue are released with an error.
+        if self.clustering_vm.is_some() {
+            self.drain_events();
+            if self.clustering_vm.is_some() {
+                self.handle_event(AppEvent::Error(AppError::TaskAborted));
+            }
+            return;
+        }
+
+        if !self.removed_results.is_empty() {
+            let keys: Vec<RunKey> = std::mem::take(&mut self.removed_results);
+            self.launch(move |t| keys.into_iter().for_each(|key| t.drop_run(key)));
+            return;
+        }
+
+        if let Some(id) = self.run_queue.pop_front() {
+            self.start_clustering(id);
+        }
+    }
+
+    /// True while a task runs or clustering rows are still pending.
+    pub fn is_busy(&self) -> bool {
+        self.runner.is_running() || self.clustering_vm.is_some() || !self.run_queue.is_empty()
+    }
+
+    fn start_clustering(&mut self, id: RunId) {
+        if self.clustering_vm.is_some() {
+            return;
+        }
+        let Some(vm) = self.vm.iter_mut().find(|vm| vm.id == id) else {
+            return;
+        };
+        let Some(dataset) = self.dataset else {
+            vm.output = format!("<< Error >>: {}", AppError::NoRawStorage);
+            self.run_queue.clear();
+            return;
+        };
+
+        let args: TraclusArgs = vm.args.clone();
+        let key: RunKey = RunKey { dataset, run: id };
+        if self.launch(move |t| t.run_clustering(&args, key)) {
+            self.clustering_vm = Some(id);
+        }
     }
 
     // ─────────────────────────────────────────────
@@ -88,19 +415,156 @@ impl TraclusDLApp {
     }
 
     fn handle_event(&mut self, event: AppEvent) {
-        let vm: &mut ViewModel = self.current_vm();
+        // Clustering events go to the row being clustered, the others to the selected row
+        let is_clustering_event: bool = matches!(
+            event,
+            AppEvent::TimeEstimate { .. }
+                | AppEvent::ComputationClusteringProgress { .. }
+                | AppEvent::CorridorFinalizationProgress { .. }
+                | AppEvent::ComputationComplete { .. }
+                | AppEvent::Cancelled
+                | AppEvent::Error(_)
+        );
+        let target: usize = self
+            .clustering_vm
+            .filter(|_| is_clustering_event)
+            .and_then(|id| self.vm.iter().position(|vm| vm.id == id))
+            .unwrap_or(self.current_selected_vm);
+        if matches!(
+            event,
+            AppEvent::ComputationComplete { .. } | AppEvent::Cancelled | AppEvent::Error(_)
+        ) {
+            self.clustering_vm = None;
+        }
 
-        match event {
+        // A new input (or a failed load) replaces what every row shows
+        match &event {
             AppEvent::LoadComplete {
                 traj_count,
+                sanitation,
+            } => {
+                if let Some(input) = self.dataset.and_then(|id| self.inputs.get_mut(&id)) {
+                    input.num_dl = *traj_count;
+                    input.sanitation = sanitation.clone();
+                }
+                for vm in self.vm.iter_mut() {
+                    vm.num_dl = *traj_count;
+                    vm.sanitation = sanitation.clone();
+                    vm.map.needs_refresh = true;
+                }
+                let vm: &mut ViewModel = self.current_vm();
+                for warning in &sanitation.warnings {
+                    vm.output += &format!("Warning: {}\n", warning);
+                }
+                return;
+            }
+            AppEvent::SessionChanged {
+                dataset,
+                run,
+                change,
+                ..
+            } => {
+                self.sessions_need_refresh = true;
+                match (run, change) {
+                    (None, SessionChange::Loaded) => {
+                        self.dataset = Some(*dataset);
+                        if let Some(input) = self.pending_input.take() {
+                            self.inputs.insert(*dataset, input);
+                        }
+                    }
+                    (None, SessionChange::Dropped) => {
+                        self.inputs.remove(dataset);
+                        if self.dataset == Some(*dataset) {
+                            self.dataset = None;
+                            self.show_input(None);
+                        }
+                    }
+                    // The row was removed while its run was computing
+                    (Some(run), SessionChange::RunStored)
+                        if !self.vm.iter().any(|vm| vm.id == *run) =>
+                    {
+                        self.removed_results.push(RunKey {
+                            dataset: *dataset,
+                            run: *run,
+                        });
+                    }
+                    (Some(run), SessionChange::RunStored) => {
+                        if let Some(input) = self.inputs.get_mut(dataset)
+                            && !input.runs.contains(run)
+                        {
+                            input.runs.push(*run);
+                        }
+                    }
+                    (Some(run), SessionChange::Dropped) => {
+                        if let Some(input) = self.inputs.get_mut(dataset) {
+                            input.runs.retain(|id| id != run);
+                        }
+                    }
+                    _ => {}
+                }
+                return;
+            }
+            AppEvent::ComparisonComplete(comparison) => {
+                if let Some(view) = &mut self.comparison {
+                    view.result = Some((**comparison).clone());
+                }
+                return;
+            }
+            // The rows go back to the dataset selected before the failed load
+            AppEvent::Error(AppError::LoadFailed(_)) => {
+                self.pending_input = None;
+                let input: Option<LoadedInput> =
+                    self.dataset.and_then(|id| self.inputs.get(&id)).cloned();
+                self.show_input(input.as_ref());
+            }
+            AppEvent::Error(_) => self.run_queue.clear(),
+            _ => {}
+        }
+
+        let vm: &mut ViewModel = &mut self.vm[target];
+        match event {
+            AppEvent::LoadComplete { .. }
+            | AppEvent::SessionChanged { .. }
+            | AppEvent::ComparisonComplete(_) => {}
+
+            AppEvent::CorrelationEstimate {
                 correlation_percent,
             } => {
-                vm.num_dl = traj_count;
                 vm.percent_correlation = correlation_percent;
             }
 
-            AppEvent::ComputationClusteringProgress { num_traj_done } => {
-                vm.output += &format!("Clustering progress: {} trajectories done.", num_traj_done);
+            AppEvent::PreRunEstimate { estimated_secs } => {
+                vm.estimated_time = format!(
+                    "~ {}",
+                    format_duration(Duration::from_secs_f64(estimated_secs))
+                );
+            }
+
+            AppEvent::TimeEstimate { remaining_secs } => {
+                vm.estimated_time = format!(
+                    "{} left",
+                    format_duration(Duration::from_secs_f64(remaining_secs))
+                );
+            }
+
+            AppEvent::ComputationClusteringProgress {
+                num_traj_done,
+                total_traj,
+            } => {
+                vm.progress = format!(
+                    "Clustering progress: {}/{} trajectories done.",
+                    num_traj_done, total_traj
+                );
+            }
+
+            AppEvent::CorridorFinalizationProgress {
+                num_corridors_done,
+                num_clusters_left,
+            } => {
+                vm.progress = format!(
+                    "Corridor finalization: {} corridors done, {} clusters left.",
+                    num_corridors_done, num_clusters_left
+                );
             }
 
             AppEvent::ComputationComplete {
@@ -108,12 +572,28 @@ impl TraclusDLApp {
                 total_seg,
                 total_seg_outside_corridor,
             } => {
+                vm.progress.clear();
+                vm.estimated_params = None; // back to the pre-run estimate
+                vm.has_results = true;
+                vm.map.needs_refresh = true;
                 vm.output += &format!(
-                    "Computation complete: {} corridors, {} segments, {} segments outside corridor.",
+                    "Computation complete: {} corridors, {} segments, {} segments outside corridor.\n",
                     total_corridors, total_seg, total_seg_outside_corridor
                 );
             }
 
+            AppEvent::Cancelled => {
+                vm.progress.clear();
+                vm.estimated_params = None;
+                vm.output += "Computation cancelled, previous results kept.\n";
+            }
+
+            AppEvent::OutputsWritten { files } => {
+                for file in files {
+                    vm.output += &format!("Output written: {}\n", file);
+                }
+            }
+
             AppEvent::Error(msg) => {
                 vm.output = format!("<< Error >>: {}", msg);
             }
@@ -121,11 +601,42 @@ impl TraclusDLApp {
     }
 
     /// Launches a task on the worker thread via GuiParallelRunner.
-    pub fn launch<F>(&mut self, task: F)
+    /// Returns false if another task is still running.
+    pub fn launch<F>(&mut self, task: F) -> bool
     where
         F: FnOnce(&mut MainTraclusDL) + Send + 'static,
     {
-        self.runner.try_run(Arc::clone(&self.main_traclus), task);
+        self.runner.try_run(Arc::clone(&self.main_traclus), task)
+    }
+
+    // Puts `input` (None: no data) in every row; a row has results when it has a run of it.
+    // The estimates are computed again for the selected row by refresh_estimate.
+    fn show_input(&mut self, input: Option<&LoadedInput>) {
+        for vm in self.vm.iter_mut() {
+            match input {
+                Some(input) => {
+                    vm.args.file = input.file.clone();
+                    vm.args.input.input_format = input.input_format;
+                    vm.args.input.csv = input.csv.clone();
+                    vm.input_name = input.input_name.clone();
+                    vm.num_dl = input.num_dl;
+                    vm.sanitation = input.sanitation.clone();
+                    vm.has_results = input.runs.contains(&vm.id);
+                }
+                None => {
+                    vm.input_name.clear();
+                    vm.num_dl = 0;
+                    vm.sanitation = SanitationReport::default();
+                    vm.has_results = false;
+                }
+            }
+            vm.percent_correlation = 0.0;
+            vm.estimated_time = NO_ESTIMATE.to_string();
+            vm.estimated_params = None;
+            vm.map.needs_refresh = true;
+        }
+        self.run_queue.clear();
+        self.comparison = None; // compared runs of the previous dataset
     }
 
     /// Returns a mutable reference to the currently selected ViewModel.
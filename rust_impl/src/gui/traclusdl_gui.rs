@@ -6,10 +6,14 @@ use eframe::egui;
 use eframe::egui::{RichText, ScrollArea, TextEdit, Vec2};
 use rfd::FileDialog;
 
+use crate::clustering::storage::session_store::{DatasetId, RunId};
+use crate::gui::map_view::render_map;
 use crate::gui::style::*;
 use crate::gui::traclusdl_app::TraclusDLApp;
-use crate::io::args::ExecutionMode;
+use crate::io::args::{CsvPreset, ExecutionMode, InputFormat};
 use crate::io::args_config::get_param_configs;
+use crate::io::input_loader::SanitationReport;
+use crate::utils::time_estimator::format_duration;
 
 // ─────────────────────────────────────────────
 // App Update (main render loop)
@@ -18,10 +22,14 @@ use crate::io::args_config::get_param_configs;
 impl eframe::App for TraclusDLApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.drain_events();
+        self.refresh_estimate();
+        self.refresh_map();
+        self.refresh_sessions();
+        self.run_pending_tasks();
 
         // Request a repaint every frame while a task is running so the
         // progress display stays live without user interaction
-        if self.runner.is_running() {
+        if self.is_busy() {
             ctx.request_repaint();
         }
 
@@ -38,6 +46,27 @@ impl eframe::App for TraclusDLApp {
         visuals.widgets.open.fg_stroke.color = COLOR_TEXT;
         ctx.set_visuals(visuals);
 
+        // Map on the right, the form keeps its centered column in the remaining space
+        egui::SidePanel::right("map_panel")
+            .exact_width(MAP_PANEL_WIDTH)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add_space(SECTION_SPACING);
+                render_sessions(ui, self);
+                ui.add_space(WIDGET_SPACING);
+                let can_compare = !self.is_busy() && self.rows_with_results().len() >= 2;
+                if ui
+                    .add_enabled(can_compare, egui::Button::new("Compare runs…"))
+                    .on_disabled_hover_text("Needs two rows with results")
+                    .clicked()
+                {
+                    self.on_open_comparison();
+                }
+                ui.add_space(WIDGET_SPACING);
+                ui.label(RichText::new("MAP").color(COLOR_LABEL).strong());
+                render_map(ui, &mut self.current_vm().map);
+            });
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.add_space(SECTION_SPACING);
 
@@ -75,6 +104,9 @@ impl eframe::App for TraclusDLApp {
                 });
             });
         });
+
+        render_output_dialog(ctx, self);
+        render_comparison_window(ctx, self);
     }
 }
 
@@ -114,27 +146,23 @@ fn render_file_section(ui: &mut egui::Ui, app: &mut TraclusDLApp) {
 
         ui.add_space(WIDGET_SPACING);
 
-        // Fields row — all read-only; values come from current_vm()
+        // Fields row — read-only but the dataset; values come from current_vm()
         ui.horizontal(|ui| {
-            ui.add(
-                TextEdit::singleline(&mut app.current_vm().input_name)
-                    .desired_width(FILE_INPUT_WIDTH)
-                    .clip_text(true)
-                    .interactive(false)
-                    .text_color(COLOR_TEXT),
-            );
+            render_dataset_selector(ui, app);
             ui.add_space(SPACE_BETWEEN_FIELD);
 
             let mut num_str = app.current_vm().num_dl.to_string();
+            let sanitation: SanitationReport = app.current_vm().sanitation.clone();
             ui.add(
                 TextEdit::singleline(&mut num_str)
                     .desired_width(NUM_DL_WIDTH)
                     .interactive(false)
                     .text_color(COLOR_TEXT),
-            );
+            )
+            .on_hover_text(format!("Input sanitation: {}", sanitation));
             ui.add_space(SPACE_BETWEEN_FIELD);
 
-            let mut pct_str = app.current_vm().percent_correlation.to_string();
+            let mut pct_str = format!("{:.2}", app.current_vm().percent_correlation);
             ui.add(
                 TextEdit::singleline(&mut pct_str)
                     .desired_width(PERCENT_CORR_WIDTH)
@@ -143,24 +171,91 @@ fn render_file_section(ui: &mut egui::Ui, app: &mut TraclusDLApp) {
             );
             ui.add_space(SPACE_BETWEEN_FIELD);
 
-            if ui
-                .add_sized(
-                    [BROWSE_BTN_WIDTH, BROWSE_BTN_HEIGHT],
-                    egui::Button::new("Browse File"),
-                )
-                .clicked()
-            {
-                if let Some(path) = FileDialog::new()
-                    .add_filter("Text file", &["txt"])
-                    .pick_file()
-                {
-                    app.on_browse_done(path);
+            // The format is chosen from a menu before the file dialog opens
+            let browse = ui.add_sized(
+                [BROWSE_BTN_WIDTH, BROWSE_BTN_HEIGHT],
+                egui::Button::new("Browse File"),
+            );
+            let popup_id = ui.make_persistent_id("browse_format_popup");
+            if browse.clicked() {
+                ui.memory_mut(|mem| mem.toggle_popup(popup_id));
+            }
+
+            let mut picked: Option<(InputFormat, Option<CsvPreset>)> = None;
+            egui::popup_below_widget(ui, popup_id, &browse, |ui| {
+                ui.set_min_width(BROWSE_MENU_WIDTH);
+                if ui.button("OD list (.txt)").clicked() {
+                    picked = Some((InputFormat::OdList, None));
+                }
+                if ui.button("CSV / TSV (auto-detect)").clicked() {
+                    picked = Some((InputFormat::Csv, None));
                 }
+                if ui.button("Enquête OD (.csv)").clicked() {
+                    picked = Some((InputFormat::Csv, Some(CsvPreset::Enquete)));
+                }
+                if ui.button("GeoJSON (.geojson)").clicked() {
+                    picked = Some((InputFormat::GeoJson, None));
+                }
+            });
+
+            if let Some((input_format, csv_preset)) = picked
+                && let Some(path) = file_dialog_for(input_format).pick_file()
+            {
+                app.on_browse_done(path, input_format, csv_preset);
             }
         });
     });
 }
 
+// Every loaded dataset stays in the session store until removed here; the selected one
+// is the input of every row
+fn render_dataset_selector(ui: &mut egui::Ui, app: &mut TraclusDLApp) {
+    let is_busy: bool = app.is_busy();
+    let selected: Option<DatasetId> = app.selected_dataset();
+    let datasets: Vec<(DatasetId, String)> = app
+        .loaded_datasets()
+        .map(|(id, name)| (id, format!("Dataset {}: {}", id, name)))
+        .collect();
+
+    let mut picked: Option<DatasetId> = None;
+    ui.add_enabled_ui(!is_busy, |ui| {
+        egui::ComboBox::from_id_source("dataset_selector")
+            .width(FILE_INPUT_WIDTH - DATASET_REMOVE_BTN_WIDTH - ui.spacing().item_spacing.x)
+            .selected_text(app.current_vm().input_name.clone())
+            .show_ui(ui, |ui| {
+                for (id, label) in &datasets {
+                    if ui.selectable_label(selected == Some(*id), label).clicked() {
+                        picked = Some(*id);
+                    }
+                }
+            });
+    });
+    if let Some(dataset) = picked {
+        app.on_select_dataset(dataset);
+    }
+
+    let can_remove: bool = !is_busy && selected.is_some();
+    if ui
+        .add_enabled(
+            can_remove,
+            egui::Button::new("✕").min_size(Vec2::new(DATASET_REMOVE_BTN_WIDTH, 0.0)),
+        )
+        .on_hover_text("Remove this dataset and its runs from memory")
+        .clicked()
+        && let Some(dataset) = selected
+    {
+        app.on_remove_dataset(dataset);
+    }
+}
+
+fn file_dialog_for(input_format: InputFormat) -> FileDialog {
+    match input_format {
+        InputFormat::Csv => FileDialog::new().add_filter("CSV file", &["csv", "tsv", "txt"]),
+        InputFormat::GeoJson => FileDialog::new().add_filter("GeoJSON file", &["geojson", "json"]),
+        _ => FileDialog::new().add_filter("Text file", &["txt"]),
+    }
+}
+
 // ─────────────────────────────────────────────
 // Section: Parameters
 // ─────────────────────────────────────────────
@@ -203,13 +298,18 @@ fn render_parameters_section(ui: &mut egui::Ui, app: &mut TraclusDLApp) {
 
                 ui.separator();
 
-                // One row per ViewModel in the list
+                // One row per ViewModel in the list; clicking "#N" shows that row's result
+                let mut to_select: Option<usize> = None;
                 let mut to_remove: Option<usize> = None;
                 let vm_count = app.vm.len();
+                let selected = app.current_selected_vm;
 
                 for (idx, vm) in app.vm.iter_mut().enumerate() {
                     ui.horizontal(|ui| {
-                        ui.label(RichText::new(format!("#{}", idx + 1)).color(COLOR_TEXT));
+                        let row_label = RichText::new(format!("#{}", idx + 1)).color(COLOR_TEXT);
+                        if ui.selectable_label(idx == selected, row_label).clicked() {
+                            to_select = Some(idx);
+                        }
                         ui.add_space(4.0);
 
                         // max_angle
@@ -261,12 +361,11 @@ fn render_parameters_section(ui: &mut egui::Ui, app: &mut TraclusDLApp) {
                     });
                 }
 
+                if let Some(idx) = to_select {
+                    app.on_select_row(idx);
+                }
                 if let Some(idx) = to_remove {
-                    app.vm.remove(idx);
-                    // Keep current_selected_vm in bounds after removal
-                    if app.current_selected_vm >= app.vm.len() {
-                        app.current_selected_vm = app.vm.len().saturating_sub(1);
-                    }
+                    app.on_remove_row(idx);
                 }
             });
 
@@ -276,7 +375,7 @@ fn render_parameters_section(ui: &mut egui::Ui, app: &mut TraclusDLApp) {
             ui.allocate_ui(Vec2::new(add_col_width, ui.available_height()), |ui| {
                 ui.centered_and_justified(|ui| {
                     if ui.add_sized([36.0, 36.0], egui::Button::new("+")).clicked() {
-                        app.vm.push(crate::gui::view_model::ViewModel::default());
+                        app.on_add_row();
                     }
                 });
             });
@@ -405,7 +504,11 @@ fn render_output_section(ui: &mut egui::Ui, app: &mut TraclusDLApp) {
                 .show(ui, |ui| {
                     // Subtract scrollbar width (~12 px) so text doesn't clip under it
                     ui.set_min_width(INNER_WIDTH - 12.0);
-                    let output = &app.current_vm().output;
+                    let vm = app.current_vm();
+                    if !vm.progress.is_empty() {
+                        ui.label(RichText::new(vm.progress.as_str()).color(COLOR_LABEL));
+                    }
+                    let output = &vm.output;
                     if output.is_empty() {
                         ui.label(
                             RichText::new("No output yet.")
@@ -426,45 +529,338 @@ fn render_output_section(ui: &mut egui::Ui, app: &mut TraclusDLApp) {
 fn render_action_bar(ui: &mut egui::Ui, app: &mut TraclusDLApp) {
     ui.set_min_width(CONTAINER_WIDTH);
 
-    let is_running = app.runner.is_running();
+    // Busy: a task is running or "Run all" still has rows to cluster
+    let is_busy = app.is_busy();
 
     ui.horizontal(|ui| {
-        if ui
-            .add_sized(
-                [ACTION_BTN_WIDTH, ACTION_BTN_HEIGHT],
-                egui::Button::new("Start\nComputation"),
-            )
-            .clicked()
-        {
-            app.on_start_computation();
-        }
-
-        ui.add_space(16.0);
-
-        ui.vertical(|ui| {
-            ui.add_space(4.0);
-            ui.label(RichText::new("Estimated time:").color(COLOR_LABEL));
-            ui.label(RichText::new("-- min --s").color(COLOR_TEXT));
-        });
-
-        // Stop + Create output flush to the right edge of CONTAINER_WIDTH
-        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+        // Start and Run all stay disabled while busy
+        ui.add_enabled_ui(!is_busy, |ui| {
             if ui
                 .add_sized(
                     [ACTION_BTN_WIDTH, ACTION_BTN_HEIGHT],
-                    egui::Button::new("Create output"),
+                    egui::Button::new("Start\nComputation"),
                 )
                 .clicked()
             {
-                println!("Create output clicked");
+                app.on_start_computation();
             }
-            ui.add_space(8.0);
             if ui
-                .add_sized([80.0, ACTION_BTN_HEIGHT], egui::Button::new("Stop"))
+                .add_sized([80.0, ACTION_BTN_HEIGHT], egui::Button::new("Run all"))
+                .on_hover_text("Cluster every parameter row, one after the other")
                 .clicked()
             {
-                println!("Stop clicked");
+                app.on_start_all();
             }
         });
+
+        ui.add_space(16.0);
+
+        ui.vertical(|ui| {
+            ui.add_space(4.0);
+            ui.label(RichText::new("Estimated time:").color(COLOR_LABEL));
+            ui.label(RichText::new(&app.current_vm().estimated_time).color(COLOR_TEXT));
+        });
+
+        // Stop + Create output flush to the right edge of CONTAINER_WIDTH
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            // Create output needs a clustered result for the selected row and an idle runner
+            let can_output = !is_busy && app.current_vm().has_results;
+            ui.add_enabled_ui(can_output, |ui| {
+                if ui
+                    .add_sized(
+                        [ACTION_BTN_WIDTH, ACTION_BTN_HEIGHT],
+                        egui::Button::new("Create output"),
+                    )
+                    .clicked()
+                {
+                    app.on_create_output();
+                }
+            });
+            ui.add_space(8.0);
+            // Stop cancels the running task and the rest of "Run all"
+            ui.add_enabled_ui(is_busy, |ui| {
+                if ui
+                    .add_sized([80.0, ACTION_BTN_HEIGHT], egui::Button::new("Stop"))
+                    .clicked()
+                {
+                    app.on_stop_computation();
+                }
+            });
+        });
     });
 }
+
+// ─────────────────────────────────────────────
+// Map panel: datasets and runs held in memory
+// ─────────────────────────────────────────────
+
+fn render_sessions(ui: &mut egui::Ui, app: &TraclusDLApp) {
+    let title = format!(
+        "SESSIONS — {} dataset(s), {}",
+        app.sessions.len(),
+        format_megabytes(app.sessions_memory)
+    );
+
+    egui::CollapsingHeader::new(RichText::new(title).color(COLOR_LABEL).strong())
+        .id_source("sessions")
+        .show(ui, |ui| {
+            ScrollArea::vertical()
+                .max_height(SESSIONS_LIST_HEIGHT)
+                .show(ui, |ui| {
+                    if app.sessions.is_empty() {
+                        ui.label(RichText::new("No data loaded.").small().color(COLOR_LABEL));
+                    }
+                    for dataset in &app.sessions {
+                        ui.label(
+                            RichText::new(format!(
+                                "Dataset {}: {} — {} lines, loaded in {}, {}",
+                                dataset.id,
+                                dataset.source,
+                                dataset.traj_count,
+                                format_duration(dataset.loading_time),
+                                format_megabytes(dataset.memory_bytes)
+                            ))
+                            .small()
+                            .color(COLOR_TEXT),
+                        );
+                        for run in &dataset.runs {
+                            // Runs are keyed by row id; removed rows are about to be dropped
+                            let row: String = app
+                                .vm
+                                .iter()
+                                .position(|vm| vm.id == run.key.run)
+                                .map_or("removed row".to_string(), |idx| format!("#{}", idx + 1));
+                            ui.label(
+                                RichText::new(format!(
+                                    "    {} (angle {}, density {}, dist {}, seg {}): {} corridors, \
+                                     {} segments, {} outside, {} in {}, {}",
+                                    row,
+                                    run.args.max_angle,
+                                    run.args.min_density,
+                                    run.args.max_dist,
+                                    run.args.segment_size,
+                                    run.total_corridors,
+                                    run.total_seg,
+                                    run.total_seg_outside_corridor,
+                                    run.args.mode,
+                                    format_duration(run.clustering_time),
+                                    format_megabytes(run.memory_bytes)
+                                ))
+                                .small()
+                                .color(COLOR_TEXT),
+                            );
+                        }
+                    }
+                });
+        });
+}
+
+fn format_megabytes(bytes: usize) -> String {
+    format!("{:.1} MB", bytes as f64 / 1e6)
+}
+
+// ─────────────────────────────────────────────
+// Window: Create output
+// ─────────────────────────────────────────────
+
+fn render_output_dialog(ctx: &egui::Context, app: &mut TraclusDLApp) {
+    let is_running = app.runner.is_running();
+    let Some(dialog) = app.current_vm().output_dialog.as_mut() else {
+        return;
+    };
+
+    let mut write = false;
+    let mut close = false;
+
+    egui::Window::new("Create output")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label(RichText::new("Destination directory").color(COLOR_LABEL));
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut dialog.output_dir)
+                        .desired_width(OUTPUT_DIR_WIDTH)
+                        .text_color(COLOR_TEXT),
+                );
+                if ui.button("Choose…").clicked()
+                    && let Some(dir) = FileDialog::new()
+                        .set_directory(&dialog.output_dir)
+                        .pick_folder()
+                {
+                    dialog.output_dir = dir.display().to_string();
+                }
+            });
+
+            ui.add_space(WIDGET_SPACING);
+            ui.label(RichText::new("Formats").color(COLOR_LABEL));
+            for (kind, selected) in dialog.selected.iter_mut() {
+                ui.checkbox(selected, kind.label());
+            }
+
+            ui.add_space(WIDGET_SPACING);
+            ui.horizontal(|ui| {
+                let any_selected = dialog.selected.iter().any(|(_, selected)| *selected);
+                write = ui
+                    .add_enabled(any_selected && !is_running, egui::Button::new("Write"))
+                    .clicked();
+                close = ui.button("Cancel").clicked();
+            });
+        });
+
+    if write {
+        app.on_write_outputs();
+    } else if close {
+        app.current_vm().output_dialog = None;
+    }
+}
+
+// ─────────────────────────────────────────────
+// Window: Compare runs
+// ─────────────────────────────────────────────
+
+fn render_comparison_window(ctx: &egui::Context, app: &mut TraclusDLApp) {
+    let is_busy = app.is_busy();
+    let rows: Vec<(usize, RunId)> = app
+        .vm
+        .iter()
+        .enumerate()
+        .filter(|(_, vm)| vm.has_results)
+        .map(|(idx, vm)| (idx, vm.id))
+        .collect();
+    let row_label = |id: RunId| -> String {
+        rows.iter()
+            .find(|(_, run)| *run == id)
+            .map_or(String::from("-"), |(idx, _)| format!("#{}", idx + 1))
+    };
+    let Some(view) = app.comparison.as_mut() else {
+        return;
+    };
+
+    let mut compare = false;
+    let mut close = false;
+
+    egui::Window::new("Compare runs")
+        .collapsible(false)
+        .resizable(true)
+        .default_width(COMPARISON_WINDOW_WIDTH)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for (label, run) in [("Run A", &mut view.run_a), ("Run B", &mut view.run_b)] {
+                    ui.label(RichText::new(label).color(COLOR_LABEL));
+                    egui::ComboBox::from_id_source(label)
+                        .selected_text(row_label(*run))
+                        .show_ui(ui, |ui| {
+                            for (_, id) in &rows {
+                                ui.selectable_value(run, *id, row_label(*id));
+                            }
+                        });
+                    ui.add_space(SPACE_BETWEEN_FIELD);
+                }
+                compare = ui
+                    .add_enabled(
+                        !is_busy && view.run_a != view.run_b,
+                        egui::Button::new("Compare"),
+                    )
+                    .clicked();
+                close = ui.button("Close").clicked();
+            });
+
+            // Only show a result for the rows currently selected
+            let Some(result) = view
+                .result
+                .as_ref()
+                .filter(|r| r.a.key.run == view.run_a && r.b.key.run == view.run_b)
+            else {
+                return;
+            };
+            let report = &result.report;
+
+            ui.add_space(WIDGET_SPACING);
+            if !result.same_dataset {
+                ui.label(
+                    RichText::new(
+                        "The runs come from different inputs, segment ids may not match.",
+                    )
+                    .color(egui::Color32::YELLOW),
+                );
+            }
+            ui.label(
+                RichText::new(format!(
+                    "{:.2}% of the {} matched segments assigned consistently \
+                     (clustered similarity {:.4}, status similarity {:.4})",
+                    report.consistency_share() * 100.0,
+                    report.matched_segments,
+                    report.clustered_similarity(),
+                    report.status_similarity()
+                ))
+                .color(COLOR_TEXT),
+            );
+            ui.label(
+                RichText::new(format!(
+                    "Clustered in both {}, in neither {}, only in A {}, only in B {}",
+                    report.both_clustered,
+                    report.both_non_clustered,
+                    report.only_clustered_a,
+                    report.only_clustered_b
+                ))
+                .small()
+                .color(COLOR_LABEL),
+            );
+
+            ui.add_space(WIDGET_SPACING);
+            ScrollArea::vertical()
+                .max_height(COMPARISON_TABLE_HEIGHT)
+                .show(ui, |ui| {
+                    egui::Grid::new("corridor_matches")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for header in [
+                                "A",
+                                "B",
+                                "shared",
+                                "weight A",
+                                "weight B",
+                                "Δweight",
+                                "start disp",
+                                "end disp",
+                            ] {
+                                ui.label(RichText::new(header).small().strong().color(COLOR_LABEL));
+                            }
+                            ui.end_row();
+
+                            for m in &report.corridor_matches {
+                                ui.label(m.corridor_a.to_string());
+                                ui.label(m.corridor_b.to_string());
+                                ui.label(m.shared_segments.to_string());
+                                ui.label(m.weight_a.to_string());
+                                ui.label(m.weight_b.to_string());
+                                ui.label(format!("{:+}", m.weight_difference()));
+                                ui.label(format!("{:.2}", m.start_displacement));
+                                ui.label(format!("{:.2}", m.end_displacement));
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            ui.add_space(WIDGET_SPACING);
+            for (label, ids) in [
+                ("Unmatched corridors of A", &report.unmatched_corridors_a),
+                ("Unmatched corridors of B", &report.unmatched_corridors_b),
+            ] {
+                let list: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+                ui.label(
+                    RichText::new(format!("{} ({}): {}", label, ids.len(), list.join(", ")))
+                        .small()
+                        .color(COLOR_LABEL),
+                );
+            }
+        });
+
+    if compare {
+        app.on_compare_runs();
+    } else if close {
+        app.comparison = None;
+    }
+}
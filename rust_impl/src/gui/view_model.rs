@@ -1,7 +1,14 @@
 // view_model.rs - Data bound to the GUI fields (form state)
 
-use crate::io::args::TraclusArgs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::clustering::storage::session_store::{RunComparison, RunId};
+use crate::gui::map_view::MapView;
+use crate::io::args::{ExecutionMode, OutputFormat, TraclusArgs};
 use crate::io::args_config::get_param_configs;
+use crate::io::input_loader::SanitationReport;
+use crate::io::output_writer::OutputKind;
 
 // ─────────────────────────────────────────────
 // ArgsBuffer
@@ -14,6 +21,18 @@ pub struct ArgsBuffer {
     pub segment_size: String,
 }
 
+impl ArgsBuffer {
+    // Buffers showing the committed values of `args`
+    pub fn from_args(args: &TraclusArgs) -> Self {
+        Self {
+            max_dist: args.max_dist.to_string(),
+            min_density: args.min_density.to_string(),
+            max_angle: args.max_angle.to_string(),
+            segment_size: args.segment_size.to_string(),
+        }
+    }
+}
+
 impl Default for ArgsBuffer {
     fn default() -> Self {
         // Initialise buffers from the same defaults as TraclusArgs
@@ -27,48 +46,139 @@ impl Default for ArgsBuffer {
     }
 }
 
+// Parameters the displayed pre-run estimate was computed for
+pub type EstimateParams = (f64, u32, f64, f64, ExecutionMode);
+
+// ─────────────────────────────────────────────
+// OutputDialog
+// ─────────────────────────────────────────────
+
+// State of the "Create output" window, open while the ViewModel holds one
+pub struct OutputDialog {
+    pub output_dir: String,
+    pub selected: Vec<(OutputKind, bool)>,
+}
+
+impl OutputDialog {
+    // Opens on the input directory with the three text outputs checked, as the CLI writes them
+    pub fn new(input_file: &str) -> Self {
+        let output_dir: String = Path::new(input_file)
+            .parent()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default();
+        let text_outputs: &[OutputKind] = OutputKind::for_format(OutputFormat::Text);
+
+        Self {
+            output_dir,
+            selected: OutputKind::ALL
+                .iter()
+                .map(|kind| (*kind, text_outputs.contains(kind)))
+                .collect(),
+        }
+    }
+
+    pub fn selected_kinds(&self) -> Vec<OutputKind> {
+        self.selected
+            .iter()
+            .filter(|(_, selected)| *selected)
+            .map(|(kind, _)| *kind)
+            .collect()
+    }
+}
+
+// ─────────────────────────────────────────────
+// ComparisonView
+// ─────────────────────────────────────────────
+
+// State of the "Compare runs" window: two rows with results, and the last comparison
+pub struct ComparisonView {
+    pub run_a: RunId,
+    pub run_b: RunId,
+    pub result: Option<RunComparison>,
+}
+
 // ─────────────────────────────────────────────
 // ViewModel
 // ─────────────────────────────────────────────
 
+// Ids stay stable when rows are removed; the session store keys the runs by them
+static NEXT_VM_ID: AtomicUsize = AtomicUsize::new(0);
+
 pub struct ViewModel {
+    pub id: RunId,
     pub args: TraclusArgs,
     pub args_buffer: ArgsBuffer,
 
     // Input file info section
     pub input_name: String,
     pub num_dl: usize,
+    pub sanitation: SanitationReport, // lines of the input left out of num_dl
     pub percent_correlation: f64,
 
     // Output section
     pub output: String,
+    pub progress: String, // latest progress line, replaced on every progress event
+    pub has_results: bool, // a clustering of the loaded input completed
+    pub output_dialog: Option<OutputDialog>,
+
+    // Map panel
+    pub map: MapView,
+
+    // Action bar
+    pub estimated_time: String,
+    pub estimated_params: Option<EstimateParams>,
 }
+pub const NO_ESTIMATE: &str = "-- min --s";
+
 impl ViewModel {
     pub fn new(args: TraclusArgs) -> Self {
         Self {
+            id: NEXT_VM_ID.fetch_add(1, Ordering::Relaxed),
+            args_buffer: ArgsBuffer::from_args(&args),
             args,
-            args_buffer: ArgsBuffer::default(),
 
             input_name: String::new(),
             num_dl: 0,
+            sanitation: SanitationReport::default(),
             percent_correlation: 0.0,
 
             output: String::new(),
+            progress: String::new(),
+            has_results: false,
+            output_dialog: None,
+
+            map: MapView::default(),
+
+            estimated_time: NO_ESTIMATE.to_string(),
+            estimated_params: None,
         }
     }
+
+    /// New parameter row starting from the parameters of `other`, on the same loaded input.
+    pub fn new_row_like(other: &ViewModel) -> Self {
+        let mut vm: ViewModel = ViewModel::new(other.args.clone());
+        vm.input_name = other.input_name.clone();
+        vm.num_dl = other.num_dl;
+        vm.sanitation = other.sanitation.clone();
+        vm.percent_correlation = other.percent_correlation;
+        vm.map.needs_refresh = true; // shows the input lines
+        vm
+    }
+
+    /// Key of the current parameters, compared with `estimated_params`.
+    pub fn estimate_params(&self) -> EstimateParams {
+        (
+            self.args.max_dist,
+            self.args.min_density,
+            self.args.max_angle,
+            self.args.segment_size,
+            self.args.mode,
+        )
+    }
 }
 
 impl Default for ViewModel {
     fn default() -> Self {
-        Self {
-            args: TraclusArgs::default(),
-            args_buffer: ArgsBuffer::default(),
-
-            input_name: String::new(),
-            num_dl: 0,
-            percent_correlation: 0.0,
-
-            output: String::new(),
-        }
+        Self::new(TraclusArgs::default())
     }
 }
@@ -1,9 +1,10 @@
 // args.rs
 
-use clap::{Parser, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::fmt;
+use std::str::FromStr;
 
-use crate::io::args_config::get_param_configs;
+use crate::io::args_config::{ArgsConfig, get_param_configs};
 
 // ─────────────────────────────────────────────
 // ExecutionMode  — algorithm parallelism strategy
@@ -47,6 +48,392 @@ impl fmt::Display for InterfaceMode {
     }
 }
 
+// ─────────────────────────────────────────────
+// InputFormat  — how the input file is read
+// ─────────────────────────────────────────────
+
+#[derive(Copy, Clone, Debug, ValueEnum, PartialEq, Default)]
+pub enum InputFormat {
+    /// From the file extension: .csv / .tsv are CSV, .geojson / .json GeoJSON,
+    /// anything else an OD list
+    #[default]
+    Auto,
+    /// Six whitespace-separated columns: id weight x1 y1 x2 y2
+    OdList,
+    /// Delimited text with a configurable column mapping
+    Csv,
+    /// FeatureCollection of LineStrings: all the vertices, weight from a property
+    #[value(name = "geojson")]
+    GeoJson,
+}
+
+// ─────────────────────────────────────────────
+// OutputFormat  — how corridors and segments are written
+// ─────────────────────────────────────────────
+
+#[derive(Copy, Clone, Debug, ValueEnum, PartialEq, Default)]
+pub enum OutputFormat {
+    /// Tab-separated WKT: corridor list plus the new and old segment lists
+    #[default]
+    Text,
+    /// GeoJSON FeatureCollections of corridors and segments
+    #[value(name = "geojson")]
+    GeoJson,
+    /// One GeoPackage: input lines, corridors, segments and the run metadata
+    #[value(name = "gpkg")]
+    GeoPackage,
+    /// ESRI Shapefiles (.shp, .shx, .dbf) of corridors and segments
+    #[value(name = "shp")]
+    Shapefile,
+}
+
+// ─────────────────────────────────────────────
+// CsvPreset  — known CSV layouts
+// ─────────────────────────────────────────────
+
+#[derive(Copy, Clone, Debug, ValueEnum, PartialEq)]
+pub enum CsvPreset {
+    /// Enquête OD survey: `;`-separated, header with id;facper;xorig;yorig;xdest;ydest
+    Enquete,
+}
+
+// ─────────────────────────────────────────────
+// ColumnRef  — a CSV column, by header name or 1-based index
+// ─────────────────────────────────────────────
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColumnRef {
+    Name(String),
+    Index(usize), // 1-based
+}
+
+impl FromStr for ColumnRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<usize>() {
+            Ok(0) => Err(String::from("column indexes start at 1")),
+            Ok(index) => Ok(ColumnRef::Index(index)),
+            Err(_) if s.trim().is_empty() => Err(String::from("empty column name")),
+            Err(_) => Ok(ColumnRef::Name(s.trim().to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ColumnRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnRef::Name(name) => write!(f, "'{}'", name),
+            ColumnRef::Index(index) => write!(f, "#{}", index),
+        }
+    }
+}
+
+// Parses a parameter and checks it against the range of its ArgsConfig
+fn parse_in_range<T>(v: &str, cfg: ArgsConfig<T>, name: &str) -> Result<T, String>
+where
+    T: FromStr + PartialOrd + fmt::Display,
+{
+    let val: T = v.parse().map_err(|_| String::from("must be a number"))?;
+    if val < cfg.min || val > cfg.max {
+        Err(format!(
+            "{} must be in range {}..={}",
+            name, cfg.min, cfg.max
+        ))
+    } else {
+        Ok(val)
+    }
+}
+
+fn parse_max_dist(v: &str) -> Result<f64, String> {
+    parse_in_range(v, get_param_configs().max_dist, "max_dist")
+}
+
+fn parse_min_density(v: &str) -> Result<u32, String> {
+    parse_in_range(v, get_param_configs().min_density, "min_density")
+}
+
+fn parse_max_angle(v: &str) -> Result<f64, String> {
+    parse_in_range(v, get_param_configs().max_angle, "max_angle")
+}
+
+fn parse_segment_size(v: &str) -> Result<f64, String> {
+    parse_in_range(v, get_param_configs().segment_size, "segment_size")
+}
+
+fn parse_delimiter(v: &str) -> Result<char, String> {
+    match v {
+        "tab" | "\\t" | "\t" => Ok('\t'),
+        _ => {
+            let mut chars = v.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(String::from("must be a single character or 'tab'")),
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────
+// CsvOptions  — CSV reader settings, every field optional
+// ─────────────────────────────────────────────
+
+/// Unset options are taken from the preset, then detected from the file.
+#[derive(Clone, Debug, Default, PartialEq, Args)]
+#[command(next_help_heading = "CSV input")]
+pub struct CsvOptions {
+    /// Known CSV layout; the options below override it
+    #[arg(long = "csv_preset", value_enum)]
+    pub preset: Option<CsvPreset>,
+
+    /// Field delimiter, a single character or 'tab' [default: detected]
+    #[arg(long = "delimiter", value_parser = parse_delimiter)]
+    pub delimiter: Option<char>,
+
+    /// Whether the first row is a header [default: detected]
+    #[arg(long = "header")]
+    pub header: Option<bool>,
+
+    /// Column of the line id (name or 1-based index) [default: row number]
+    #[arg(long = "col_id")]
+    pub col_id: Option<ColumnRef>,
+
+    /// Column of the weight [default: 1 for every line]
+    #[arg(long = "col_weight")]
+    pub col_weight: Option<ColumnRef>,
+
+    /// Column of the start x coordinate [default: detected]
+    #[arg(long = "col_start_x")]
+    pub col_start_x: Option<ColumnRef>,
+
+    /// Column of the start y coordinate [default: detected]
+    #[arg(long = "col_start_y")]
+    pub col_start_y: Option<ColumnRef>,
+
+    /// Column of the end x coordinate [default: detected]
+    #[arg(long = "col_end_x")]
+    pub col_end_x: Option<ColumnRef>,
+
+    /// Column of the end y coordinate [default: detected]
+    #[arg(long = "col_end_y")]
+    pub col_end_y: Option<ColumnRef>,
+}
+
+// ─────────────────────────────────────────────
+// SanitizePolicy  — what the loader does with a line it cannot cluster
+// ─────────────────────────────────────────────
+
+#[derive(Copy, Clone, Debug, ValueEnum, PartialEq, Default)]
+pub enum SanitizePolicy {
+    /// Leave the line out
+    #[default]
+    Drop,
+    /// Leave the line out and print a warning naming it
+    Warn,
+    /// Stop loading with an error naming the line
+    Fail,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Args)]
+#[command(next_help_heading = "Input sanitation")]
+pub struct SanitizeOptions {
+    /// Lines of length 0: all their vertices at the same point
+    #[arg(long = "zero_length", value_enum, default_value_t = SanitizePolicy::default())]
+    pub zero_length: SanitizePolicy,
+
+    /// Lines with a NaN or infinite coordinate
+    #[arg(long = "non_finite", value_enum, default_value_t = SanitizePolicy::default())]
+    pub non_finite: SanitizePolicy,
+
+    /// Lines of weight 0
+    #[arg(long = "zero_weight", value_enum, default_value_t = SanitizePolicy::default())]
+    pub zero_weight: SanitizePolicy,
+}
+
+// ─────────────────────────────────────────────
+// Partitioning  — how trajectories are cut into segments
+// ─────────────────────────────────────────────
+
+#[derive(Copy, Clone, Debug, ValueEnum, PartialEq, Default)]
+pub enum Partitioning {
+    /// Every leg cut into segments of segment_size
+    #[default]
+    Fixed,
+    /// Characteristic points chosen by minimum description length, as in TRACLUS:
+    /// one segment of variable length between consecutive points
+    Mdl,
+}
+
+impl fmt::Display for Partitioning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Partitioning::Fixed => write!(f, "fixed"),
+            Partitioning::Mdl => write!(f, "mdl"),
+        }
+    }
+}
+
+const DEFAULT_MDL_COST_WEIGHT: f64 = 1.0;
+
+fn parse_mdl_cost_weight(v: &str) -> Result<f64, String> {
+    let val: f64 = v.parse().map_err(|_| String::from("must be a number"))?;
+    if val.is_finite() && val > 0.0 {
+        Ok(val)
+    } else {
+        Err(String::from("mdl_cost_weight must be greater than 0"))
+    }
+}
+
+fn parse_min_segment_length(v: &str) -> Result<f64, String> {
+    let val: f64 = v.parse().map_err(|_| String::from("must be a number"))?;
+    if val.is_finite() && val >= 0.0 {
+        Ok(val)
+    } else {
+        Err(String::from("min_segment_length must be 0 or more"))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Args)]
+#[command(next_help_heading = "Partitioning")]
+pub struct PartitionOptions {
+    /// How trajectories are cut into the segments the clustering compares
+    #[arg(long = "partitioning", value_enum, default_value_t = Partitioning::default())]
+    pub partitioning: Partitioning,
+
+    /// MDL only: weight of the approximation error against the segment lengths;
+    /// a higher weight keeps more characteristic points
+    #[arg(long = "mdl_cost_weight", default_value_t = DEFAULT_MDL_COST_WEIGHT, value_parser = parse_mdl_cost_weight)]
+    pub mdl_cost_weight: f64,
+
+    /// MDL only: no characteristic point closer than this to the previous one
+    #[arg(long = "min_segment_length", default_value_t = 0.0, value_parser = parse_min_segment_length)]
+    pub min_segment_length: f64,
+}
+
+impl Default for PartitionOptions {
+    fn default() -> Self {
+        Self {
+            partitioning: Partitioning::default(),
+            mdl_cost_weight: DEFAULT_MDL_COST_WEIGHT,
+            min_segment_length: 0.0,
+        }
+    }
+}
+
+// ─────────────────────────────────────────────
+// DistanceMetric  — how the reachability test measures a candidate against the seed
+// ─────────────────────────────────────────────
+
+#[derive(Copy, Clone, Debug, ValueEnum, PartialEq, Default)]
+pub enum DistanceMetric {
+    /// From the middle of the seed segment to the candidate leg
+    #[default]
+    Midpoint,
+    /// Weighted perpendicular, parallel and angular distance of TRACLUS
+    Traclus,
+    /// Hausdorff distance between the seed and candidate segments
+    Hausdorff,
+}
+
+impl fmt::Display for DistanceMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DistanceMetric::Midpoint => write!(f, "midpoint"),
+            DistanceMetric::Traclus => write!(f, "traclus"),
+            DistanceMetric::Hausdorff => write!(f, "hausdorff"),
+        }
+    }
+}
+
+const DEFAULT_DISTANCE_WEIGHT: f64 = 1.0;
+
+fn parse_distance_weight(v: &str) -> Result<f64, String> {
+    let val: f64 = v.parse().map_err(|_| String::from("must be a number"))?;
+    if val.is_finite() && val >= 0.0 {
+        Ok(val)
+    } else {
+        Err(String::from("distance weights must be 0 or more"))
+    }
+}
+
+#[derive(Clone, Debug, Args)]
+#[command(next_help_heading = "Distance metric")]
+pub struct DistanceOptions {
+    /// Distance compared with max_dist when testing a candidate segment
+    #[arg(long = "distance", value_enum, default_value_t = DistanceMetric::default())]
+    pub metric: DistanceMetric,
+
+    /// TRACLUS only: weight of the perpendicular distance
+    #[arg(long = "w_perpendicular", default_value_t = DEFAULT_DISTANCE_WEIGHT, value_parser = parse_distance_weight)]
+    pub w_perpendicular: f64,
+
+    /// TRACLUS only: weight of the parallel distance
+    #[arg(long = "w_parallel", default_value_t = DEFAULT_DISTANCE_WEIGHT, value_parser = parse_distance_weight)]
+    pub w_parallel: f64,
+
+    /// TRACLUS only: weight of the angular distance
+    #[arg(long = "w_angular", default_value_t = DEFAULT_DISTANCE_WEIGHT, value_parser = parse_distance_weight)]
+    pub w_angular: f64,
+}
+
+impl Default for DistanceOptions {
+    fn default() -> Self {
+        Self {
+            metric: DistanceMetric::default(),
+            w_perpendicular: DEFAULT_DISTANCE_WEIGHT,
+            w_parallel: DEFAULT_DISTANCE_WEIGHT,
+            w_angular: DEFAULT_DISTANCE_WEIGHT,
+        }
+    }
+}
+
+// ─────────────────────────────────────────────
+// InputCrs / MetricCrs  — coordinate reference systems of the input and of the clustering
+// ─────────────────────────────────────────────
+
+#[derive(Copy, Clone, Debug, ValueEnum, PartialEq, Default)]
+pub enum InputCrs {
+    /// Coordinates of a projected CRS in metres, clustered as they are
+    #[default]
+    Projected,
+    /// WGS84 longitude/latitude in degrees, projected before clustering and back on output
+    #[value(name = "wgs84")]
+    Wgs84,
+}
+
+impl fmt::Display for InputCrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputCrs::Projected => write!(f, "projected"),
+            InputCrs::Wgs84 => write!(f, "wgs84"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum, PartialEq, Default)]
+pub enum MetricCrs {
+    /// UTM zone of the centre of the input
+    #[default]
+    Utm,
+    /// NAD83 / MTM zone 8 (EPSG:32188), for Montreal
+    #[value(name = "mtm8")]
+    Mtm8,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Args)]
+#[command(next_help_heading = "Coordinate reference system")]
+pub struct CrsOptions {
+    /// CRS of the input coordinates
+    #[arg(long = "input_crs", value_enum, default_value_t = InputCrs::default())]
+    pub input_crs: InputCrs,
+
+    /// wgs84 input only: metric CRS of the clustering, the CRS of max_dist and segment_size
+    #[arg(long = "projection", value_enum, default_value_t = MetricCrs::default())]
+    pub projection: MetricCrs,
+}
+
+const DEFAULT_WEIGHT_PROPERTY: &str = "weight";
+
 fn default_mode() -> ExecutionMode {
     ExecutionMode::Serial
 }
@@ -54,12 +441,46 @@ fn default_interface_mode() -> InterfaceMode {
     InterfaceMode::Gui
 }
 
+// ─────────────────────────────────────────────
+// InputArgs  — how an input file is read, shared by a run, bench and inspect
+// ─────────────────────────────────────────────
+
+#[derive(Clone, Debug, PartialEq, Args)]
+pub struct InputArgs {
+    #[arg(long = "input_format", value_enum, default_value_t = InputFormat::default())]
+    pub input_format: InputFormat,
+
+    #[command(flatten)]
+    pub csv: CsvOptions,
+
+    /// Feature property holding the weight of a GeoJSON line [1 when absent]
+    #[arg(long = "weight_property", default_value = DEFAULT_WEIGHT_PROPERTY, help_heading = "GeoJSON input")]
+    pub weight_property: String,
+
+    #[command(flatten)]
+    pub sanitize: SanitizeOptions,
+
+    #[command(flatten)]
+    pub crs: CrsOptions,
+}
+
+impl Default for InputArgs {
+    fn default() -> Self {
+        Self {
+            input_format: InputFormat::default(),
+            csv: CsvOptions::default(),
+            weight_property: DEFAULT_WEIGHT_PROPERTY.to_string(),
+            sanitize: SanitizeOptions::default(),
+            crs: CrsOptions::default(),
+        }
+    }
+}
+
 // ─────────────────────────────────────────────
 // TraclusArgs
 // ─────────────────────────────────────────────
 
 #[derive(Clone, Parser, Debug)]
-#[command(author, version, about = "Traclus DL Optimized in Rust")]
 pub struct TraclusArgs {
     #[arg(short = 'f', long = "file", default_value = "")]
     pub file: String,
@@ -68,15 +489,7 @@ pub struct TraclusArgs {
         short = 'd',
         long = "max_dist",
         default_value_t = get_param_configs().max_dist.default,
-        value_parser = |v: &str| {
-            let cfg = get_param_configs().max_dist;
-            let val: f64 = v.parse().map_err(|_| String::from("must be a number"))?;
-            if val < cfg.min || val > cfg.max {
-                Err(format!("max_dist must be in range {}..={}", cfg.min, cfg.max))
-            } else {
-                Ok(val)
-            }
-        }
+        value_parser = parse_max_dist
     )]
     pub max_dist: f64,
 
@@ -84,15 +497,7 @@ pub struct TraclusArgs {
         short = 'n',
         long = "min_density",
         default_value_t = get_param_configs().min_density.default,
-        value_parser = |v: &str| {
-            let cfg = get_param_configs().min_density;
-            let val: u32 = v.parse().map_err(|_| String::from("must be a number"))?;
-            if val < cfg.min || val > cfg.max {
-                Err(format!("min_density must be in range {}..={}", cfg.min, cfg.max))
-            } else {
-                Ok(val)
-            }
-        }
+        value_parser = parse_min_density
     )]
     pub min_density: u32,
 
@@ -100,15 +505,7 @@ pub struct TraclusArgs {
         short = 'a',
         long = "max_angle",
         default_value_t = get_param_configs().max_angle.default,
-        value_parser = |v: &str| {
-            let cfg = get_param_configs().max_angle;
-            let val: f64 = v.parse().map_err(|_| String::from("must be a number"))?;
-            if val < cfg.min || val > cfg.max {
-                Err(format!("max_angle must be in range {}..={}", cfg.min, cfg.max))
-            } else {
-                Ok(val)
-            }
-        }
+        value_parser = parse_max_angle
     )]
     pub max_angle: f64,
 
@@ -116,23 +513,35 @@ pub struct TraclusArgs {
         short = 's',
         long = "segment_size",
         default_value_t = get_param_configs().segment_size.default,
-        value_parser = |v: &str| {
-            let cfg = get_param_configs().segment_size;
-            let val: f64 = v.parse().map_err(|_| String::from("must be a number"))?;
-            if val < cfg.min || val > cfg.max {
-                Err(format!("segment_size must be in range {}..={}", cfg.min, cfg.max))
-            } else {
-                Ok(val)
-            }
-        }
+        value_parser = parse_segment_size
     )]
     pub segment_size: f64,
 
+    /// Cluster both directions of an axis together: angles are compared modulo 180°
+    #[arg(long = "undirected")]
+    pub undirected: bool,
+
+    #[command(flatten)]
+    pub partition: PartitionOptions,
+
+    #[command(flatten)]
+    pub distance: DistanceOptions,
+
     #[arg(short = 'm', long = "mode",      value_enum, default_value_t = default_mode())]
     pub mode: ExecutionMode,
 
     #[arg(short = 'i', long = "interface", value_enum, default_value_t = default_interface_mode())]
     pub interface_mode: InterfaceMode,
+
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    #[arg(long = "output_format", value_enum, default_value_t = OutputFormat::default())]
+    pub output_format: OutputFormat,
+
+    /// Directory of the output files [default: the directory of the input file]
+    #[arg(long = "output_dir")]
+    pub output_dir: Option<String>,
 }
 
 impl Default for TraclusArgs {
@@ -144,8 +553,216 @@ impl Default for TraclusArgs {
             min_density: cfg.min_density.default,
             max_angle: cfg.max_angle.default,
             segment_size: cfg.segment_size.default,
+            undirected: false,
+            partition: PartitionOptions::default(),
+            distance: DistanceOptions::default(),
             mode: default_mode(),
             interface_mode: default_interface_mode(),
+            input: InputArgs::default(),
+            output_format: OutputFormat::default(),
+            output_dir: None,
         }
     }
 }
+
+// ─────────────────────────────────────────────
+// Cli  — a clustering run (the arguments above), or one of the tool subcommands
+// ─────────────────────────────────────────────
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Traclus DL Optimized in Rust",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub run: TraclusArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compare two clustering runs from their text outputs
+    Compare(CompareArgs),
+    /// Time the load, clustering, finalization and output phases over a parameter grid
+    Bench(Box<BenchArgs>),
+    /// Generate a synthetic dataset with its ground-truth corridors
+    Generate(GenerateArgs),
+    /// Report statistics of an input file before clustering it
+    Inspect(InspectArgs),
+}
+
+// ─────────────────────────────────────────────
+// CompareArgs
+// ─────────────────────────────────────────────
+
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    /// Segment list of run A, new format (*.segmentlist_new.txt)
+    pub segments_a: String,
+
+    /// Segment list of run B, new format (*.segmentlist_new.txt)
+    pub segments_b: String,
+
+    /// Corridor list of run A [default: the corridorlist file beside segments_a]
+    #[arg(long = "corridors_a")]
+    pub corridors_a: Option<String>,
+
+    /// Corridor list of run B [default: the corridorlist file beside segments_b]
+    #[arg(long = "corridors_b")]
+    pub corridors_b: Option<String>,
+
+    /// Print the report as JSON instead of text
+    #[arg(long = "json")]
+    pub json: bool,
+}
+
+// ─────────────────────────────────────────────
+// BenchArgs  — parameter grid of the benchmark, as in benchmarks/arguments_traclus.py
+// ─────────────────────────────────────────────
+
+/// The parameter lists are zipped, not crossed: the shorter lists repeat their last value.
+/// Every parameter set runs on every input path, in every mode.
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Input files
+    #[arg(required = true)]
+    pub paths: Vec<String>,
+
+    /// Directory the input paths are relative to
+    #[arg(long = "data_dir")]
+    pub data_dir: Option<String>,
+
+    #[arg(long = "max_dist", value_delimiter = ',', value_parser = parse_max_dist,
+          default_values_t = vec![get_param_configs().max_dist.default])]
+    pub max_dist: Vec<f64>,
+
+    #[arg(long = "min_density", value_delimiter = ',', value_parser = parse_min_density,
+          default_values_t = vec![get_param_configs().min_density.default])]
+    pub min_density: Vec<u32>,
+
+    #[arg(long = "max_angle", value_delimiter = ',', value_parser = parse_max_angle,
+          default_values_t = vec![get_param_configs().max_angle.default])]
+    pub max_angle: Vec<f64>,
+
+    #[arg(long = "segment_size", alias = "seg_size", value_delimiter = ',', value_parser = parse_segment_size,
+          default_values_t = vec![get_param_configs().segment_size.default])]
+    pub segment_size: Vec<f64>,
+
+    /// Cluster both directions of an axis together: angles are compared modulo 180°
+    #[arg(long = "undirected")]
+    pub undirected: bool,
+
+    #[command(flatten)]
+    pub partition: PartitionOptions,
+
+    #[command(flatten)]
+    pub distance: DistanceOptions,
+
+    /// Execution modes to time
+    #[arg(long = "modes", value_enum, value_delimiter = ',', default_values = ["serial", "parallel-rayon"])]
+    pub modes: Vec<ExecutionMode>,
+
+    /// Timed runs of every parameter set and mode; the last one also writes the outputs
+    #[arg(long = "runs", default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    pub runs: u32,
+
+    /// Untimed runs before the timed ones
+    #[arg(long = "warmup", default_value_t = 1)]
+    pub warmup: u32,
+
+    /// Report file: JSON when it ends in .json, CSV otherwise
+    #[arg(long = "report", default_value = "bench_report.csv")]
+    pub report: String,
+
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    #[arg(long = "output_format", value_enum, default_value_t = OutputFormat::default())]
+    pub output_format: OutputFormat,
+
+    /// Directory of the output files [default: the directory of each input file]
+    #[arg(long = "output_dir")]
+    pub output_dir: Option<String>,
+}
+
+// ─────────────────────────────────────────────
+// GenerateArgs  — synthetic datasets of inputs/dataset_factory.py
+// ─────────────────────────────────────────────
+
+#[derive(Copy, Clone, Debug, ValueEnum, PartialEq)]
+pub enum Scenario {
+    /// Random points of the Montreal island to random points of the island (500 lines)
+    MontrealToMontreal,
+    /// Between two circles of 500 m (150 lines)
+    SmallRadiusToSmallRadius,
+    /// From a South Shore spot to random points of the Montreal island (5000 lines)
+    UpTheBridges,
+    /// 1000 m radial lines around a center, every 10 degrees
+    CircleAround,
+    /// 10 vertical lines of 1000 m, 20 m apart
+    Parallels,
+}
+
+#[derive(Args, Debug)]
+pub struct GenerateArgs {
+    pub scenario: Scenario,
+
+    /// Dataset file; the ground truth is written beside it
+    pub output: String,
+
+    /// Number of lines [default: the scenario's]; not used by circle-around
+    #[arg(long = "lines")]
+    pub lines: Option<usize>,
+
+    /// Seed of the random points; the same seed gives the same file
+    #[arg(long = "seed", default_value_t = 42)]
+    pub seed: u64,
+
+    /// Format of the dataset file [default: from its extension, as for the input]
+    #[arg(long = "format", value_enum, default_value_t = InputFormat::default())]
+    pub format: InputFormat,
+
+    /// Degrees between two circle-around lines
+    #[arg(long = "angle_interval", default_value_t = 10.0)]
+    pub angle_interval: f64,
+
+    /// Meters between two parallel lines
+    #[arg(long = "spacing", default_value_t = 20.0)]
+    pub spacing: f64,
+}
+
+// ─────────────────────────────────────────────
+// InspectArgs
+// ─────────────────────────────────────────────
+
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+    /// Input file
+    pub file: String,
+
+    /// Angle of the buckets of the histogram, as in the clustering
+    #[arg(long = "max_angle", value_parser = parse_max_angle,
+          default_value_t = get_param_configs().max_angle.default)]
+    pub max_angle: f64,
+
+    /// Buckets over 0 to 180°, as in an undirected clustering
+    #[arg(long = "undirected")]
+    pub undirected: bool,
+
+    /// Segment sizes to count the segments of
+    #[arg(long = "segment_size", alias = "seg_size", value_delimiter = ',', value_parser = parse_segment_size,
+          default_values_t = vec![get_param_configs().segment_size.default])]
+    pub segment_size: Vec<f64>,
+
+    /// Print the statistics as JSON instead of text
+    #[arg(long = "json")]
+    pub json: bool,
+
+    #[command(flatten)]
+    pub input: InputArgs,
+}
@@ -0,0 +1,127 @@
+// dataset_writer.rs - Writer of generated datasets and of their ground-truth corridors
+//
+// Datasets are written in any input format the loader reads, so they can be clustered
+// directly. The ground truth goes beside the dataset: a corridor list in the format of the
+// run outputs, and the corridor of every line (-1 for the lines outside any corridor).
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::clustering::synthetic_dataset::SyntheticDataset;
+use crate::io::args::InputFormat;
+use crate::io::io_error::IoError;
+use crate::io::output_writer::{line_string, write_feature_collection};
+
+/// Writes the lines of `dataset` to `path`; `format` must be resolved (not Auto).
+pub fn write_dataset(
+    path: &str,
+    format: InputFormat,
+    dataset: &SyntheticDataset,
+) -> Result<(), IoError> {
+    let result: io::Result<()> = match format {
+        InputFormat::Csv => write_csv(path, dataset),
+        InputFormat::GeoJson => {
+            let features = dataset.lines.iter().map(|line| {
+                json!({
+                    "type": "Feature",
+                    "properties": { "id": line.line_id, "weight": line.weight },
+                    "geometry": line_string(&line.start, &line.end),
+                })
+            });
+            write_feature_collection(path, features)
+        }
+        _ => write_od_list(path, dataset),
+    };
+    result.map_err(|err| IoError::write(path, err))
+}
+
+/// Writes the ground truth beside `dataset_path` and returns the two files written.
+pub fn write_ground_truth(
+    dataset_path: &str,
+    dataset: &SyntheticDataset,
+) -> Result<Vec<String>, IoError> {
+    let corridors_path: String = path_beside(dataset_path, "truth_corridors");
+    write_truth_corridors(&corridors_path, dataset)
+        .map_err(|err| IoError::write(&corridors_path, err))?;
+
+    let lines_path: String = path_beside(dataset_path, "truth_lines");
+    write_truth_lines(&lines_path, dataset).map_err(|err| IoError::write(&lines_path, err))?;
+
+    Ok(vec![corridors_path, lines_path])
+}
+
+// Format: {id}\t{weight}\t{x1}\t{y1}\t{x2}\t{y2}, no header
+fn write_od_list(path: &str, dataset: &SyntheticDataset) -> io::Result<()> {
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(path)?);
+    for line in &dataset.lines {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            line.line_id, line.weight, line.start.x, line.start.y, line.end.x, line.end.y
+        )?;
+    }
+    writer.flush()
+}
+
+// Header names the CSV reader detects; tab-separated for a .tsv file
+fn write_csv(path: &str, dataset: &SyntheticDataset) -> io::Result<()> {
+    let is_tsv: bool = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("tsv"));
+    let d: char = if is_tsv { '\t' } else { ',' };
+
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(path)?);
+    writeln!(writer, "id{d}weight{d}xorig{d}yorig{d}xdest{d}ydest")?;
+    for line in &dataset.lines {
+        writeln!(
+            writer,
+            "{}{d}{}{d}{}{d}{}{d}{}{d}{}",
+            line.line_id, line.weight, line.start.x, line.start.y, line.end.x, line.end.y
+        )?;
+    }
+    writer.flush()
+}
+
+// Same columns as the corridorlist output, so it reads like a run
+fn write_truth_corridors(path: &str, dataset: &SyntheticDataset) -> io::Result<()> {
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(path)?);
+    writeln!(writer, "name\tweight\tcoordinates")?;
+    for corridor in &dataset.corridors {
+        writeln!(
+            writer,
+            "{}\t{}\tLINESTRING({} {}, {} {})",
+            corridor.id,
+            corridor.weight,
+            corridor.start.x,
+            corridor.start.y,
+            corridor.end.x,
+            corridor.end.y
+        )?;
+    }
+    writer.flush()
+}
+
+fn write_truth_lines(path: &str, dataset: &SyntheticDataset) -> io::Result<()> {
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(path)?);
+    writeln!(writer, "line_id\tcorridor_id")?;
+    for (line_id, corridor) in dataset.line_corridors() {
+        let corridor_id: i64 = corridor.map_or(-1, |id| id as i64);
+        writeln!(writer, "{}\t{}", line_id, corridor_id)?;
+    }
+    writer.flush()
+}
+
+// "dir/name.ext" -> "dir/name.{suffix}.txt"
+fn path_beside(dataset_path: &str, suffix: &str) -> String {
+    let path: &Path = Path::new(dataset_path);
+    let stem: String = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}.{}.txt", stem, suffix))
+        .to_string_lossy()
+        .into_owned()
+}
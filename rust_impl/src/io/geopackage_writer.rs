@@ -0,0 +1,540 @@
+// geopackage_writer.rs - Writer of the whole result of a run into one GeoPackage
+//
+// A GeoPackage is an SQLite file with a few registry tables (OGC 12-128r18), read
+// directly by QGIS and GDAL. Layers: input_lines, corridors, clustered_segments and
+// non_clustered_segments, all LineStrings; plus the attribute table run_metadata with
+// the TraclusArgs, the execution mode and the timings. Coordinates are written as they
+// were read, in the undefined Cartesian SRS (-1), since the input has no declared CRS;
+// longitude/latitude input is projected back and written in WGS 84 (4326).
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rusqlite::{Connection, Transaction, params};
+
+use crate::clustering::geometry::point::Point;
+use crate::clustering::geometry::projection::Projection;
+use crate::clustering::storage::clustered_trajectories::ClusteredTrajectories;
+use crate::clustering::storage::raw_trajectories::RawTrajectories;
+use crate::io::args::TraclusArgs;
+use crate::io::io_error::IoError;
+use crate::io::output_writer::{build_output_filename, output_point};
+use crate::utils::run_timings::RunTimings;
+
+const GPKG_APPLICATION_ID: i32 = 0x4750_4B47; // "GPKG"
+const GPKG_USER_VERSION: i32 = 10300; // 1.3.0
+const UNDEFINED_CARTESIAN_SRS: i32 = -1;
+const WGS84_SRS: i32 = 4326;
+
+// Bounding box of the LineStrings written to a layer, stored in gpkg_contents
+struct Extent {
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+}
+
+impl Extent {
+    fn empty() -> Self {
+        Self {
+            min_x: f64::INFINITY,
+            min_y: f64::INFINITY,
+            max_x: f64::NEG_INFINITY,
+            max_y: f64::NEG_INFINITY,
+        }
+    }
+
+    fn of(points: &[Point]) -> Self {
+        let mut extent: Extent = Self::empty();
+        for point in points {
+            extent.min_x = extent.min_x.min(point.x);
+            extent.min_y = extent.min_y.min(point.y);
+            extent.max_x = extent.max_x.max(point.x);
+            extent.max_y = extent.max_y.max(point.y);
+        }
+        extent
+    }
+
+    fn extend(&mut self, other: &Extent) {
+        self.min_x = self.min_x.min(other.min_x);
+        self.min_y = self.min_y.min(other.min_y);
+        self.max_x = self.max_x.max(other.max_x);
+        self.max_y = self.max_y.max(other.max_y);
+    }
+
+    // An empty layer is registered without extent (NULL columns)
+    fn as_columns(&self) -> [Option<f64>; 4] {
+        if self.min_x > self.max_x {
+            return [None; 4];
+        }
+        [
+            Some(self.min_x),
+            Some(self.min_y),
+            Some(self.max_x),
+            Some(self.max_y),
+        ]
+    }
+}
+
+// ─────────────────────────────────────────────
+// Entry point
+// ─────────────────────────────────────────────
+
+// Generate the GeoPackage output file holding inputs, corridors, segments and metadata
+pub fn generate_geopackage(
+    args: &TraclusArgs,
+    raw_storage: &RawTrajectories,
+    clust_storage: &ClusteredTrajectories,
+    timings: &RunTimings,
+) -> Result<String, IoError> {
+    let output_filename: String = build_output_filename(args, "traclus", "gpkg");
+
+    // Tables cannot be created twice: a previous run with the same parameters is replaced
+    if Path::new(&output_filename).exists() {
+        fs::remove_file(&output_filename).map_err(|err| IoError::write(&output_filename, err))?;
+    }
+
+    // A failed write leaves no half-filled file behind
+    if let Err(err) = write_geopackage(&output_filename, args, raw_storage, clust_storage, timings)
+    {
+        let _ = fs::remove_file(&output_filename);
+        return Err(IoError::write(&output_filename, io::Error::other(err)));
+    }
+
+    println!("GeoPackage output written to: {}", output_filename);
+    Ok(output_filename)
+}
+
+fn write_geopackage(
+    output_filename: &str,
+    args: &TraclusArgs,
+    raw_storage: &RawTrajectories,
+    clust_storage: &ClusteredTrajectories,
+    timings: &RunTimings,
+) -> rusqlite::Result<()> {
+    let mut conn: Connection = Connection::open(output_filename)?;
+    let tx: Transaction = conn.transaction()?;
+
+    let projection: Option<&Projection> = raw_storage.projection.as_ref();
+    create_registry(&tx)?;
+    write_input_lines(&tx, raw_storage, projection)?;
+    write_corridors(&tx, clust_storage, args.undirected, projection)?;
+    write_clustered_segments(&tx, clust_storage, projection)?;
+    write_non_clustered_segments(&tx, clust_storage, projection)?;
+    write_run_metadata(&tx, args, raw_storage, clust_storage, timings)?;
+
+    tx.commit()
+}
+
+// ─────────────────────────────────────────────
+// GeoPackage registry
+// ─────────────────────────────────────────────
+
+fn create_registry(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(&format!(
+        "PRAGMA application_id = {GPKG_APPLICATION_ID};
+         PRAGMA user_version = {GPKG_USER_VERSION};
+
+         CREATE TABLE gpkg_spatial_ref_sys (
+             srs_name TEXT NOT NULL,
+             srs_id INTEGER PRIMARY KEY,
+             organization TEXT NOT NULL,
+             organization_coordsys_id INTEGER NOT NULL,
+             definition TEXT NOT NULL,
+             description TEXT
+         );
+         INSERT INTO gpkg_spatial_ref_sys VALUES
+             ('Undefined cartesian SRS', -1, 'NONE', -1, 'undefined', 'undefined cartesian coordinate reference system'),
+             ('Undefined geographic SRS', 0, 'NONE', 0, 'undefined', 'undefined geographic coordinate reference system'),
+             ('WGS 84 geodetic', 4326, 'EPSG', 4326,
+              'GEOGCS[\"WGS 84\",DATUM[\"WGS_1984\",SPHEROID[\"WGS 84\",6378137,298.257223563,AUTHORITY[\"EPSG\",\"7030\"]],AUTHORITY[\"EPSG\",\"6326\"]],PRIMEM[\"Greenwich\",0,AUTHORITY[\"EPSG\",\"8901\"]],UNIT[\"degree\",0.0174532925199433,AUTHORITY[\"EPSG\",\"9122\"]],AUTHORITY[\"EPSG\",\"4326\"]]',
+              'longitude/latitude coordinates in decimal degrees on the WGS 84 spheroid');
+
+         CREATE TABLE gpkg_contents (
+             table_name TEXT NOT NULL PRIMARY KEY,
+             data_type TEXT NOT NULL,
+             identifier TEXT UNIQUE,
+             description TEXT DEFAULT '',
+             last_change DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+             min_x DOUBLE,
+             min_y DOUBLE,
+             max_x DOUBLE,
+             max_y DOUBLE,
+             srs_id INTEGER,
+             CONSTRAINT fk_gc_r_srs_id FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys(srs_id)
+         );
+
+         CREATE TABLE gpkg_geometry_columns (
+             table_name TEXT NOT NULL,
+             column_name TEXT NOT NULL,
+             geometry_type_name TEXT NOT NULL,
+             srs_id INTEGER NOT NULL,
+             z TINYINT NOT NULL,
+             m TINYINT NOT NULL,
+             CONSTRAINT pk_geom_cols PRIMARY KEY (table_name, column_name),
+             CONSTRAINT fk_gc_tn FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name),
+             CONSTRAINT fk_gc_srs FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys (srs_id)
+         );"
+    ))
+}
+
+// Creates a LineString feature table; `columns` are the attribute column definitions
+fn create_feature_table(tx: &Transaction, table: &str, columns: &str) -> rusqlite::Result<()> {
+    tx.execute_batch(&format!(
+        "CREATE TABLE {table} (fid INTEGER PRIMARY KEY AUTOINCREMENT, geom LINESTRING, {columns});"
+    ))
+}
+
+// Registers a filled table; feature tables also get their geometry column (after the
+// gpkg_contents row it references)
+
+fn register_contents(
+    tx: &Transaction,
+    table: &str,
+    data_type: &str,
+    description: &str,
+    extent: &Extent,
+    projection: Option<&Projection>,
+) -> rusqlite::Result<()> {
+    let [min_x, min_y, max_x, max_y] = extent.as_columns();
+    let srs_id: Option<i32> = (data_type == "features").then_some(srs_id(projection));
+
+    tx.execute(
+        "INSERT INTO gpkg_contents (table_name, data_type, identifier, description, min_x, min_y, max_x, max_y, srs_id)
+         VALUES (?1, ?2, ?1, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![table, data_type, description, min_x, min_y, max_x, max_y, srs_id],
+    )?;
+
+    if let Some(srs_id) = srs_id {
+        tx.execute(
+            "INSERT INTO gpkg_geometry_columns VALUES (?1, 'geom', 'LINESTRING', ?2, 0, 0)",
+            params![table, srs_id],
+        )?;
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────
+// Layers
+// ─────────────────────────────────────────────
+
+fn write_input_lines(
+    tx: &Transaction,
+    raw_storage: &RawTrajectories,
+    projection: Option<&Projection>,
+) -> rusqlite::Result<()> {
+    create_feature_table(
+        tx,
+        "input_lines",
+        "id INTEGER, weight INTEGER, angle DOUBLE",
+    )?;
+
+    let mut extent: Extent = Extent::empty();
+    let mut stmt =
+        tx.prepare("INSERT INTO input_lines (geom, id, weight, angle) VALUES (?1, ?2, ?3, ?4)")?;
+
+    for traj in &raw_storage.trajectories {
+        let points: Vec<Point> = traj
+            .points()
+            .iter()
+            .map(|point| output_point(projection, point))
+            .collect();
+        let line_extent: Extent = Extent::of(&points);
+        stmt.execute(params![
+            line_string_blob(&points, &line_extent, projection),
+            traj.id as i64,
+            traj.weight,
+            traj.angle
+        ])?;
+        extent.extend(&line_extent);
+    }
+
+    register_contents(
+        tx,
+        "input_lines",
+        "features",
+        "Input desire lines",
+        &extent,
+        projection,
+    )
+}
+
+// The undirected mode adds the weight in each direction
+fn write_corridors(
+    tx: &Transaction,
+    clust_storage: &ClusteredTrajectories,
+    undirected: bool,
+    projection: Option<&Projection>,
+) -> rusqlite::Result<()> {
+    if undirected {
+        create_feature_table(
+            tx,
+            "corridors",
+            "corridor_id INTEGER, weight INTEGER, forward_weight INTEGER, backward_weight INTEGER",
+        )?;
+    } else {
+        create_feature_table(tx, "corridors", "corridor_id INTEGER, weight INTEGER")?;
+    }
+
+    let mut extent: Extent = Extent::empty();
+    let mut stmt = if undirected {
+        tx.prepare(
+            "INSERT INTO corridors (geom, corridor_id, weight, forward_weight, backward_weight)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?
+    } else {
+        tx.prepare("INSERT INTO corridors (geom, corridor_id, weight) VALUES (?1, ?2, ?3)")?
+    };
+
+    for corridor in &clust_storage.corridors {
+        let points: [Point; 2] = [
+            output_point(projection, &corridor.start),
+            output_point(projection, &corridor.end),
+        ];
+        let line_extent: Extent = Extent::of(&points);
+        let geom: Vec<u8> = line_string_blob(&points, &line_extent, projection);
+        if undirected {
+            stmt.execute(params![
+                geom,
+                corridor.id as i64,
+                corridor.weight,
+                corridor.forward_weight,
+                corridor.backward_weight
+            ])?;
+        } else {
+            stmt.execute(params![geom, corridor.id as i64, corridor.weight])?;
+        }
+        extent.extend(&line_extent);
+    }
+
+    register_contents(
+        tx,
+        "corridors",
+        "features",
+        "Corridors",
+        &extent,
+        projection,
+    )
+}
+
+fn write_clustered_segments(
+    tx: &Transaction,
+    clust_storage: &ClusteredTrajectories,
+    projection: Option<&Projection>,
+) -> rusqlite::Result<()> {
+    create_feature_table(
+        tx,
+        "clustered_segments",
+        "corridor_id INTEGER, trajectory_id INTEGER, segment_id INTEGER, weight INTEGER, angle DOUBLE",
+    )?;
+
+    let mut extent: Extent = Extent::empty();
+    let mut stmt = tx
+        .prepare(
+            "INSERT INTO clustered_segments (geom, corridor_id, trajectory_id, segment_id, weight, angle)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+
+    let clustered = clust_storage
+        .get_all_cluster_members_iter()
+        .filter(|(corridor_id, _)| *corridor_id >= 0);
+    for (corridor_id, cluster_member) in clustered {
+        let points: [Point; 2] = [
+            output_point(projection, &cluster_member.start),
+            output_point(projection, &cluster_member.end_point()),
+        ];
+        let line_extent: Extent = Extent::of(&points);
+        stmt.execute(params![
+            line_string_blob(&points, &line_extent, projection),
+            corridor_id,
+            cluster_member.traj_id as i64,
+            cluster_member.segment_id as i64,
+            cluster_member.weight,
+            cluster_member.angle()
+        ])?;
+        extent.extend(&line_extent);
+    }
+
+    register_contents(
+        tx,
+        "clustered_segments",
+        "features",
+        "Segments grouped into a corridor",
+        &extent,
+        projection,
+    )
+}
+
+fn write_non_clustered_segments(
+    tx: &Transaction,
+    clust_storage: &ClusteredTrajectories,
+    projection: Option<&Projection>,
+) -> rusqlite::Result<()> {
+    create_feature_table(
+        tx,
+        "non_clustered_segments",
+        "trajectory_id INTEGER, segment_id INTEGER, weight INTEGER, angle DOUBLE",
+    )?;
+
+    let mut extent: Extent = Extent::empty();
+    let mut stmt = tx.prepare(
+        "INSERT INTO non_clustered_segments (geom, trajectory_id, segment_id, weight, angle)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+
+    for cluster_member in &clust_storage.non_clustered_segments {
+        let points: [Point; 2] = [
+            output_point(projection, &cluster_member.start),
+            output_point(projection, &cluster_member.end_point()),
+        ];
+        let line_extent: Extent = Extent::of(&points);
+        stmt.execute(params![
+            line_string_blob(&points, &line_extent, projection),
+            cluster_member.traj_id as i64,
+            cluster_member.segment_id as i64,
+            cluster_member.weight,
+            cluster_member.angle()
+        ])?;
+        extent.extend(&line_extent);
+    }
+
+    register_contents(
+        tx,
+        "non_clustered_segments",
+        "features",
+        "Segments outside any corridor",
+        &extent,
+        projection,
+    )
+}
+
+// Key/value rows: the parameters of the run, the execution mode and the timings
+fn write_run_metadata(
+    tx: &Transaction,
+    args: &TraclusArgs,
+    raw_storage: &RawTrajectories,
+    clust_storage: &ClusteredTrajectories,
+    timings: &RunTimings,
+) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE run_metadata (
+             fid INTEGER PRIMARY KEY AUTOINCREMENT,
+             key TEXT NOT NULL UNIQUE,
+             value TEXT
+         );",
+    )?;
+
+    let rows: [(&str, String); 27] = [
+        ("file", args.file.clone()),
+        ("max_dist", args.max_dist.to_string()),
+        ("min_density", args.min_density.to_string()),
+        ("max_angle", args.max_angle.to_string()),
+        ("segment_size", args.segment_size.to_string()),
+        ("undirected", args.undirected.to_string()),
+        ("partitioning", args.partition.partitioning.to_string()),
+        (
+            "mdl_cost_weight",
+            args.partition.mdl_cost_weight.to_string(),
+        ),
+        (
+            "min_segment_length",
+            args.partition.min_segment_length.to_string(),
+        ),
+        ("distance", args.distance.metric.to_string()),
+        ("w_perpendicular", args.distance.w_perpendicular.to_string()),
+        ("w_parallel", args.distance.w_parallel.to_string()),
+        ("w_angular", args.distance.w_angular.to_string()),
+        ("input_crs", args.input.crs.input_crs.to_string()),
+        (
+            "projection",
+            raw_storage
+                .projection
+                .map_or(String::from("none"), |projection| {
+                    projection.zone.to_string()
+                }),
+        ),
+        ("mode", args.mode.to_string()),
+        ("input_format", format!("{:?}", args.input.input_format)),
+        ("csv", format!("{:?}", args.input.csv)),
+        ("weight_property", args.input.weight_property.clone()),
+        ("loading_secs", timings.loading.as_secs_f64().to_string()),
+        (
+            "clustering_secs",
+            timings.clustering.as_secs_f64().to_string(),
+        ),
+        (
+            "finalization_secs",
+            timings.finalization.as_secs_f64().to_string(),
+        ),
+        ("total_secs", timings.total().as_secs_f64().to_string()),
+        (
+            "input_lines",
+            raw_storage.get_total_trajectories().to_string(),
+        ),
+        ("corridors", clust_storage.corridors.len().to_string()),
+        (
+            "clustered_segments",
+            clust_storage
+                .get_all_cluster_members_iter()
+                .filter(|(corridor_id, _)| *corridor_id >= 0)
+                .count()
+                .to_string(),
+        ),
+        (
+            "non_clustered_segments",
+            clust_storage.non_clustered_segments.len().to_string(),
+        ),
+    ];
+
+    let mut stmt = tx.prepare("INSERT INTO run_metadata (key, value) VALUES (?1, ?2)")?;
+    for (key, value) in rows {
+        stmt.execute(params![key, value])?;
+    }
+
+    register_contents(
+        tx,
+        "run_metadata",
+        "attributes",
+        "TraclusDL parameters and timings",
+        &Extent::empty(),
+        None,
+    )
+}
+
+// ─────────────────────────────────────────────
+// Geometry encoding
+// ─────────────────────────────────────────────
+
+// SRS of the written coordinates
+fn srs_id(projection: Option<&Projection>) -> i32 {
+    match projection {
+        Some(_) => WGS84_SRS,
+        None => UNDEFINED_CARTESIAN_SRS,
+    }
+}
+
+// GeoPackage binary: "GP" header with the SRS and the XY envelope, then little-endian WKB
+fn line_string_blob(points: &[Point], extent: &Extent, projection: Option<&Projection>) -> Vec<u8> {
+    const FLAGS_LITTLE_ENDIAN_XY_ENVELOPE: u8 = 0b0000_0011;
+    const WKB_LITTLE_ENDIAN: u8 = 1;
+    const WKB_LINESTRING: u32 = 2;
+
+    let mut blob: Vec<u8> = Vec::with_capacity(8 + 32 + 9 + 16 * points.len());
+    blob.extend_from_slice(b"GP");
+    blob.push(0); // version 1
+    blob.push(FLAGS_LITTLE_ENDIAN_XY_ENVELOPE);
+    blob.extend_from_slice(&srs_id(projection).to_le_bytes());
+    for value in [extent.min_x, extent.max_x, extent.min_y, extent.max_y] {
+        blob.extend_from_slice(&value.to_le_bytes());
+    }
+
+    blob.push(WKB_LITTLE_ENDIAN);
+    blob.extend_from_slice(&WKB_LINESTRING.to_le_bytes());
+    blob.extend_from_slice(&(points.len() as u32).to_le_bytes());
+    for point in points {
+        blob.extend_from_slice(&point.x.to_le_bytes());
+        blob.extend_from_slice(&point.y.to_le_bytes());
+    }
+    blob
+}
@@ -0,0 +1,589 @@
+// csv_reader.rs - Reader of delimited text (CSV / TSV) with a column mapping
+//
+// Columns are mapped to id, weight and start/end coordinates by header name or by
+// 1-based index. Whatever the user leaves unset comes from the preset, then from
+// detection: delimiter from the first line, header from non-numeric mapped fields,
+// columns from well-known header names or from the OD list column order.
+// Quoted fields are supported on a single line; a field cannot contain a line break.
+// In parse errors, the column is the 1-based field number.
+
+use std::path::Path;
+
+use crate::clustering::geometry::input_od_line::InputODLine;
+use crate::clustering::geometry::point::Point;
+use crate::io::args::{ColumnRef, CsvOptions, CsvPreset};
+use crate::io::io_error::IoError;
+
+const DETECTED_DELIMITERS: [char; 4] = [';', ',', '\t', '|'];
+
+// Header names recognised when a column is not mapped, compared case-insensitively
+const ID_NAMES: [&str; 4] = ["id", "line_id", "traj_id", "trajectory_id"];
+const WEIGHT_NAMES: [&str; 4] = ["weight", "facper", "count", "w"];
+const START_X_NAMES: [&str; 5] = ["xorig", "x_orig", "start_x", "x1", "ox"];
+const START_Y_NAMES: [&str; 5] = ["yorig", "y_orig", "start_y", "y1", "oy"];
+const END_X_NAMES: [&str; 4] = ["xdest", "x_dest", "end_x", "x2"];
+const END_Y_NAMES: [&str; 4] = ["ydest", "y_dest", "end_y", "y2"];
+
+pub fn has_csv_extension(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv") || ext.eq_ignore_ascii_case("tsv"))
+}
+
+fn has_tsv_extension(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("tsv"))
+}
+
+// ─────────────────────────────────────────────
+// Options: user settings on top of the preset
+// ─────────────────────────────────────────────
+
+fn preset_options(preset: CsvPreset) -> CsvOptions {
+    let name = |s: &str| Some(ColumnRef::Name(s.to_string()));
+    match preset {
+        CsvPreset::Enquete => CsvOptions {
+            preset: Some(preset),
+            delimiter: Some(';'),
+            header: Some(true),
+            col_id: name("id"),
+            col_weight: name("facper"),
+            col_start_x: name("xorig"),
+            col_start_y: name("yorig"),
+            col_end_x: name("xdest"),
+            col_end_y: name("ydest"),
+        },
+    }
+}
+
+// Options explicitly set by the user win over the preset ones
+fn merge_with_preset(options: &CsvOptions) -> CsvOptions {
+    let Some(preset) = options.preset else {
+        return options.clone();
+    };
+    let base: CsvOptions = preset_options(preset);
+
+    CsvOptions {
+        preset: Some(preset),
+        delimiter: options.delimiter.or(base.delimiter),
+        header: options.header.or(base.header),
+        col_id: options.col_id.clone().or(base.col_id),
+        col_weight: options.col_weight.clone().or(base.col_weight),
+        col_start_x: options.col_start_x.clone().or(base.col_start_x),
+        col_start_y: options.col_start_y.clone().or(base.col_start_y),
+        col_end_x: options.col_end_x.clone().or(base.col_end_x),
+        col_end_y: options.col_end_y.clone().or(base.col_end_y),
+    }
+}
+
+// ─────────────────────────────────────────────
+// Record splitting
+// ─────────────────────────────────────────────
+
+// Splits one line on `delimiter`; a field starting with '"' runs to the closing quote,
+// and '""' inside it stands for one quote. Fields are trimmed.
+fn split_record(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields: Vec<String> = Vec::new();
+    let mut field: String = String::new();
+    let mut in_quotes: bool = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' && field.trim().is_empty() {
+            field.clear();
+            in_quotes = true;
+        } else if c == delimiter {
+            fields.push(field.trim().to_string());
+            field.clear();
+        } else {
+            field.push(c);
+        }
+    }
+    fields.push(field.trim().to_string());
+
+    fields
+}
+
+fn detect_delimiter(first_line: &str, path: &str) -> Result<char, IoError> {
+    if has_tsv_extension(path) {
+        return Ok('\t');
+    }
+
+    DETECTED_DELIMITERS
+        .iter()
+        .map(|&d| (d, first_line.matches(d).count()))
+        .filter(|&(_, count)| count > 0)
+        .max_by_key(|&(_, count)| count)
+        .map(|(d, _)| d)
+        .ok_or_else(|| {
+            IoError::parse(
+                path,
+                1,
+                1,
+                "could not detect the delimiter, set it with --delimiter",
+            )
+        })
+}
+
+// ─────────────────────────────────────────────
+// Layout: resolved 0-based column indexes
+// ─────────────────────────────────────────────
+
+struct CsvLayout {
+    id: Option<usize>,
+    weight: Option<usize>,
+    start_x: usize,
+    start_y: usize,
+    end_x: usize,
+    end_y: usize,
+}
+
+impl CsvLayout {
+    fn max_index(&self) -> usize {
+        [self.start_x, self.start_y, self.end_x, self.end_y]
+            .into_iter()
+            .chain(self.id)
+            .chain(self.weight)
+            .max()
+            .unwrap()
+    }
+}
+
+fn find_by_name(header: &[String], name: &str) -> Option<usize> {
+    header
+        .iter()
+        .position(|field| field.eq_ignore_ascii_case(name))
+}
+
+fn resolve_column(
+    column: &Option<ColumnRef>,
+    header: Option<&[String]>,
+    aliases: &[&str],
+    what: &str,
+    path: &str,
+) -> Result<Option<usize>, IoError> {
+    match column {
+        Some(ColumnRef::Index(index)) => Ok(Some(index - 1)),
+        Some(ColumnRef::Name(name)) => {
+            let Some(header) = header else {
+                return Err(IoError::parse(
+                    path,
+                    1,
+                    1,
+                    format!(
+                        "{} column {} needs a header row",
+                        what,
+                        column.as_ref().unwrap()
+                    ),
+                ));
+            };
+            find_by_name(header, name).map(Some).ok_or_else(|| {
+                IoError::parse(
+                    path,
+                    1,
+                    1,
+                    format!("{} column '{}' not found in header", what, name),
+                )
+            })
+        }
+        None => Ok(header.and_then(|h| aliases.iter().find_map(|name| find_by_name(h, name)))),
+    }
+}
+
+fn resolve_layout(
+    options: &CsvOptions,
+    header: Option<&[String]>,
+    num_fields: usize,
+    path: &str,
+) -> Result<CsvLayout, IoError> {
+    let id = resolve_column(&options.col_id, header, &ID_NAMES, "id", path)?;
+    let weight = resolve_column(&options.col_weight, header, &WEIGHT_NAMES, "weight", path)?;
+    let start_x = resolve_column(
+        &options.col_start_x,
+        header,
+        &START_X_NAMES,
+        "start x",
+        path,
+    )?;
+    let start_y = resolve_column(
+        &options.col_start_y,
+        header,
+        &START_Y_NAMES,
+        "start y",
+        path,
+    )?;
+    let end_x = resolve_column(&options.col_end_x, header, &END_X_NAMES, "end x", path)?;
+    let end_y = resolve_column(&options.col_end_y, header, &END_Y_NAMES, "end y", path)?;
+
+    if let (Some(start_x), Some(start_y), Some(end_x), Some(end_y)) =
+        (start_x, start_y, end_x, end_y)
+    {
+        return Ok(CsvLayout {
+            id,
+            weight,
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+        });
+    }
+
+    // No header (or unknown names) and nothing mapped: the OD list column order
+    let nothing_mapped: bool = [start_x, start_y, end_x, end_y].iter().all(Option::is_none);
+    if header.is_none() && nothing_mapped {
+        match num_fields {
+            n if n >= 6 => {
+                return Ok(CsvLayout {
+                    id: id.or(Some(0)),
+                    weight: weight.or(Some(1)),
+                    start_x: 2,
+                    start_y: 3,
+                    end_x: 4,
+                    end_y: 5,
+                });
+            }
+            4 => {
+                return Ok(CsvLayout {
+                    id,
+                    weight,
+                    start_x: 0,
+                    start_y: 1,
+                    end_x: 2,
+                    end_y: 3,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Err(IoError::parse(
+        path,
+        1,
+        1,
+        "could not find the start/end coordinate columns, \
+         map them with --col_start_x, --col_start_y, --col_end_x and --col_end_y",
+    ))
+}
+
+// Without a user choice, the first row is a header when one of the fields that would
+// be read as a number is not a number (or when columns are mapped by name).
+fn detect_header(options: &CsvOptions, first_record: &[String]) -> bool {
+    let columns: [&Option<ColumnRef>; 6] = [
+        &options.col_id,
+        &options.col_weight,
+        &options.col_start_x,
+        &options.col_start_y,
+        &options.col_end_x,
+        &options.col_end_y,
+    ];
+    if columns
+        .iter()
+        .any(|c| matches!(c, Some(ColumnRef::Name(_))))
+    {
+        return true;
+    }
+
+    let mapped: Vec<usize> = columns
+        .iter()
+        .filter_map(|c| match c {
+            Some(ColumnRef::Index(index)) => Some(index - 1),
+            _ => None,
+        })
+        .collect();
+    let checked: Vec<usize> = if mapped.is_empty() {
+        (0..first_record.len().min(6)).collect()
+    } else {
+        mapped
+    };
+
+    checked.iter().any(|&index| {
+        first_record
+            .get(index)
+            .is_some_and(|field| field.parse::<f64>().is_err())
+    })
+}
+
+// ─────────────────────────────────────────────
+// Parsing
+// ─────────────────────────────────────────────
+
+fn parse_number(
+    record: &[String],
+    index: usize,
+    what: &str,
+    path: &str,
+    line_number: usize,
+) -> Result<f64, IoError> {
+    let field: &str = &record[index];
+    field.parse::<f64>().map_err(|_| {
+        IoError::parse(
+            path,
+            line_number,
+            index + 1,
+            format!("failed to parse {}: {:?}", what, field),
+        )
+    })
+}
+
+fn parse_record(
+    record: &[String],
+    layout: &CsvLayout,
+    path: &str,
+    line_number: usize,
+    row_number: usize,
+) -> Result<InputODLine, IoError> {
+    if record.len() <= layout.max_index() {
+        return Err(IoError::parse(
+            path,
+            line_number,
+            record.len(),
+            format!(
+                "expected at least {} fields, found {}",
+                layout.max_index() + 1,
+                record.len()
+            ),
+        ));
+    }
+
+    let line_id: usize = match layout.id {
+        Some(index) => record[index].parse().map_err(|_| {
+            IoError::parse(
+                path,
+                line_number,
+                index + 1,
+                format!("failed to parse id: {:?}", record[index]),
+            )
+        })?,
+        None => row_number,
+    };
+
+    // Survey weights are expansion factors: rounded to the nearest whole trip
+    let weight: u32 = match layout.weight {
+        Some(index) => {
+            let weight: f64 = parse_number(record, index, "weight", path, line_number)?;
+            if !(0.0..=u32::MAX as f64).contains(&weight) {
+                return Err(IoError::parse(
+                    path,
+                    line_number,
+                    index + 1,
+                    format!("weight out of range: {}", weight),
+                ));
+            }
+            weight.round() as u32
+        }
+        None => 1,
+    };
+
+    Ok(InputODLine {
+        line_id,
+        weight,
+        start: Point {
+            x: parse_number(record, layout.start_x, "start x", path, line_number)?,
+            y: parse_number(record, layout.start_y, "start y", path, line_number)?,
+        },
+        end: Point {
+            x: parse_number(record, layout.end_x, "end x", path, line_number)?,
+            y: parse_number(record, layout.end_y, "end y", path, line_number)?,
+        },
+        via: Vec::new(),
+    })
+}
+
+pub fn parse_csv(
+    content: &str,
+    path: &str,
+    options: &CsvOptions,
+) -> Result<Vec<InputODLine>, IoError> {
+    let options: CsvOptions = merge_with_preset(options);
+    let content: &str = content.strip_prefix('\u{feff}').unwrap_or(content);
+
+    // (line number, line) of the non-blank lines
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let Some((first_line_number, first_line)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+
+    let delimiter: char = match options.delimiter {
+        Some(delimiter) => delimiter,
+        None => detect_delimiter(first_line, path)?,
+    };
+    let first_record: Vec<String> = split_record(first_line, delimiter);
+    let has_header: bool = options
+        .header
+        .unwrap_or_else(|| detect_header(&options, &first_record));
+
+    let header: Option<&[String]> = has_header.then_some(first_record.as_slice());
+    let layout: CsvLayout = resolve_layout(&options, header, first_record.len(), path)?;
+
+    let mut od_lines: Vec<InputODLine> = Vec::new();
+    if !has_header {
+        od_lines.push(parse_record(
+            &first_record,
+            &layout,
+            path,
+            first_line_number,
+            1,
+        )?);
+    }
+
+    for (line_number, line) in lines {
+        let record: Vec<String> = split_record(line, delimiter);
+        let row_number: usize = od_lines.len() + 1;
+        od_lines.push(parse_record(
+            &record,
+            &layout,
+            path,
+            line_number,
+            row_number,
+        )?);
+    }
+
+    Ok(od_lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str, options: &CsvOptions) -> Result<Vec<InputODLine>, IoError> {
+        parse_csv(content, "trips.csv", options)
+    }
+
+    fn coordinates(line: &InputODLine) -> [f64; 4] {
+        [line.start.x, line.start.y, line.end.x, line.end.y]
+    }
+
+    #[test]
+    fn split_record_with_quotes() {
+        assert_eq!(split_record("a; b ;c", ';'), vec!["a", "b", "c"]);
+        assert_eq!(
+            split_record("\"x;y\";\"say \"\"hi\"\"\";3", ';'),
+            vec!["x;y", "say \"hi\"", "3"]
+        );
+        assert_eq!(split_record("1,,3", ','), vec!["1", "", "3"]);
+    }
+
+    #[test]
+    fn delimiter_is_the_most_frequent_candidate() {
+        assert_eq!(detect_delimiter("a;b;c,d", "trips.csv").unwrap(), ';');
+        assert_eq!(detect_delimiter("a\tb\tc", "trips.csv").unwrap(), '\t');
+        assert_eq!(detect_delimiter("a,b", "trips.tsv").unwrap(), '\t');
+        assert!(detect_delimiter("abc", "trips.csv").is_err());
+    }
+
+    #[test]
+    fn header_detected_from_non_numeric_fields() {
+        let options: CsvOptions = CsvOptions::default();
+        let record = |line: &str| split_record(line, ',');
+        assert!(detect_header(&options, &record("id,weight,x1,y1,x2,y2")));
+        assert!(!detect_header(&options, &record("1,2,3.5,4,5,-6e3")));
+
+        // Mapped by name: the first row must be a header
+        let by_name: CsvOptions = CsvOptions {
+            col_start_x: Some(ColumnRef::Name(String::from("x1"))),
+            ..CsvOptions::default()
+        };
+        assert!(detect_header(&by_name, &record("1,2,3,4,5,6")));
+    }
+
+    #[test]
+    fn columns_found_by_well_known_names() {
+        let content: &str = "end_y,start_x,weight,end_x,start_y\n4,1,2,3,2\n";
+        let lines: Vec<InputODLine> = parse(content, &CsvOptions::default()).unwrap();
+        assert_eq!(coordinates(&lines[0]), [1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(lines[0].weight, 2);
+        // Without an id column, lines are numbered by row
+        assert_eq!(lines[0].line_id, 1);
+    }
+
+    #[test]
+    fn headerless_rows_use_the_od_list_order() {
+        let lines: Vec<InputODLine> =
+            parse("5,2,0,0,10,10\n6,1,1,1,2,2\n", &CsvOptions::default()).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].line_id, lines[0].weight), (5, 2));
+        assert_eq!(coordinates(&lines[1]), [1.0, 1.0, 2.0, 2.0]);
+
+        // Four columns are the coordinates alone
+        let lines: Vec<InputODLine> = parse("0;0;3;4\n", &CsvOptions::default()).unwrap();
+        assert_eq!(coordinates(&lines[0]), [0.0, 0.0, 3.0, 4.0]);
+        assert_eq!(lines[0].weight, 1);
+    }
+
+    #[test]
+    fn enquete_preset_rounds_the_expansion_factor() {
+        let options: CsvOptions = CsvOptions {
+            preset: Some(CsvPreset::Enquete),
+            ..CsvOptions::default()
+        };
+        let content: &str = "\u{feff}id;facper;xorig;yorig;xdest;ydest\n12;24.6;100;200;300;400\n";
+        let lines: Vec<InputODLine> = parse(content, &options).unwrap();
+        assert_eq!((lines[0].line_id, lines[0].weight), (12, 25));
+        assert_eq!(coordinates(&lines[0]), [100.0, 200.0, 300.0, 400.0]);
+    }
+
+    #[test]
+    fn user_mapping_overrides_the_preset() {
+        let options: CsvOptions = CsvOptions {
+            preset: Some(CsvPreset::Enquete),
+            col_weight: Some(ColumnRef::Index(2)),
+            ..CsvOptions::default()
+        };
+        let content: &str = "id;w;xorig;yorig;xdest;ydest\n1;3;0;0;1;1\n";
+        assert_eq!(parse(content, &options).unwrap()[0].weight, 3);
+    }
+
+    #[test]
+    fn errors_carry_the_line_and_field() {
+        let content: &str = "x1,y1,x2,y2\n0,0,1,1\n\n0,0,oops,1\n";
+        let err: IoError = parse(content, &CsvOptions::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "trips.csv:4:3: failed to parse end x: \"oops\""
+        );
+
+        let options: CsvOptions = CsvOptions {
+            col_start_x: Some(ColumnRef::Name(String::from("lon"))),
+            ..CsvOptions::default()
+        };
+        let err: IoError = parse(content, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "trips.csv:1:1: start x column 'lon' not found in header"
+        );
+    }
+
+    #[test]
+    fn unknown_header_without_mapping_is_an_error() {
+        assert!(parse("a,b,c,d\n0,0,1,1\n", &CsvOptions::default()).is_err());
+    }
+
+    #[test]
+    fn offset_columns_are_not_end_coordinates() {
+        // dx/dy are offsets from the origin: reading them as end points would be wrong
+        let err: IoError = parse("ox,oy,dx,dy\n0,0,5,5\n", &CsvOptions::default()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("could not find the start/end coordinate columns")
+        );
+    }
+}
@@ -0,0 +1,266 @@
+// geojson_reader.rs - Reader of GeoJSON FeatureCollections of LineStrings
+//
+// Each feature becomes one trajectory following the vertices of its LineString, a
+// straight desire line when it has only two. The weight is read from a configurable property (rounded, 1 when absent),
+// the id from the feature "id" member or an "id" property, else the feature number.
+// JSON syntax errors carry the line and column; feature errors the 1-based feature number.
+
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::clustering::geometry::input_od_line::InputODLine;
+use crate::clustering::geometry::point::Point;
+use crate::io::io_error::IoError;
+
+pub fn has_geojson_extension(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("geojson") || ext.eq_ignore_ascii_case("json"))
+}
+
+fn parse_position(value: &Value) -> Option<Point> {
+    let coords: &Vec<Value> = value.as_array()?;
+    if coords.len() < 2 {
+        return None;
+    }
+    Some(Point {
+        x: coords[0].as_f64()?,
+        y: coords[1].as_f64()?,
+    })
+}
+
+// Integer ids only; anything else falls back to the feature number
+fn parse_id(value: Option<&Value>) -> Option<usize> {
+    match value? {
+        Value::Number(n) => n.as_u64().map(|id| id as usize),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn parse_weight(
+    properties: Option<&Map<String, Value>>,
+    weight_property: &str,
+) -> Result<u32, String> {
+    let value: Option<&Value> = properties.and_then(|p| p.get(weight_property));
+    let weight: f64 = match value {
+        None | Some(Value::Null) => return Ok(1),
+        Some(Value::Number(n)) => n.as_f64().unwrap_or(f64::NAN),
+        Some(Value::String(s)) if s.trim().parse::<f64>().is_ok() => s.trim().parse().unwrap(),
+        Some(other) => {
+            return Err(format!(
+                "property '{}' is not a number: {}",
+                weight_property, other
+            ));
+        }
+    };
+
+    if !(0.0..=u32::MAX as f64).contains(&weight) {
+        return Err(format!(
+            "property '{}' out of range: {}",
+            weight_property,
+            value.unwrap()
+        ));
+    }
+    Ok(weight.round() as u32)
+}
+
+fn parse_feature(
+    feature: &Value,
+    number: usize,
+    weight_property: &str,
+) -> Result<InputODLine, String> {
+    if feature.get("type").and_then(Value::as_str) != Some("Feature") {
+        return Err(String::from("not a Feature"));
+    }
+
+    let geometry: &Value = match feature.get("geometry") {
+        Some(geometry) if !geometry.is_null() => geometry,
+        _ => return Err(String::from("no geometry")),
+    };
+    let geometry_type: &str = geometry.get("type").and_then(Value::as_str).unwrap_or("?");
+    if geometry_type != "LineString" {
+        return Err(format!(
+            "geometry is a {}, expected a LineString",
+            geometry_type
+        ));
+    }
+
+    let positions: &Vec<Value> = geometry
+        .get("coordinates")
+        .and_then(Value::as_array)
+        .ok_or_else(|| String::from("LineString without coordinates"))?;
+    if positions.len() < 2 {
+        return Err(String::from("LineString with less than 2 vertices"));
+    }
+    let mut points: Vec<Point> = positions
+        .iter()
+        .enumerate()
+        .map(|(index, position)| {
+            parse_position(position).ok_or_else(|| format!("invalid vertex {}", index + 1))
+        })
+        .collect::<Result<_, String>>()?;
+    let end: Point = points.pop().unwrap();
+    let start: Point = points.remove(0);
+
+    let properties: Option<&Map<String, Value>> =
+        feature.get("properties").and_then(Value::as_object);
+    let line_id: usize = parse_id(feature.get("id"))
+        .or_else(|| parse_id(properties.and_then(|p| p.get("id"))))
+        .unwrap_or(number);
+
+    Ok(InputODLine {
+        line_id,
+        weight: parse_weight(properties, weight_property)?,
+        start,
+        end,
+        via: points,
+    })
+}
+
+pub fn parse_geojson(
+    content: &str,
+    path: &str,
+    weight_property: &str,
+) -> Result<Vec<InputODLine>, IoError> {
+    let root: Value = serde_json::from_str(content).map_err(|err| {
+        // serde_json appends the position to its message; it is already in the error
+        let position: String = format!(" at line {} column {}", err.line(), err.column());
+        let message: String = err.to_string().replace(&position, "");
+        IoError::parse(path, err.line(), err.column(), message)
+    })?;
+
+    // A lone Feature is accepted as a collection of one
+    let features: Vec<&Value> = match root.get("type").and_then(Value::as_str) {
+        Some("FeatureCollection") => root
+            .get("features")
+            .and_then(Value::as_array)
+            .map(|features| features.iter().collect())
+            .ok_or_else(|| {
+                IoError::parse(path, 1, 1, "FeatureCollection without a features array")
+            })?,
+        Some("Feature") => vec![&root],
+        _ => {
+            return Err(IoError::parse(
+                path,
+                1,
+                1,
+                "expected a GeoJSON FeatureCollection",
+            ));
+        }
+    };
+
+    features
+        .into_iter()
+        .enumerate()
+        .map(|(index, feature)| {
+            parse_feature(feature, index + 1, weight_property)
+                .map_err(|message| IoError::invalid_feature(path, index + 1, message))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collection(features: &[&str]) -> String {
+        format!(
+            "{{\"type\": \"FeatureCollection\", \"features\": [{}]}}",
+            features.join(",")
+        )
+    }
+
+    fn line_feature(extra: &str, coordinates: &str) -> String {
+        format!(
+            "{{\"type\": \"Feature\", {} \"geometry\": {{\"type\": \"LineString\", \"coordinates\": {}}}}}",
+            extra, coordinates
+        )
+    }
+
+    #[test]
+    fn ids_and_weights_from_the_feature() {
+        let content: String = collection(&[
+            &line_feature(
+                "\"id\": 40, \"properties\": {\"weight\": 2.6},",
+                "[[0, 0], [1, 1]]",
+            ),
+            &line_feature(
+                "\"properties\": {\"id\": \"41\", \"trips\": 9},",
+                "[[0, 0], [2, 2]]",
+            ),
+            &line_feature("", "[[0, 0], [3, 3]]"),
+        ]);
+        let lines: Vec<InputODLine> = parse_geojson(&content, "lines.geojson", "weight").unwrap();
+
+        let ids: Vec<(usize, u32)> = lines.iter().map(|l| (l.line_id, l.weight)).collect();
+        // The second weight is in another property, the third feature falls back to its number
+        assert_eq!(ids, vec![(40, 3), (41, 1), (3, 1)]);
+        assert_eq!((lines[2].end.x, lines[2].end.y), (3.0, 3.0));
+    }
+
+    #[test]
+    fn configurable_weight_property() {
+        let content: String = collection(&[&line_feature(
+            "\"properties\": {\"trips\": \"12\"},",
+            "[[0, 0], [1, 1]]",
+        )]);
+        let lines: Vec<InputODLine> = parse_geojson(&content, "lines.geojson", "trips").unwrap();
+        assert_eq!(lines[0].weight, 12);
+    }
+
+    #[test]
+    fn inner_vertices_become_via_points() {
+        let content: String =
+            collection(&[&line_feature("", "[[0, 0], [5, 1], [9, 0], [10, 10]]")]);
+        let lines: Vec<InputODLine> = parse_geojson(&content, "lines.geojson", "weight").unwrap();
+        let via: Vec<(f64, f64)> = lines[0].via.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(via, vec![(5.0, 1.0), (9.0, 0.0)]);
+        assert_eq!((lines[0].end.x, lines[0].end.y), (10.0, 10.0));
+    }
+
+    #[test]
+    fn lone_feature_is_a_collection_of_one() {
+        let content: String = line_feature("", "[[0, 0], [1, 1]]");
+        assert_eq!(
+            parse_geojson(&content, "lines.geojson", "weight")
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn invalid_features_report_their_number() {
+        let point: &str =
+            "{\"type\": \"Feature\", \"geometry\": {\"type\": \"Point\", \"coordinates\": [0, 0]}}";
+        let content: String = collection(&[&line_feature("", "[[0, 0], [1, 1]]"), point]);
+        let err: IoError = parse_geojson(&content, "lines.geojson", "weight").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "lines.geojson: feature 2: geometry is a Point, expected a LineString"
+        );
+
+        let content: String = collection(&[&line_feature(
+            "\"properties\": {\"weight\": -1},",
+            "[[0, 0], [1, 1]]",
+        )]);
+        let err: IoError = parse_geojson(&content, "lines.geojson", "weight").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "lines.geojson: feature 1: property 'weight' out of range: -1"
+        );
+    }
+
+    #[test]
+    fn syntax_errors_carry_line_and_column() {
+        let err: IoError =
+            parse_geojson("{\n  \"type\": ]", "lines.geojson", "weight").unwrap_err();
+        match err {
+            IoError::Parse { line, column, .. } => assert_eq!((line, column), (2, 11)),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+}
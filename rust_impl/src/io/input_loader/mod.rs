@@ -0,0 +1,84 @@
+// input_loader - Reads the input file into RawTrajectories
+//
+// Each reader turns the file content into InputODLines; this module picks the reader
+// from TraclusArgs::input_format, sanitizes the lines, projects longitude/latitude input
+// to a metric CRS and builds the trajectories, partitioned into segments as
+// TraclusArgs::partition asks.
+
+mod csv_reader;
+mod geojson_reader;
+mod od_list;
+mod reprojection;
+mod sanitizer;
+
+pub use sanitizer::SanitationReport;
+
+use crate::clustering::geometry::input_od_line::InputODLine;
+use crate::clustering::geometry::trajectory::Trajectory;
+use crate::clustering::storage::raw_trajectories::RawTrajectories;
+use crate::io::args::{InputCrs, InputFormat, Partitioning, TraclusArgs};
+use crate::io::io_error::IoError;
+
+use std::fs;
+use std::path::Path;
+
+fn read_file<P: AsRef<Path>>(path: P) -> Result<String, IoError> {
+    fs::read_to_string(&path).map_err(|err| IoError::read(&path, err))
+}
+
+/// Format actually used for `args.file`: Auto is resolved from the file extension.
+pub fn resolve_input_format(args: &TraclusArgs) -> InputFormat {
+    match args.input.input_format {
+        InputFormat::Auto => {
+            // A CSV preset only makes sense for CSV files
+            if args.input.csv.preset.is_some() || csv_reader::has_csv_extension(&args.file) {
+                InputFormat::Csv
+            } else if geojson_reader::has_geojson_extension(&args.file) {
+                InputFormat::GeoJson
+            } else {
+                InputFormat::OdList
+            }
+        }
+        format => format,
+    }
+}
+
+/// Trajectories of `args.file`, with the lines the sanitation left out.
+pub fn parse_input_data(
+    args: &TraclusArgs,
+) -> Result<(RawTrajectories, SanitationReport), IoError> {
+    let content: String = read_file(&args.file)?;
+
+    let od_lines: Vec<InputODLine> = match resolve_input_format(args) {
+        InputFormat::Csv => csv_reader::parse_csv(&content, &args.file, &args.input.csv)?,
+        InputFormat::GeoJson => {
+            geojson_reader::parse_geojson(&content, &args.file, &args.input.weight_property)?
+        }
+        _ => od_list::parse_od_list(&content, &args.file)?,
+    };
+    let (mut od_lines, report): (Vec<InputODLine>, SanitationReport) =
+        sanitizer::sanitize(od_lines, &args.input.sanitize, &args.file)?;
+
+    let mut trajectory_storage: RawTrajectories =
+        RawTrajectories::new(args.max_angle, args.undirected);
+    if args.input.crs.input_crs == InputCrs::Wgs84 {
+        trajectory_storage.projection = Some(reprojection::project_lines(
+            &mut od_lines,
+            args.input.crs.projection,
+            &args.file,
+        )?);
+    }
+    for od_line in od_lines {
+        let trajectory: Trajectory = match args.partition.partitioning {
+            Partitioning::Fixed => Trajectory::new(od_line, args.segment_size),
+            Partitioning::Mdl => Trajectory::new_mdl(
+                od_line,
+                args.partition.mdl_cost_weight,
+                args.partition.min_segment_length,
+            ),
+        };
+        trajectory_storage.add_trajectory(trajectory);
+    }
+
+    Ok((trajectory_storage, report))
+}
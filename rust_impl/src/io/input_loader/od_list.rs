@@ -0,0 +1,120 @@
+// od_list.rs - Reader of the original OD list format
+// One line per desire line, six whitespace-separated columns: id weight x1 y1 x2 y2
+
+use crate::clustering::geometry::input_od_line::InputODLine;
+use crate::clustering::geometry::point::Point;
+use crate::io::io_error::IoError;
+
+const NUM_FIELDS: usize = 6;
+
+// 1-based column of `field`, which must be a slice of `line`
+#[inline]
+fn column_of(line: &str, field: &str) -> usize {
+    let byte_offset: usize = field.as_ptr() as usize - line.as_ptr() as usize;
+    line[..byte_offset].chars().count() + 1
+}
+
+/// Parses one input line; errors only carry the column and message,
+/// the caller adds the path and line number.
+#[inline]
+fn parse_line_to_od(line: &str, line_id: usize) -> Result<InputODLine, (usize, String)> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+
+    if parts.len() != NUM_FIELDS {
+        let column: usize = parts
+            .get(NUM_FIELDS)
+            .map_or(1, |part| column_of(line, part));
+        return Err((
+            column,
+            format!(
+                "expected {} fields, found {}: {}",
+                NUM_FIELDS,
+                parts.len(),
+                line
+            ),
+        ));
+    }
+
+    let parse_field = |index: usize, name: &str| -> Result<f64, (usize, String)> {
+        parts[index].parse().map_err(|_| {
+            (
+                column_of(line, parts[index]),
+                format!("failed to parse {}: {:?}", name, parts[index]),
+            )
+        })
+    };
+
+    let weight: u32 = parts[1].parse().map_err(|_| {
+        (
+            column_of(line, parts[1]),
+            format!("failed to parse weight: {:?}", parts[1]),
+        )
+    })?;
+
+    Ok(InputODLine {
+        line_id,
+        weight,
+        start: Point {
+            x: parse_field(2, "start x")?,
+            y: parse_field(3, "start y")?,
+        },
+        end: Point {
+            x: parse_field(4, "end x")?,
+            y: parse_field(5, "end y")?,
+        },
+        via: Vec::new(),
+    })
+}
+
+pub fn parse_od_list(content: &str, path: &str) -> Result<Vec<InputODLine>, IoError> {
+    let mut od_lines: Vec<InputODLine> = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line_number: usize = index + 1;
+        let od_line: InputODLine = parse_line_to_od(line, line_number)
+            .map_err(|(column, message)| IoError::parse(path, line_number, column, message))?;
+        od_lines.push(od_line);
+    }
+
+    Ok(od_lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_six_columns() {
+        let lines: Vec<InputODLine> =
+            parse_od_list("7 3 0.5 1.5 10 20\n8 1 -1 -2 -3 -4\n", "trips.txt").unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].line_id, 1);
+        assert_eq!(lines[0].weight, 3);
+        assert_eq!((lines[0].start.x, lines[0].start.y), (0.5, 1.5));
+        assert_eq!((lines[1].end.x, lines[1].end.y), (-3.0, -4.0));
+    }
+
+    #[test]
+    fn wrong_field_count_points_at_the_extra_field() {
+        let err: IoError = parse_od_list("1 1 0 0 1 1\n2 1 0 0 1 1 9\n", "trips.txt").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "trips.txt:2:13: expected 6 fields, found 7: 2 1 0 0 1 1 9"
+        );
+    }
+
+    #[test]
+    fn unparsable_field_points_at_its_column() {
+        let err: IoError = parse_od_list("1 1 0 0  abc 1\n", "trips.txt").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "trips.txt:1:10: failed to parse end x: \"abc\""
+        );
+
+        let err: IoError = parse_od_list("1 -2 0 0 1 1\n", "trips.txt").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "trips.txt:1:3: failed to parse weight: \"-2\""
+        );
+    }
+}
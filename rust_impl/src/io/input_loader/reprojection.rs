@@ -0,0 +1,91 @@
+// reprojection.rs - Projection of longitude/latitude lines to the metric CRS of the clustering
+//
+// Runs after the sanitation, so every coordinate is finite. A coordinate out of the
+// longitude/latitude ranges most likely means the file is not in WGS84 at all: loading
+// stops on the first one, whatever the sanitation policies.
+
+use crate::clustering::geometry::input_od_line::InputODLine;
+use crate::clustering::geometry::point::Point;
+use crate::clustering::geometry::projection::{MetricZone, Projection};
+use crate::io::args::MetricCrs;
+use crate::io::io_error::IoError;
+
+// Beyond this distance from the central meridian the projection distorts too much to be of use
+const MAX_LONGITUDE_OFFSET: f64 = 60.0;
+
+/// Projects every vertex of `lines` in place to `target`, the UTM zone being the one of the
+/// centre of their bounding box.
+pub fn project_lines(
+    lines: &mut [InputODLine],
+    target: MetricCrs,
+    path: &str,
+) -> Result<Projection, IoError> {
+    let mut min: Point = Point {
+        x: f64::INFINITY,
+        y: f64::INFINITY,
+    };
+    let mut max: Point = Point {
+        x: f64::NEG_INFINITY,
+        y: f64::NEG_INFINITY,
+    };
+    for line in lines.iter() {
+        for point in line.points() {
+            check_range(point, line.line_id, path)?;
+            min = Point {
+                x: min.x.min(point.x),
+                y: min.y.min(point.y),
+            };
+            max = Point {
+                x: max.x.max(point.x),
+                y: max.y.max(point.y),
+            };
+        }
+    }
+
+    let zone: MetricZone = match target {
+        MetricCrs::Utm if lines.is_empty() => MetricZone::utm_at(&Point { x: 0.0, y: 0.0 }),
+        MetricCrs::Utm => MetricZone::utm_at(&((min + max) * 0.5)),
+        MetricCrs::Mtm8 => MetricZone::Mtm8,
+    };
+    let projection: Projection = Projection::new(zone);
+
+    for line in lines.iter_mut() {
+        let line_id: usize = line.line_id;
+        let points = std::iter::once(&mut line.start)
+            .chain(line.via.iter_mut())
+            .chain(std::iter::once(&mut line.end));
+        for point in points {
+            if (point.x - zone.central_meridian()).abs() > MAX_LONGITUDE_OFFSET {
+                return Err(IoError::invalid_line(
+                    path,
+                    line_id,
+                    format!(
+                        "longitude {} is more than {}° from the central meridian of {}",
+                        point.x, MAX_LONGITUDE_OFFSET, zone
+                    ),
+                ));
+            }
+            *point = projection.forward(point);
+        }
+    }
+
+    Ok(projection)
+}
+
+fn check_range(point: &Point, line_id: usize, path: &str) -> Result<(), IoError> {
+    let message: String = if !(-180.0..=180.0).contains(&point.x) {
+        format!("longitude {} is out of range -180..=180", point.x)
+    } else if !(-90.0..=90.0).contains(&point.y) {
+        format!("latitude {} is out of range -90..=90", point.y)
+    } else {
+        return Ok(());
+    };
+    Err(IoError::invalid_line(
+        path,
+        line_id,
+        format!(
+            "{}; is the input really in WGS84 longitude/latitude?",
+            message
+        ),
+    ))
+}
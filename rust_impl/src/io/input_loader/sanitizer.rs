@@ -0,0 +1,229 @@
+// sanitizer.rs - Sanitation of the parsed lines before they become trajectories
+//
+// A zero-length line (all its vertices at one point) has no segment to cluster, a NaN or
+// infinite coordinate has no place in the angle buckets or the spatial index, and a zero
+// weight adds nothing to a density.
+// Each rule has its own SanitizePolicy; a line is counted by the first rule it breaks.
+// Warnings are returned in the report, for the caller to print or show.
+
+use std::fmt;
+
+use crate::clustering::geometry::input_od_line::InputODLine;
+use crate::io::args::{SanitizeOptions, SanitizePolicy};
+use crate::io::io_error::IoError;
+
+// Warnings kept per rule before the lines are only counted
+const MAX_WARNINGS_PER_RULE: usize = 10;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Rule {
+    NonFinite,
+    ZeroLength,
+    ZeroWeight,
+}
+
+impl Rule {
+    fn describe(self) -> &'static str {
+        match self {
+            Rule::NonFinite => "has a NaN or infinite coordinate",
+            Rule::ZeroLength => "has a length of 0",
+            Rule::ZeroWeight => "has a weight of 0",
+        }
+    }
+
+    fn policy(self, options: &SanitizeOptions) -> SanitizePolicy {
+        match self {
+            Rule::NonFinite => options.non_finite,
+            Rule::ZeroLength => options.zero_length,
+            Rule::ZeroWeight => options.zero_weight,
+        }
+    }
+}
+
+/// Lines left out of the input by each rule.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SanitationReport {
+    pub non_finite: usize,
+    pub zero_length: usize,
+    pub zero_weight: usize,
+    pub warnings: Vec<String>, // lines dropped by a Warn policy, the first ones of each rule
+}
+
+impl SanitationReport {
+    pub fn dropped(&self) -> usize {
+        self.non_finite + self.zero_length + self.zero_weight
+    }
+
+    fn count(&mut self, rule: Rule) -> usize {
+        let count: &mut usize = match rule {
+            Rule::NonFinite => &mut self.non_finite,
+            Rule::ZeroLength => &mut self.zero_length,
+            Rule::ZeroWeight => &mut self.zero_weight,
+        };
+        *count += 1;
+        *count
+    }
+}
+
+impl fmt::Display for SanitationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} line(s) dropped: {} non-finite, {} zero-length, {} zero-weight",
+            self.dropped(),
+            self.non_finite,
+            self.zero_length,
+            self.zero_weight
+        )
+    }
+}
+
+fn broken_rule(line: &InputODLine) -> Option<Rule> {
+    if !line.points().all(|p| p.x.is_finite() && p.y.is_finite()) {
+        Some(Rule::NonFinite)
+    } else if line
+        .points()
+        .all(|p| p.x == line.start.x && p.y == line.start.y)
+    {
+        Some(Rule::ZeroLength)
+    } else if line.weight == 0 {
+        Some(Rule::ZeroWeight)
+    } else {
+        None
+    }
+}
+
+/// Keeps the lines breaking no rule; `path` names the input in warnings and errors.
+pub fn sanitize(
+    od_lines: Vec<InputODLine>,
+    options: &SanitizeOptions,
+    path: &str,
+) -> Result<(Vec<InputODLine>, SanitationReport), IoError> {
+    let mut report: SanitationReport = SanitationReport::default();
+    let mut kept: Vec<InputODLine> = Vec::with_capacity(od_lines.len());
+
+    for line in od_lines {
+        let Some(rule) = broken_rule(&line) else {
+            kept.push(line);
+            continue;
+        };
+
+        match rule.policy(options) {
+            SanitizePolicy::Fail => {
+                return Err(IoError::invalid_line(path, line.line_id, rule.describe()));
+            }
+            SanitizePolicy::Warn => {
+                let count: usize = report.count(rule);
+                if count <= MAX_WARNINGS_PER_RULE {
+                    report.warnings.push(format!(
+                        "{}: line id {} {}, dropped",
+                        path,
+                        line.line_id,
+                        rule.describe()
+                    ));
+                }
+            }
+            SanitizePolicy::Drop => {
+                report.count(rule);
+            }
+        }
+    }
+
+    Ok((kept, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::geometry::point::Point;
+
+    fn line(line_id: usize, weight: u32, start: (f64, f64), end: (f64, f64)) -> InputODLine {
+        InputODLine {
+            line_id,
+            weight,
+            start: Point {
+                x: start.0,
+                y: start.1,
+            },
+            end: Point { x: end.0, y: end.1 },
+            via: Vec::new(),
+        }
+    }
+
+    // One valid line, then one line breaking each rule
+    fn mixed_lines() -> Vec<InputODLine> {
+        vec![
+            line(1, 1, (0.0, 0.0), (1.0, 1.0)),
+            line(2, 1, (0.0, f64::NAN), (1.0, 1.0)),
+            line(3, 1, (5.0, 5.0), (5.0, 5.0)),
+            line(4, 0, (0.0, 0.0), (1.0, 1.0)),
+        ]
+    }
+
+    fn ids(lines: &[InputODLine]) -> Vec<usize> {
+        lines.iter().map(|line| line.line_id).collect()
+    }
+
+    #[test]
+    fn default_policies_drop_and_count() {
+        let (kept, report) =
+            sanitize(mixed_lines(), &SanitizeOptions::default(), "trips.txt").unwrap();
+        assert_eq!(ids(&kept), vec![1]);
+        assert_eq!(
+            (report.non_finite, report.zero_length, report.zero_weight),
+            (1, 1, 1)
+        );
+        assert_eq!(
+            report.to_string(),
+            "3 line(s) dropped: 1 non-finite, 1 zero-length, 1 zero-weight"
+        );
+    }
+
+    #[test]
+    fn fail_policy_names_the_line() {
+        let options: SanitizeOptions = SanitizeOptions {
+            zero_length: SanitizePolicy::Fail,
+            ..SanitizeOptions::default()
+        };
+        let err: IoError = sanitize(mixed_lines(), &options, "trips.txt").unwrap_err();
+        assert_eq!(err.to_string(), "trips.txt: line id 3: has a length of 0");
+    }
+
+    #[test]
+    fn a_line_counts_for_its_first_broken_rule() {
+        // Non-finite, zero-length and zero-weight at once
+        let lines: Vec<InputODLine> = vec![line(9, 0, (f64::INFINITY, 0.0), (f64::INFINITY, 0.0))];
+        let (kept, report) = sanitize(lines, &SanitizeOptions::default(), "trips.txt").unwrap();
+        assert!(kept.is_empty());
+        assert_eq!((report.non_finite, report.dropped()), (1, 1));
+    }
+
+    #[test]
+    fn polyline_returning_to_its_start_is_not_zero_length() {
+        let mut closed: InputODLine = line(1, 1, (0.0, 0.0), (0.0, 0.0));
+        closed.via.push(Point { x: 10.0, y: 0.0 });
+        let (kept, _) = sanitize(vec![closed], &SanitizeOptions::default(), "trips.txt").unwrap();
+        assert_eq!(ids(&kept), vec![1]);
+    }
+
+    #[test]
+    fn warn_policy_reports_the_first_lines_of_each_rule() {
+        let options: SanitizeOptions = SanitizeOptions {
+            zero_weight: SanitizePolicy::Warn,
+            ..SanitizeOptions::default()
+        };
+        let lines: Vec<InputODLine> = (1..=15)
+            .map(|line_id| line(line_id, 0, (0.0, 0.0), (1.0, 1.0)))
+            .chain(mixed_lines())
+            .collect();
+        let (kept, report) = sanitize(lines, &options, "trips.txt").unwrap();
+
+        assert_eq!(ids(&kept), vec![1]);
+        assert_eq!(report.zero_weight, 16);
+        assert_eq!(report.warnings.len(), MAX_WARNINGS_PER_RULE);
+        assert_eq!(
+            report.warnings[0],
+            "trips.txt: line id 1 has a weight of 0, dropped"
+        );
+    }
+}
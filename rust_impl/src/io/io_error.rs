@@ -0,0 +1,178 @@
+// io_error.rs - Errors of the input loader and the output writer
+//
+// Every variant carries the file path, and parse errors the line and column of the
+// offending field, so the GUI and the CLI can point at the exact place to fix.
+// Messages are Strings (not std::io::Error) so the error stays Clone and can travel
+// inside AppEvent::Error.
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+pub enum IoError {
+    /// The input file could not be opened or read
+    Read { path: String, message: String },
+
+    /// A line of the input file is malformed; `line` and `column` are 1-based
+    Parse {
+        path: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+
+    /// A GeoJSON feature cannot be read as a desire line; `feature` is 1-based
+    InvalidFeature {
+        path: String,
+        feature: usize,
+        message: String,
+    },
+
+    /// A parsed line refused by the sanitation (policy `fail`) or the projection; `line_id` is the line's id
+    InvalidLine {
+        path: String,
+        line_id: usize,
+        message: String,
+    },
+
+    /// An output file could not be created or written
+    Write { path: String, message: String },
+}
+
+impl IoError {
+    pub fn read(path: impl AsRef<Path>, err: io::Error) -> Self {
+        IoError::Read {
+            path: path.as_ref().display().to_string(),
+            message: err.to_string(),
+        }
+    }
+
+    pub fn parse(
+        path: impl AsRef<Path>,
+        line: usize,
+        column: usize,
+        message: impl Into<String>,
+    ) -> Self {
+        IoError::Parse {
+            path: path.as_ref().display().to_string(),
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_feature(
+        path: impl AsRef<Path>,
+        feature: usize,
+        message: impl Into<String>,
+    ) -> Self {
+        IoError::InvalidFeature {
+            path: path.as_ref().display().to_string(),
+            feature,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_line(
+        path: impl AsRef<Path>,
+        line_id: usize,
+        message: impl Into<String>,
+    ) -> Self {
+        IoError::InvalidLine {
+            path: path.as_ref().display().to_string(),
+            line_id,
+            message: message.into(),
+        }
+    }
+
+    pub fn write(path: impl AsRef<Path>, err: io::Error) -> Self {
+        IoError::Write {
+            path: path.as_ref().display().to_string(),
+            message: err.to_string(),
+        }
+    }
+
+    /// Process exit code used by the command line (2 is taken by clap usage errors).
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            IoError::Read { .. } => 3,
+            IoError::Parse { .. }
+            | IoError::InvalidFeature { .. }
+            | IoError::InvalidLine { .. } => 4,
+            IoError::Write { .. } => 5,
+        }
+    }
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IoError::Read { path, message } => {
+                write!(f, "Failed to read input file {}: {}", path, message)
+            }
+            IoError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path, line, column, message),
+            IoError::InvalidFeature {
+                path,
+                feature,
+                message,
+            } => write!(f, "{}: feature {}: {}", path, feature, message),
+            IoError::InvalidLine {
+                path,
+                line_id,
+                message,
+            } => write!(f, "{}: line id {}: {}", path, line_id, message),
+            IoError::Write { path, message } => {
+                write!(f, "Failed to write output file {}: {}", path, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IoError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_error_points_at_line_and_column() {
+        let err: IoError = IoError::parse("trips.txt", 12, 3, "failed to parse weight: \"x\"");
+        assert_eq!(
+            err.to_string(),
+            "trips.txt:12:3: failed to parse weight: \"x\""
+        );
+    }
+
+    #[test]
+    fn read_and_write_errors_name_the_file() {
+        let not_found: io::Error = io::Error::new(io::ErrorKind::NotFound, "no such file");
+        let err: IoError = IoError::read("missing.txt", not_found);
+        assert_eq!(
+            err.to_string(),
+            "Failed to read input file missing.txt: no such file"
+        );
+
+        let denied: io::Error = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        let err: IoError = IoError::write("out/corridors.txt", denied);
+        assert_eq!(
+            err.to_string(),
+            "Failed to write output file out/corridors.txt: denied"
+        );
+    }
+
+    #[test]
+    fn exit_codes_by_kind() {
+        let io_err = || io::Error::other("failure");
+        assert_eq!(IoError::read("a", io_err()).exit_code(), 3);
+        assert_eq!(IoError::parse("a", 1, 1, "bad").exit_code(), 4);
+        assert_eq!(IoError::invalid_feature("a", 1, "bad").exit_code(), 4);
+        assert_eq!(IoError::invalid_line("a", 1, "bad").exit_code(), 4);
+        assert_eq!(IoError::write("a", io_err()).exit_code(), 5);
+    }
+}
@@ -4,21 +4,23 @@
 // CPU usage is kept low with zero busy-wait — the thread parks completely between events.
 
 use std::sync::mpsc::Receiver;
-use std::thread;
-use std::time::Instant;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use crate::gui::app_events::AppEvent;
+use crate::utils::time_estimator::format_duration;
 
 pub struct Logger;
 
 impl Logger {
     /// Spawn the logger thread.
     /// `rx` is the Receiver obtained from EventBus::subscribe().
-    pub fn start(rx: Receiver<AppEvent>) {
+    /// The thread ends once every sender is dropped; join it to flush the last events.
+    pub fn start(rx: Receiver<AppEvent>) -> JoinHandle<()> {
         thread::Builder::new()
             .name("traclus-logger".to_string())
             .spawn(move || Self::run(rx))
-            .expect("failed to spawn logger thread");
+            .expect("failed to spawn logger thread")
     }
 
     fn run(rx: Receiver<AppEvent>) {
@@ -29,21 +31,63 @@ impl Logger {
             match event {
                 AppEvent::LoadComplete {
                     traj_count,
-                    correlation_percent,
+                    sanitation,
                 } => {
                     println!(
-                        "[LOG] LOAD COMPLETED at {:?} — {} trajectories loaded, correlation: {:.2}%.",
+                        "[LOG] LOAD COMPLETED at {:?} — {} trajectories loaded, {}.",
                         start_time.elapsed(),
                         traj_count,
+                        sanitation
+                    );
+                }
+
+                AppEvent::CorrelationEstimate {
+                    correlation_percent,
+                } => {
+                    println!(
+                        "[LOG] CORRELATION at {:?} — {:.2}% of the weight has a neighbour.",
+                        start_time.elapsed(),
                         correlation_percent
                     );
                 }
 
-                AppEvent::ComputationClusteringProgress { num_traj_done } => {
+                AppEvent::PreRunEstimate { estimated_secs } => {
                     println!(
-                        "[LOG] COMPUTATION CLUSTERING PROGRESS at {:?} — {} trajectories done.",
+                        "[LOG] PRE-RUN ESTIMATE at {:?} — about {}.",
                         start_time.elapsed(),
-                        num_traj_done
+                        format_duration(Duration::from_secs_f64(estimated_secs))
+                    );
+                }
+
+                AppEvent::TimeEstimate { remaining_secs } => {
+                    println!(
+                        "[LOG] TIME ESTIMATE at {:?} — {} remaining.",
+                        start_time.elapsed(),
+                        format_duration(Duration::from_secs_f64(remaining_secs))
+                    );
+                }
+
+                AppEvent::ComputationClusteringProgress {
+                    num_traj_done,
+                    total_traj,
+                } => {
+                    println!(
+                        "[LOG] COMPUTATION CLUSTERING PROGRESS at {:?} — {}/{} trajectories done.",
+                        start_time.elapsed(),
+                        num_traj_done,
+                        total_traj
+                    );
+                }
+
+                AppEvent::CorridorFinalizationProgress {
+                    num_corridors_done,
+                    num_clusters_left,
+                } => {
+                    println!(
+                        "[LOG] CORRIDOR FINALIZATION PROGRESS at {:?} — {} corridors done, {} clusters left.",
+                        start_time.elapsed(),
+                        num_corridors_done,
+                        num_clusters_left
                     );
                 }
 
@@ -61,6 +105,49 @@ impl Logger {
                     );
                 }
 
+                AppEvent::Cancelled => {
+                    println!(
+                        "[LOG] COMPUTATION CANCELLED at {:?} — previous results kept.",
+                        start_time.elapsed()
+                    );
+                }
+
+                AppEvent::OutputsWritten { files } => {
+                    println!(
+                        "[LOG] OUTPUTS WRITTEN at {:?} — {}.",
+                        start_time.elapsed(),
+                        files.join(", ")
+                    );
+                }
+
+                AppEvent::SessionChanged {
+                    dataset,
+                    run,
+                    change,
+                    memory_bytes,
+                } => {
+                    let entry: String = match run {
+                        Some(run) => format!("dataset {} run {}", dataset, run),
+                        None => format!("dataset {}", dataset),
+                    };
+                    println!(
+                        "[LOG] SESSION CHANGED at {:?} — {} {}, store holds about {:.1} MB.",
+                        start_time.elapsed(),
+                        entry,
+                        change,
+                        memory_bytes as f64 / 1e6
+                    );
+                }
+
+                AppEvent::ComparisonComplete(comparison) => {
+                    println!(
+                        "[LOG] COMPARISON COMPLETE at {:?} — {:.2}% of the segments assigned consistently, {} corridor matches.",
+                        start_time.elapsed(),
+                        comparison.report.consistency_share() * 100.0,
+                        comparison.report.corridor_matches.len()
+                    );
+                }
+
                 AppEvent::Error(msg) => {
                     eprintln!("[LOG][ERROR] {}", msg);
                 }
@@ -1,5 +1,10 @@
 pub mod output_writer;
+pub mod dataset_writer;
+pub mod geopackage_writer;
+pub mod shapefile_writer;
 pub mod args;
 pub mod input_loader;
+pub mod io_error;
 pub mod logger;
+pub mod run_reader;
 pub mod args_config;
@@ -1,32 +1,111 @@
+use crate::clustering::geometry::point::Point;
+use crate::clustering::geometry::projection::Projection;
 use crate::clustering::objects::cluster_member::ClusterMember;
 use crate::clustering::objects::corridor::Corridor;
 use crate::clustering::storage::clustered_trajectories::ClusteredTrajectories;
-use crate::io::args::TraclusArgs;
+use crate::io::args::{DistanceMetric, DistanceOptions, OutputFormat, Partitioning, TraclusArgs};
+use crate::io::io_error::IoError;
+use serde_json::{Value, json};
 use std::path::Path;
 
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
+
+// Leads the first line of the new segment list, followed by the partition settings that
+// numbered the segments: `compare` only matches segment ids of runs cut the same way.
+// The old format keeps its single header line, read by benchmarks/measurements.py.
+pub const PARTITION_LINE_PREFIX: &str = "# partition: ";
 
 pub enum SegmentOutputFormat {
     OldTraclus,
     NewTraclus,
 }
 
+// One deliverable of a run; an OutputFormat of the command line selects a fixed set
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OutputKind {
+    CorridorList,
+    SegmentListNew,
+    SegmentListOld,
+    GeoJson,
+    GeoPackage,
+    Shapefile,
+}
+
+impl OutputKind {
+    pub const ALL: [OutputKind; 6] = [
+        OutputKind::CorridorList,
+        OutputKind::SegmentListNew,
+        OutputKind::SegmentListOld,
+        OutputKind::GeoJson,
+        OutputKind::GeoPackage,
+        OutputKind::Shapefile,
+    ];
+
+    pub fn for_format(format: OutputFormat) -> &'static [OutputKind] {
+        match format {
+            OutputFormat::Text => &[
+                OutputKind::CorridorList,
+                OutputKind::SegmentListNew,
+                OutputKind::SegmentListOld,
+            ],
+            OutputFormat::GeoJson => &[OutputKind::GeoJson],
+            OutputFormat::GeoPackage => &[OutputKind::GeoPackage],
+            OutputFormat::Shapefile => &[OutputKind::Shapefile],
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OutputKind::CorridorList => "Corridor list (.txt)",
+            OutputKind::SegmentListNew => "Segment list, new format (.txt)",
+            OutputKind::SegmentListOld => "Segment list, old format (.txt)",
+            OutputKind::GeoJson => "Corridors and segments (.geojson)",
+            OutputKind::GeoPackage => "GeoPackage with inputs and metadata (.gpkg)",
+            OutputKind::Shapefile => "Corridors and segments (.shp)",
+        }
+    }
+}
+
 // Generate the corridor output file to a text file
-pub fn generate_corridor_file(args: &TraclusArgs, clust_storage: &ClusteredTrajectories) {
+pub fn generate_corridor_file(
+    args: &TraclusArgs,
+    clust_storage: &ClusteredTrajectories,
+    projection: Option<&Projection>,
+) -> Result<String, IoError> {
     let output_filename: String = build_corridor_output_filename(args);
 
-    let file: File = File::create(&output_filename).expect("Failed to create corridor output file");
+    write_corridors(&output_filename, clust_storage, args.undirected, projection)
+        .map_err(|err| IoError::write(&output_filename, err))?;
+
+    println!("Corridor output written to: {}", output_filename);
+    Ok(output_filename)
+}
+
+// The undirected mode adds the weight in each direction before the coordinates
+fn write_corridors(
+    output_filename: &str,
+    clust_storage: &ClusteredTrajectories,
+    undirected: bool,
+    projection: Option<&Projection>,
+) -> io::Result<()> {
+    let file: File = File::create(output_filename)?;
     let mut writer: BufWriter<File> = BufWriter::new(file);
 
-    writeln!(writer, "name\tweight\tcoordinates").expect("Failed to write corridor header");
+    if undirected {
+        writeln!(
+            writer,
+            "name\tweight\tforward_weight\tbackward_weight\tcoordinates"
+        )?;
+    } else {
+        writeln!(writer, "name\tweight\tcoordinates")?;
+    }
 
     for corridor in &clust_storage.corridors {
-        write_single_corridor(&mut writer, corridor);
+        write_single_corridor(&mut writer, corridor, undirected, projection)?;
     }
 
-    writer.flush().expect("Failed to flush corridor file");
-    println!("Corridor output written to: {}", output_filename);
+    writer.flush()
 }
 
 // Generate the segment output file to a text file
@@ -34,85 +113,234 @@ pub fn generate_segment_file(
     args: &TraclusArgs,
     clust_storage: &ClusteredTrajectories,
     format: SegmentOutputFormat,
-) {
+    projection: Option<&Projection>,
+) -> Result<String, IoError> {
     let output_filename: String = build_segment_output_filename(args, &format);
 
-    let file: File = File::create(&output_filename).expect("Failed to create segment output file");
+    write_segments(&output_filename, args, clust_storage, &format, projection)
+        .map_err(|err| IoError::write(&output_filename, err))?;
+
+    println!("Segment output written to: {}", output_filename);
+    Ok(output_filename)
+}
+
+fn write_segments(
+    output_filename: &str,
+    args: &TraclusArgs,
+    clust_storage: &ClusteredTrajectories,
+    format: &SegmentOutputFormat,
+    projection: Option<&Projection>,
+) -> io::Result<()> {
+    let file: File = File::create(output_filename)?;
     let mut writer: BufWriter<File> = BufWriter::new(file);
 
-    write_segment_header(&mut writer, &format);
+    write_segment_header(&mut writer, args, format)?;
 
     for (corridor_id, cluster_member) in clust_storage.get_all_cluster_members_iter() {
         match format {
             SegmentOutputFormat::OldTraclus => {
-                write_single_segment_old(&mut writer, corridor_id, cluster_member);
+                write_single_segment_old(&mut writer, corridor_id, cluster_member, projection)?;
             }
             SegmentOutputFormat::NewTraclus => {
-                write_single_segment_new(&mut writer, corridor_id, cluster_member);
+                write_single_segment_new(&mut writer, corridor_id, cluster_member, projection)?;
             }
         }
     }
 
-    writer.flush().expect("Failed to flush segment file");
+    writer.flush()
+}
+
+// Generate the corridor output file as a GeoJSON FeatureCollection
+pub fn generate_corridor_geojson(
+    args: &TraclusArgs,
+    clust_storage: &ClusteredTrajectories,
+    projection: Option<&Projection>,
+) -> Result<String, IoError> {
+    let output_filename: String = build_output_filename(args, "corridorlist", "geojson");
+
+    let features = clust_storage.corridors.iter().map(|corridor| {
+        let mut properties: Value = json!({ "id": corridor.id, "weight": corridor.weight });
+        if args.undirected {
+            properties["forward_weight"] = json!(corridor.forward_weight);
+            properties["backward_weight"] = json!(corridor.backward_weight);
+        }
+        json!({
+            "type": "Feature",
+            "properties": properties,
+            "geometry": line_string(
+                &output_point(projection, &corridor.start),
+                &output_point(projection, &corridor.end),
+            ),
+        })
+    });
+    write_feature_collection(&output_filename, features)
+        .map_err(|err| IoError::write(&output_filename, err))?;
+
+    println!("Corridor output written to: {}", output_filename);
+    Ok(output_filename)
+}
+
+// Generate the segment output file as a GeoJSON FeatureCollection
+pub fn generate_segment_geojson(
+    args: &TraclusArgs,
+    clust_storage: &ClusteredTrajectories,
+    projection: Option<&Projection>,
+) -> Result<String, IoError> {
+    let output_filename: String = build_output_filename(args, "segmentlist", "geojson");
+
+    let features =
+        clust_storage
+            .get_all_cluster_members_iter()
+            .map(|(corridor_id, cluster_member)| {
+                json!({
+                    "type": "Feature",
+                    "properties": {
+                        "corridor_id": corridor_id,
+                        "trajectory_id": cluster_member.traj_id,
+                        "segment_id": cluster_member.segment_id,
+                        "weight": cluster_member.weight,
+                        "angle": cluster_member.angle(),
+                    },
+                    "geometry": line_string(
+                        &output_point(projection, &cluster_member.start),
+                        &output_point(projection, &cluster_member.end_point()),
+                    ),
+                })
+            });
+    write_feature_collection(&output_filename, features)
+        .map_err(|err| IoError::write(&output_filename, err))?;
 
     println!("Segment output written to: {}", output_filename);
+    Ok(output_filename)
 }
 
-fn build_corridor_output_filename(args: &TraclusArgs) -> String {
-    let input_path: &Path = Path::new(&args.file);
-    let basename: &str = input_path
-        .file_stem()
-        .and_then(|n| n.to_str())
-        .unwrap_or("output");
+// One feature per line, so large outputs stay readable and diffable
+pub fn write_feature_collection(
+    output_filename: &str,
+    features: impl Iterator<Item = Value>,
+) -> io::Result<()> {
+    let file: File = File::create(output_filename)?;
+    let mut writer: BufWriter<File> = BufWriter::new(file);
 
-    let parent_dir: &Path = input_path.parent().unwrap_or_else(|| Path::new("."));
+    writeln!(writer, "{{\"type\":\"FeatureCollection\",\"features\":[")?;
+    for (i, feature) in features.enumerate() {
+        if i > 0 {
+            writeln!(writer, ",")?;
+        }
+        serde_json::to_writer(&mut writer, &feature)?;
+    }
+    writeln!(writer, "\n]}}")?;
 
-    format!(
-        "{}/{}[{}-{}-{}-{}-{}].corridorlist.txt",
-        parent_dir.display(),
-        basename,
-        args.max_dist.round(),
-        args.min_density,
-        args.max_angle.round(),
-        args.segment_size.round(),
-        args.mode,
-    )
+    writer.flush()
+}
+
+/// Coordinates written for `point`: back to longitude/latitude when the input was projected.
+pub fn output_point(projection: Option<&Projection>, point: &Point) -> Point {
+    match projection {
+        Some(projection) => projection.inverse(point),
+        None => *point,
+    }
+}
+
+pub fn line_string(start: &Point, end: &Point) -> Value {
+    json!({
+        "type": "LineString",
+        "coordinates": [[start.x, start.y], [end.x, end.y]],
+    })
+}
+
+fn build_corridor_output_filename(args: &TraclusArgs) -> String {
+    build_output_filename(args, "corridorlist", "txt")
 }
 
 fn build_segment_output_filename(args: &TraclusArgs, format: &SegmentOutputFormat) -> String {
+    let suffix = match format {
+        SegmentOutputFormat::OldTraclus => "segmentlist_old",
+        SegmentOutputFormat::NewTraclus => "segmentlist_new",
+    };
+
+    build_output_filename(args, suffix, "txt")
+}
+
+// {output_dir or input_dir}/{input_stem}[{max_dist}-{min_density}-{max_angle}-{partitioning}-{mode}{distance}{direction}].{suffix}.{extension}
+// where {partitioning} is the segment_size, or mdl{mdl_cost_weight}_min{min_segment_length}
+// with the MDL partitioning, {distance} is empty for the midpoint metric, -hausdorff, or
+// -traclus{w⊥}_{w∥}_{wθ}, and {direction} is -undirected in the undirected mode, empty otherwise
+pub fn build_output_filename(args: &TraclusArgs, suffix: &str, extension: &str) -> String {
     let input_path: &Path = Path::new(&args.file);
     let basename: &str = input_path
         .file_stem()
         .and_then(|n| n.to_str())
         .unwrap_or("output");
 
-    let parent_dir: &Path = input_path.parent().unwrap_or_else(|| Path::new("."));
-
-    let suffix = match format {
-        SegmentOutputFormat::OldTraclus => "segmentlist_old",
-        SegmentOutputFormat::NewTraclus => "segmentlist_new",
+    // A bare file name has an empty parent: write next to it, not at the root
+    let parent_dir: &Path = match &args.output_dir {
+        Some(output_dir) => Path::new(output_dir),
+        None => input_path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new(".")),
     };
 
     format!(
-        "{}/{}[{}-{}-{}-{}-{}].{}.txt",
+        "{}/{}[{}-{}-{}-{}-{}{}{}].{}.{}",
         parent_dir.display(),
         basename,
         args.max_dist.round(),
         args.min_density,
         args.max_angle.round(),
-        args.segment_size.round(),
+        partitioning_tag(args),
         args.mode,
-        suffix
+        distance_tag(args),
+        if args.undirected { "-undirected" } else { "" },
+        suffix,
+        extension
     )
 }
 
+fn partitioning_tag(args: &TraclusArgs) -> String {
+    match args.partition.partitioning {
+        Partitioning::Fixed => args.segment_size.round().to_string(),
+        Partitioning::Mdl => format!(
+            "mdl{}_min{}",
+            args.partition.mdl_cost_weight, args.partition.min_segment_length
+        ),
+    }
+}
+
+/// "fixed segment_size=100", or "mdl mdl_cost_weight=1 min_segment_length=0": the settings
+/// the segment ids depend on.
+pub fn partition_settings(args: &TraclusArgs) -> String {
+    match args.partition.partitioning {
+        Partitioning::Fixed => format!("fixed segment_size={}", args.segment_size),
+        Partitioning::Mdl => format!(
+            "mdl mdl_cost_weight={} min_segment_length={}",
+            args.partition.mdl_cost_weight, args.partition.min_segment_length
+        ),
+    }
+}
+
+fn distance_tag(args: &TraclusArgs) -> String {
+    let distance: &DistanceOptions = &args.distance;
+    match distance.metric {
+        DistanceMetric::Midpoint => String::new(),
+        DistanceMetric::Hausdorff => format!("-{}", distance.metric),
+        DistanceMetric::Traclus => format!(
+            "-{}{}_{}_{}",
+            distance.metric, distance.w_perpendicular, distance.w_parallel, distance.w_angular
+        ),
+    }
+}
+
 // Format: {corridor_id}\t{trajectory_id}\t{segment_id}\t{weight}\t{angle}\tLINESTRING({x1} {y1}, {x2} {y2})
 fn write_single_segment_new(
     writer: &mut BufWriter<File>,
     corridor_id: i32,
     cluster_member: &ClusterMember,
-) {
-    let end_point = cluster_member.end_point();
+    projection: Option<&Projection>,
+) -> io::Result<()> {
+    let start_point = output_point(projection, &cluster_member.start);
+    let end_point = output_point(projection, &cluster_member.end_point());
     writeln!(
         writer,
         "{}\t{}\t{}\t{}\t{}\tLINESTRING({} {}, {} {})",
@@ -121,12 +349,11 @@ fn write_single_segment_new(
         cluster_member.segment_id,
         cluster_member.weight,
         cluster_member.angle(),
-        cluster_member.start.x,
-        cluster_member.start.y,
+        start_point.x,
+        start_point.y,
         end_point.x,
         end_point.y
     )
-    .expect("Failed to write new segment");
 }
 
 // Format: {trajectory_id:segment_id}\t{weight}\t{angle}\t{corridor_id}\tLINESTRING({x1} {y1}, {x2} {y2})
@@ -134,9 +361,11 @@ fn write_single_segment_old(
     writer: &mut BufWriter<File>,
     corridor_id: i32,
     cluster_member: &ClusterMember,
-) {
-    let end_point = cluster_member.end_point();
-    let start_str = cluster_member.start.x.to_string() + ":" + &cluster_member.start.y.to_string();
+    projection: Option<&Projection>,
+) -> io::Result<()> {
+    let start_point = output_point(projection, &cluster_member.start);
+    let end_point = output_point(projection, &cluster_member.end_point());
+    let start_str = start_point.x.to_string() + ":" + &start_point.y.to_string();
     let segment_id = cluster_member.traj_id.to_string() + ":" + &start_str;
 
     writeln!(
@@ -146,44 +375,67 @@ fn write_single_segment_old(
         cluster_member.weight,
         cluster_member.angle(),
         corridor_id,
-        cluster_member.start.x,
-        cluster_member.start.y,
+        start_point.x,
+        start_point.y,
         end_point.x,
         end_point.y
     )
-    .expect("Failed to write old segment");
 }
 
-// Format: {id}\t{weight}\tLINESTRING({x1} {y1}, {x2} {y2})
-fn write_single_corridor(writer: &mut BufWriter<File>, corridor: &Corridor) {
+// Format: {id}\t{weight}\tLINESTRING({x1} {y1}, {x2} {y2}),
+// {id}\t{weight}\t{forward_weight}\t{backward_weight}\tLINESTRING(...) when undirected
+fn write_single_corridor(
+    writer: &mut BufWriter<File>,
+    corridor: &Corridor,
+    undirected: bool,
+    projection: Option<&Projection>,
+) -> io::Result<()> {
+    let start_point = output_point(projection, &corridor.start);
+    let end_point = output_point(projection, &corridor.end);
+    if undirected {
+        return writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\tLINESTRING({} {}, {} {})",
+            corridor.id,
+            corridor.weight,
+            corridor.forward_weight,
+            corridor.backward_weight,
+            start_point.x,
+            start_point.y,
+            end_point.x,
+            end_point.y
+        );
+    }
     writeln!(
         writer,
         "{}\t{}\tLINESTRING({} {}, {} {})",
-        corridor.id,
-        corridor.weight,
-        corridor.start.x,
-        corridor.start.y,
-        corridor.end.x,
-        corridor.end.y
+        corridor.id, corridor.weight, start_point.x, start_point.y, end_point.x, end_point.y
     )
-    .expect("Failed to write corridor");
 }
 
 // Writes the segment header based on the specified format.
 // Old Traclus: id weight angle corridor_id coordinates
 // New Traclus: corridor_id trajectory_id segment_id weight angle coordinates
-fn write_segment_header(writer: &mut BufWriter<File>, format: &SegmentOutputFormat) {
+fn write_segment_header(
+    writer: &mut BufWriter<File>,
+    args: &TraclusArgs,
+    format: &SegmentOutputFormat,
+) -> io::Result<()> {
     match format {
         SegmentOutputFormat::OldTraclus => {
             writeln!(writer, "id\tweight\tangle\tcorridor_id\tcoordinates")
-                .expect("Failed to write old segment header");
         }
         SegmentOutputFormat::NewTraclus => {
+            writeln!(
+                writer,
+                "{}{}",
+                PARTITION_LINE_PREFIX,
+                partition_settings(args)
+            )?;
             writeln!(
                 writer,
                 "corridor_id\ttrajectory_id\tsegment_id\tweight\tangle\tcoordinates"
             )
-            .expect("Failed to write new segment header");
         }
     }
 }
@@ -0,0 +1,336 @@
+// run_reader.rs - Reader of the text outputs of a run, to compare runs written to disk
+//
+// Reads a corridor list and the segment list in the new format; the old format keys the
+// segments by their start point and carries no segment id, so it cannot be matched.
+// The partition line before the column header of the new format is optional: files of
+// other implementations or older runs start with the column header.
+// The files may come from this implementation or any other writing the same columns.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use crate::clustering::comparison::{CorridorLine, RunSnapshot, SegmentKey};
+use crate::clustering::geometry::point::Point;
+use crate::io::io_error::IoError;
+use crate::io::output_writer::PARTITION_LINE_PREFIX;
+
+const SEGMENT_LIST_NEW_SUFFIX: &str = ".segmentlist_new.";
+const CORRIDOR_LIST_SUFFIX: &str = ".corridorlist.";
+const OLD_SEGMENT_HEADER: &str = "id\tweight\tangle\tcorridor_id\tcoordinates";
+
+/// Corridor list written next to a new-format segment list by the same run.
+pub fn corridor_list_beside(segments_path: &str) -> Option<String> {
+    segments_path
+        .contains(SEGMENT_LIST_NEW_SUFFIX)
+        .then(|| segments_path.replacen(SEGMENT_LIST_NEW_SUFFIX, CORRIDOR_LIST_SUFFIX, 1))
+}
+
+pub fn read_run_outputs(segments_path: &str, corridors_path: &str) -> Result<RunSnapshot, IoError> {
+    let corridors: Vec<CorridorLine> = read_corridor_list(corridors_path)?;
+    let corridor_ids: HashSet<usize> = corridors.iter().map(|c| c.id).collect();
+
+    let content: String =
+        fs::read_to_string(segments_path).map_err(|err| IoError::read(segments_path, err))?;
+    let mut segments: HashMap<SegmentKey, Option<usize>> = HashMap::new();
+    let mut partition: Option<String> = None;
+    let mut header_index: usize = 0;
+
+    for (index, line) in content.lines().enumerate() {
+        let line_number: usize = index + 1;
+        if index == 0
+            && let Some(settings) = line.strip_prefix(PARTITION_LINE_PREFIX)
+        {
+            partition = Some(settings.trim_end().to_string());
+            header_index = 1;
+            continue;
+        }
+        if index == header_index {
+            if line.trim_end() == OLD_SEGMENT_HEADER {
+                return Err(IoError::parse(
+                    segments_path,
+                    line_number,
+                    1,
+                    "old segment list format has no segment id, compare the segmentlist_new file",
+                ));
+            }
+            continue; // header
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<(usize, &str)> = tab_fields(line);
+        if fields.len() != 6 {
+            return Err(IoError::parse(
+                segments_path,
+                line_number,
+                1,
+                format!("expected 6 tab-separated fields, found {}", fields.len()),
+            ));
+        }
+        let parse_err = |(column, field): (usize, &str), name: &str| {
+            IoError::parse(
+                segments_path,
+                line_number,
+                column,
+                format!("failed to parse {}: {:?}", name, field),
+            )
+        };
+
+        let corridor_id: i64 = fields[0]
+            .1
+            .parse()
+            .map_err(|_| parse_err(fields[0], "corridor id"))?;
+        let traj_id: usize = fields[1]
+            .1
+            .parse()
+            .map_err(|_| parse_err(fields[1], "trajectory id"))?;
+        let segment_id: usize = fields[2]
+            .1
+            .parse()
+            .map_err(|_| parse_err(fields[2], "segment id"))?;
+
+        // -1 marks the non-clustered segments
+        let corridor: Option<usize> = match corridor_id {
+            -1 => None,
+            id if id >= 0 && corridor_ids.contains(&(id as usize)) => Some(id as usize),
+            _ => {
+                return Err(IoError::parse(
+                    segments_path,
+                    line_number,
+                    fields[0].0,
+                    format!("corridor {} is not in {}", corridor_id, corridors_path),
+                ));
+            }
+        };
+        segments.insert((traj_id, segment_id), corridor);
+    }
+
+    Ok(RunSnapshot {
+        corridors,
+        segments,
+        partition,
+    })
+}
+
+// Format: {id}\t{weight}\tLINESTRING({x1} {y1}, {x2} {y2}), after a header line; an undirected
+// run has the weight in each direction before the LINESTRING, not needed here
+fn read_corridor_list(path: &str) -> Result<Vec<CorridorLine>, IoError> {
+    let content: String = fs::read_to_string(path).map_err(|err| IoError::read(path, err))?;
+    let mut corridors: Vec<CorridorLine> = Vec::new();
+
+    for (index, line) in content.lines().enumerate().skip(1) {
+        let line_number: usize = index + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<(usize, &str)> = tab_fields(line);
+        if fields.len() != 3 && fields.len() != 5 {
+            return Err(IoError::parse(
+                path,
+                line_number,
+                1,
+                format!(
+                    "expected 3 or 5 tab-separated fields, found {}",
+                    fields.len()
+                ),
+            ));
+        }
+        let parse_err = |(column, field): (usize, &str), name: &str| {
+            IoError::parse(
+                path,
+                line_number,
+                column,
+                format!("failed to parse {}: {:?}", name, field),
+            )
+        };
+
+        let id: usize = fields[0]
+            .1
+            .parse()
+            .map_err(|_| parse_err(fields[0], "corridor id"))?;
+        let weight: u32 = fields[1]
+            .1
+            .parse()
+            .map_err(|_| parse_err(fields[1], "weight"))?;
+        let coordinates: (usize, &str) = fields[fields.len() - 1];
+        let (start, end) =
+            parse_wkt_line(coordinates.1).ok_or_else(|| parse_err(coordinates, "LINESTRING"))?;
+
+        corridors.push(CorridorLine {
+            id,
+            weight,
+            start,
+            end,
+        });
+    }
+
+    Ok(corridors)
+}
+
+// Fields of a tab-separated line with their 1-based column
+fn tab_fields(line: &str) -> Vec<(usize, &str)> {
+    let mut column: usize = 1;
+    line.trim_end_matches('\r')
+        .split('\t')
+        .map(|field| {
+            let start: usize = column;
+            column += field.chars().count() + 1;
+            (start, field.trim())
+        })
+        .collect()
+}
+
+// "LINESTRING(x1 y1, x2 y2)"; more vertices are accepted, the first and last are kept
+fn parse_wkt_line(wkt: &str) -> Option<(Point, Point)> {
+    let inner: &str = wkt
+        .trim()
+        .strip_prefix("LINESTRING")?
+        .trim()
+        .strip_prefix('(')?
+        .strip_suffix(')')?;
+
+    let points: Vec<Point> = inner
+        .split(',')
+        .map(|pair| {
+            let mut coords = pair.split_whitespace().map(|c| c.parse::<f64>());
+            match (coords.next(), coords.next(), coords.next()) {
+                (Some(Ok(x)), Some(Ok(y)), None) => Some(Point { x, y }),
+                _ => None,
+            }
+        })
+        .collect::<Option<Vec<Point>>>()?;
+
+    if points.len() < 2 {
+        return None;
+    }
+    Some((points[0], points[points.len() - 1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // Writes the two files of a run in a directory of its own
+    fn write_run(name: &str, segments: &str, corridors: &str) -> (String, String) {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("run_reader_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let segments_path: PathBuf = dir.join("trips.segmentlist_new.txt");
+        let corridors_path: PathBuf = dir.join("trips.corridorlist.txt");
+        fs::write(&segments_path, segments).unwrap();
+        fs::write(&corridors_path, corridors).unwrap();
+        (
+            segments_path.to_string_lossy().into_owned(),
+            corridors_path.to_string_lossy().into_owned(),
+        )
+    }
+
+    const CORRIDORS: &str = "id\tweight\tcoordinates\n\
+                             0\t4\tLINESTRING(0 0, 5 5, 10 0)\n\
+                             1\t2\tLINESTRING(1.5 -2, 3 4)\n";
+
+    #[test]
+    fn corridor_list_is_found_beside_a_new_segment_list() {
+        assert_eq!(
+            corridor_list_beside("out/trips.segmentlist_new.txt").as_deref(),
+            Some("out/trips.corridorlist.txt")
+        );
+        assert_eq!(corridor_list_beside("out/trips.segmentlist.txt"), None);
+    }
+
+    #[test]
+    fn wkt_line_keeps_its_first_and_last_vertex() {
+        let (start, end) = parse_wkt_line("LINESTRING (0 1, 2 3, 4.5 -6)").unwrap();
+        assert_eq!((start.x, start.y, end.x, end.y), (0.0, 1.0, 4.5, -6.0));
+
+        assert!(parse_wkt_line("LINESTRING(0 1)").is_none());
+        assert!(parse_wkt_line("LINESTRING(0 1 2, 3 4)").is_none());
+        assert!(parse_wkt_line("POINT(0 1)").is_none());
+    }
+
+    #[test]
+    fn tab_fields_report_their_column() {
+        assert_eq!(
+            tab_fields("ab\t c \td\r"),
+            vec![(1, "ab"), (4, "c"), (8, "d")]
+        );
+    }
+
+    #[test]
+    fn run_outputs_are_read_back() {
+        let segments: &str = "corridor_id\ttraj_id\tsegment_id\tweight\tangle\tcoordinates\n\
+                              0\t3\t0\t1\t45\tLINESTRING(0 0, 1 1)\n\
+                              1\t3\t1\t1\t45\tLINESTRING(1 1, 2 2)\n\
+                              \n\
+                              -1\t8\t0\t2\t90\tLINESTRING(5 5, 5 6)\n";
+        let (segments_path, corridors_path) = write_run("valid", segments, CORRIDORS);
+        let run: RunSnapshot = read_run_outputs(&segments_path, &corridors_path).unwrap();
+
+        assert_eq!(run.corridors.len(), 2);
+        assert_eq!((run.corridors[1].id, run.corridors[1].weight), (1, 2));
+        assert_eq!(
+            (run.corridors[0].end.x, run.corridors[0].end.y),
+            (10.0, 0.0)
+        );
+        assert_eq!(run.segments.len(), 3);
+        assert_eq!(run.segments[&(3, 1)], Some(1));
+        assert_eq!(run.segments[&(8, 0)], None);
+        assert_eq!(run.partition, None);
+    }
+
+    #[test]
+    fn partition_line_is_read_before_the_header() {
+        let segments: String = format!(
+            "{}fixed segment_size=250\n\
+             corridor_id\ttraj_id\tsegment_id\tweight\tangle\tcoordinates\n\
+             0\t3\t0\t1\t45\tLINESTRING(0 0, 1 1)\n",
+            PARTITION_LINE_PREFIX
+        );
+        let (segments_path, corridors_path) = write_run("partition", &segments, CORRIDORS);
+        let run: RunSnapshot = read_run_outputs(&segments_path, &corridors_path).unwrap();
+
+        assert_eq!(run.partition.as_deref(), Some("fixed segment_size=250"));
+        assert_eq!(run.segments.len(), 1);
+        assert_eq!(run.segments[&(3, 0)], Some(0));
+    }
+
+    #[test]
+    fn old_segment_list_is_refused() {
+        let segments: String = format!(
+            "{}\n0\t1\t45\t0\tLINESTRING(0 0, 1 1)\n",
+            OLD_SEGMENT_HEADER
+        );
+        let (segments_path, corridors_path) = write_run("old", &segments, CORRIDORS);
+        let err: String = read_run_outputs(&segments_path, &corridors_path)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(":1:1: old segment list format"), "{}", err);
+    }
+
+    #[test]
+    fn segment_of_an_unknown_corridor_is_an_error() {
+        let segments: &str = "header\n7\t3\t0\t1\t45\tLINESTRING(0 0, 1 1)\n";
+        let (segments_path, corridors_path) = write_run("unknown", segments, CORRIDORS);
+        let err: String = read_run_outputs(&segments_path, &corridors_path)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(":2:1: corridor 7 is not in"), "{}", err);
+    }
+
+    #[test]
+    fn bad_corridor_line_is_located() {
+        let corridors: &str = "id\tweight\tcoordinates\n0\tmany\tLINESTRING(0 0, 1 1)\n";
+        let (segments_path, corridors_path) = write_run("weight", "header\n", corridors);
+        let err: String = read_run_outputs(&segments_path, &corridors_path)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains(":2:3: failed to parse weight: \"many\""),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,354 @@
+// shapefile_writer.rs - Writer of corridors and segments as ESRI Shapefiles
+//
+// Each layer is a PolyLine shapefile: .shp (geometry), .shx (record index) and .dbf
+// (dBase III attributes), written by hand from the ESRI whitepaper (July 1998).
+// dBase field names are limited to 10 characters, hence corr_id / traj_id / seg_id
+// for the corridor_id / trajectory_id / segment_id columns of the text outputs, and
+// fwd_weight / bwd_weight for the forward_weight / backward_weight of an undirected run.
+// A .prj is written only for longitude/latitude input, projected back to WGS 84 on output;
+// other inputs carry no coordinate reference system.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::clustering::geometry::point::Point;
+use crate::clustering::geometry::projection::Projection;
+use crate::clustering::storage::clustered_trajectories::ClusteredTrajectories;
+use crate::io::args::TraclusArgs;
+use crate::io::io_error::IoError;
+use crate::io::output_writer::{build_output_filename, output_point};
+
+const SHP_FILE_CODE: i32 = 9994;
+const SHP_VERSION: i32 = 1000;
+const SHP_POLYLINE: i32 = 3;
+const SHP_HEADER_BYTES: usize = 100;
+// Shape type, box, NumParts, NumPoints, one part index, two points
+const POLYLINE_CONTENT_BYTES: usize = 4 + 32 + 4 + 4 + 4 + 2 * 16;
+const RECORD_HEADER_BYTES: usize = 8;
+const WGS84_PRJ: &str = "GEOGCS[\"GCS_WGS_1984\",DATUM[\"D_WGS_1984\",SPHEROID[\"WGS_1984\",6378137.0,298.257223563]],PRIMEM[\"Greenwich\",0.0],UNIT[\"Degree\",0.0174532925199433]]";
+
+struct DbfField {
+    name: &'static str,
+    length: u8,
+    decimals: u8,
+}
+
+// One two-vertex PolyLine and its attribute values, in the order of the DbfFields
+struct ShapeRecord {
+    start: Point,
+    end: Point,
+    attributes: Vec<f64>,
+}
+
+const CORRIDOR_FIELDS: [DbfField; 2] = [
+    DbfField {
+        name: "id",
+        length: 10,
+        decimals: 0,
+    },
+    DbfField {
+        name: "weight",
+        length: 10,
+        decimals: 0,
+    },
+];
+
+const UNDIRECTED_CORRIDOR_FIELDS: [DbfField; 4] = [
+    DbfField {
+        name: "id",
+        length: 10,
+        decimals: 0,
+    },
+    DbfField {
+        name: "weight",
+        length: 10,
+        decimals: 0,
+    },
+    DbfField {
+        name: "fwd_weight",
+        length: 10,
+        decimals: 0,
+    },
+    DbfField {
+        name: "bwd_weight",
+        length: 10,
+        decimals: 0,
+    },
+];
+
+const SEGMENT_FIELDS: [DbfField; 5] = [
+    DbfField {
+        name: "corr_id",
+        length: 10,
+        decimals: 0,
+    },
+    DbfField {
+        name: "traj_id",
+        length: 10,
+        decimals: 0,
+    },
+    DbfField {
+        name: "seg_id",
+        length: 10,
+        decimals: 0,
+    },
+    DbfField {
+        name: "weight",
+        length: 10,
+        decimals: 0,
+    },
+    DbfField {
+        name: "angle",
+        length: 20,
+        decimals: 10,
+    },
+];
+
+// ─────────────────────────────────────────────
+// Entry points
+// ─────────────────────────────────────────────
+
+// Generate the corridor output as a shapefile (.shp, .shx, .dbf)
+pub fn generate_corridor_shapefile(
+    args: &TraclusArgs,
+    clust_storage: &ClusteredTrajectories,
+    projection: Option<&Projection>,
+) -> Result<String, IoError> {
+    let output_filename: String = build_output_filename(args, "corridorlist", "shp");
+
+    let records: Vec<ShapeRecord> = clust_storage
+        .corridors
+        .iter()
+        .map(|corridor| {
+            let mut attributes: Vec<f64> = vec![corridor.id as f64, corridor.weight as f64];
+            if args.undirected {
+                attributes.push(corridor.forward_weight as f64);
+                attributes.push(corridor.backward_weight as f64);
+            }
+            ShapeRecord {
+                start: output_point(projection, &corridor.start),
+                end: output_point(projection, &corridor.end),
+                attributes,
+            }
+        })
+        .collect();
+    let fields: &[DbfField] = if args.undirected {
+        &UNDIRECTED_CORRIDOR_FIELDS
+    } else {
+        &CORRIDOR_FIELDS
+    };
+    write_shapefile(&output_filename, fields, &records, projection)?;
+
+    println!("Corridor output written to: {}", output_filename);
+    Ok(output_filename)
+}
+
+// Generate the segment output as a shapefile, columns of SegmentOutputFormat::NewTraclus
+pub fn generate_segment_shapefile(
+    args: &TraclusArgs,
+    clust_storage: &ClusteredTrajectories,
+    projection: Option<&Projection>,
+) -> Result<String, IoError> {
+    let output_filename: String = build_output_filename(args, "segmentlist", "shp");
+
+    let records: Vec<ShapeRecord> = clust_storage
+        .get_all_cluster_members_iter()
+        .map(|(corridor_id, cluster_member)| ShapeRecord {
+            start: output_point(projection, &cluster_member.start),
+            end: output_point(projection, &cluster_member.end_point()),
+            attributes: vec![
+                corridor_id as f64,
+                cluster_member.traj_id as f64,
+                cluster_member.segment_id as f64,
+                cluster_member.weight as f64,
+                cluster_member.angle(),
+            ],
+        })
+        .collect();
+    write_shapefile(&output_filename, &SEGMENT_FIELDS, &records, projection)?;
+
+    println!("Segment output written to: {}", output_filename);
+    Ok(output_filename)
+}
+
+// Writes the .shp and its .shx and .dbf siblings, and the .prj of projected-back output;
+// errors name the file that failed
+fn write_shapefile(
+    shp_filename: &str,
+    fields: &[DbfField],
+    records: &[ShapeRecord],
+    projection: Option<&Projection>,
+) -> Result<(), IoError> {
+    let shx_path: PathBuf = Path::new(shp_filename).with_extension("shx");
+    let dbf_path: PathBuf = Path::new(shp_filename).with_extension("dbf");
+
+    write_shp(shp_filename, records).map_err(|err| IoError::write(shp_filename, err))?;
+    write_shx(&shx_path, records).map_err(|err| IoError::write(&shx_path, err))?;
+    write_dbf(&dbf_path, fields, records).map_err(|err| IoError::write(&dbf_path, err))?;
+
+    let prj_path: PathBuf = Path::new(shp_filename).with_extension("prj");
+    if projection.is_some() {
+        fs::write(&prj_path, WGS84_PRJ).map_err(|err| IoError::write(&prj_path, err))?;
+    } else if prj_path.exists() {
+        // Left by a longitude/latitude run of the same parameters, it would now be wrong
+        fs::remove_file(&prj_path).map_err(|err| IoError::write(&prj_path, err))?;
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────
+// .shp and .shx
+// ─────────────────────────────────────────────
+
+fn write_shp(path: &str, records: &[ShapeRecord]) -> io::Result<()> {
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(path)?);
+    let record_bytes: usize = RECORD_HEADER_BYTES + POLYLINE_CONTENT_BYTES;
+    write_main_header(
+        &mut writer,
+        records,
+        SHP_HEADER_BYTES + records.len() * record_bytes,
+    )?;
+
+    for (index, record) in records.iter().enumerate() {
+        writer.write_all(&(index as i32 + 1).to_be_bytes())?;
+        writer.write_all(&((POLYLINE_CONTENT_BYTES / 2) as i32).to_be_bytes())?;
+
+        writer.write_all(&SHP_POLYLINE.to_le_bytes())?;
+        write_box(&mut writer, &bounding_box([(&record.start, &record.end)]))?;
+        writer.write_all(&1i32.to_le_bytes())?; // NumParts
+        writer.write_all(&2i32.to_le_bytes())?; // NumPoints
+        writer.write_all(&0i32.to_le_bytes())?; // Parts[0]
+        for point in [&record.start, &record.end] {
+            writer.write_all(&point.x.to_le_bytes())?;
+            writer.write_all(&point.y.to_le_bytes())?;
+        }
+    }
+
+    writer.flush()
+}
+
+fn write_shx(path: &Path, records: &[ShapeRecord]) -> io::Result<()> {
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(path)?);
+    write_main_header(&mut writer, records, SHP_HEADER_BYTES + records.len() * 8)?;
+
+    let record_bytes: usize = RECORD_HEADER_BYTES + POLYLINE_CONTENT_BYTES;
+    for index in 0..records.len() {
+        let offset: usize = SHP_HEADER_BYTES + index * record_bytes;
+        writer.write_all(&((offset / 2) as i32).to_be_bytes())?;
+        writer.write_all(&((POLYLINE_CONTENT_BYTES / 2) as i32).to_be_bytes())?;
+    }
+
+    writer.flush()
+}
+
+// Shared by .shp and .shx: lengths are in 16-bit words, big-endian; the rest little-endian
+fn write_main_header(
+    writer: &mut BufWriter<File>,
+    records: &[ShapeRecord],
+    file_bytes: usize,
+) -> io::Result<()> {
+    writer.write_all(&SHP_FILE_CODE.to_be_bytes())?;
+    writer.write_all(&[0u8; 20])?;
+    writer.write_all(&((file_bytes / 2) as i32).to_be_bytes())?;
+    writer.write_all(&SHP_VERSION.to_le_bytes())?;
+    writer.write_all(&SHP_POLYLINE.to_le_bytes())?;
+    write_box(
+        writer,
+        &bounding_box(records.iter().map(|record| (&record.start, &record.end))),
+    )?;
+    writer.write_all(&[0u8; 32]) // Z and M ranges, unused
+}
+
+// [xmin, ymin, xmax, ymax]; all zeros when there is no shape
+fn bounding_box<'a>(lines: impl IntoIterator<Item = (&'a Point, &'a Point)>) -> [f64; 4] {
+    let mut bbox: [f64; 4] = [
+        f64::INFINITY,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::NEG_INFINITY,
+    ];
+    for (start, end) in lines {
+        for point in [start, end] {
+            bbox[0] = bbox[0].min(point.x);
+            bbox[1] = bbox[1].min(point.y);
+            bbox[2] = bbox[2].max(point.x);
+            bbox[3] = bbox[3].max(point.y);
+        }
+    }
+    if bbox[0] > bbox[2] {
+        return [0.0; 4];
+    }
+    bbox
+}
+
+fn write_box(writer: &mut BufWriter<File>, bbox: &[f64; 4]) -> io::Result<()> {
+    for value in bbox {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────
+// .dbf (dBase III)
+// ─────────────────────────────────────────────
+
+fn write_dbf(path: &Path, fields: &[DbfField], records: &[ShapeRecord]) -> io::Result<()> {
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(path)?);
+    let header_bytes: u16 = (32 + 32 * fields.len() + 1) as u16;
+    let record_bytes: u16 = 1 + fields.iter().map(|f| f.length as u16).sum::<u16>();
+    let (year, month, day) = today();
+
+    writer.write_all(&[0x03, (year - 1900) as u8, month, day])?;
+    writer.write_all(&(records.len() as u32).to_le_bytes())?;
+    writer.write_all(&header_bytes.to_le_bytes())?;
+    writer.write_all(&record_bytes.to_le_bytes())?;
+    writer.write_all(&[0u8; 20])?;
+
+    for field in fields {
+        let mut name: [u8; 11] = [0; 11];
+        name[..field.name.len()].copy_from_slice(field.name.as_bytes());
+        writer.write_all(&name)?;
+        writer.write_all(b"N")?;
+        writer.write_all(&[0u8; 4])?;
+        writer.write_all(&[field.length, field.decimals])?;
+        writer.write_all(&[0u8; 14])?;
+    }
+    writer.write_all(&[0x0D])?;
+
+    for record in records {
+        writer.write_all(b" ")?; // not deleted
+        for (field, value) in fields.iter().zip(&record.attributes) {
+            let text: String = format!(
+                "{:>width$.decimals$}",
+                value,
+                width = field.length as usize,
+                decimals = field.decimals as usize
+            );
+            writer.write_all(text.as_bytes())?;
+        }
+    }
+    writer.write_all(&[0x1A])?;
+
+    writer.flush()
+}
+
+// Date of last update (UTC) for the .dbf header, from days since the Unix epoch
+fn today() -> (i64, u8, u8) {
+    let secs: u64 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let days: i64 = (secs / 86_400) as i64 + 719_468;
+    let era: i64 = days.div_euclid(146_097);
+    let day_of_era: i64 = days - era * 146_097;
+    let year_of_era: i64 =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year: i64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp: i64 = (5 * day_of_year + 2) / 153;
+    let day: i64 = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month: i64 = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year: i64 = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month as u8, day as u8)
+}
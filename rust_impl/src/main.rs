@@ -1,14 +1,17 @@
 use crate::clustering::main_traclusdl::MainTraclusDL;
+use crate::commands::run_command;
 use crate::gui::app_events::AppEvent;
 use crate::gui::traclusdl_app::start_gui;
-use crate::io::args::{InterfaceMode, TraclusArgs};
+use crate::io::args::{Cli, InterfaceMode, TraclusArgs};
 use crate::io::logger::Logger;
 
 use clap::Parser;
+use std::process::ExitCode;
 use std::sync::mpsc::Receiver;
-use std::thread::available_parallelism;
+use std::thread::{JoinHandle, available_parallelism};
 
 mod clustering;
+mod commands;
 mod gui;
 mod io;
 mod utils;
@@ -41,10 +44,19 @@ fn get_number_of_cpus(args: &TraclusArgs) -> usize {
 // Entry point
 // ─────────────────────────────────────────────
 
-fn main() -> std::io::Result<()> {
-    let traclus_args: TraclusArgs = TraclusArgs::parse();
-    print!(
-        "Starting TraclusDL Rust implementation with {:?}...\n",
+/// Exit codes: 0 success, 2 invalid arguments (clap), 3 unreadable input file,
+/// 4 malformed input line or GeoJSON feature, or a line refused by the sanitation,
+/// 5 output file not written, 6 clustering cancelled.
+/// Subcommands use the same codes.
+fn main() -> ExitCode {
+    let cli: Cli = Cli::parse();
+    if let Some(command) = cli.command {
+        return run_command(command);
+    }
+
+    let traclus_args: TraclusArgs = cli.run;
+    println!(
+        "Starting TraclusDL Rust implementation with {:?}...",
         traclus_args
     );
 
@@ -52,13 +64,13 @@ fn main() -> std::io::Result<()> {
     let mut main_traclusdl: MainTraclusDL = MainTraclusDL::new(num_computation_threads);
 
     // Subscribe all subscriber
-    match traclus_args.interface_mode {
+    let logger_handle: Option<JoinHandle<()>> = match traclus_args.interface_mode {
         InterfaceMode::Logger | InterfaceMode::GuiAndLogger => {
             let logger_rx: Receiver<AppEvent> = main_traclusdl.event.subscribe();
-            Logger::start(logger_rx);
+            Some(Logger::start(logger_rx))
         }
-        _ => {}
-    }
+        _ => None,
+    };
 
     // Route to the appropriate front-end
     match traclus_args.interface_mode {
@@ -66,9 +78,20 @@ fn main() -> std::io::Result<()> {
             start_gui(traclus_args, main_traclusdl);
         }
         InterfaceMode::Logger | InterfaceMode::Performance => {
-            main_traclusdl.run_full_traclus(traclus_args);
+            let result = main_traclusdl.run_full_traclus(traclus_args);
+
+            // Dropping the event senders ends the logger once it printed everything
+            drop(main_traclusdl);
+            if let Some(handle) = logger_handle {
+                let _ = handle.join();
+            }
+
+            if let Err(err) = result {
+                eprintln!("Error: {}", err);
+                return ExitCode::from(err.exit_code());
+            }
         }
     }
 
-    Ok(())
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,84 @@
+// cancellation_token.rs - Cooperative cancellation shared between the GUI and the computation
+//
+// The GUI thread sets the flag (Stop button) without touching the MainTraclusDL mutex.
+// The clustering code polls it at regular points and unwinds with Err(Cancelled),
+// so a cancelled run never replaces the results of the previous one.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Returned by any computation step that stopped because its token was cancelled.
+#[derive(Debug, Clone, Copy)]
+pub struct Cancelled;
+
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the running computation to stop at its next check.
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Clears a previous cancellation before a new task starts.
+    pub fn reset(&self) {
+        self.flag.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    /// Err(Cancelled) once cancel() has been called, meant to be used with `?`.
+    #[inline]
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_token_is_not_cancelled() {
+        let token: CancellationToken = CancellationToken::new();
+        assert!(!token.is_cancelled());
+        assert!(token.check().is_ok());
+    }
+
+    #[test]
+    fn cancel_is_seen_by_every_clone() {
+        let token: CancellationToken = CancellationToken::new();
+        let worker: CancellationToken = token.clone();
+        token.cancel();
+        assert!(worker.is_cancelled());
+        assert!(worker.check().is_err());
+    }
+
+    #[test]
+    fn reset_clears_a_cancellation() {
+        let token: CancellationToken = CancellationToken::new();
+        token.cancel();
+        token.reset();
+        assert!(token.check().is_ok());
+    }
+
+    #[test]
+    fn cancel_from_another_thread() {
+        let token: CancellationToken = CancellationToken::new();
+        let remote: CancellationToken = token.clone();
+        std::thread::spawn(move || remote.cancel()).join().unwrap();
+        assert!(token.is_cancelled());
+    }
+}
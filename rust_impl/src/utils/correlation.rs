@@ -0,0 +1,185 @@
+// correlation.rs - "% correlation" of the loaded data, shown in the file section and the logger
+//
+// Share of the trajectory weight that has at least one other trajectory within max_angle
+// and max_dist, whole legs compared. A trajectory without such a neighbour can only end up
+// outside any corridor, so the share bounds from above what a run can cluster.
+// Angles are compared modulo 180° when the storage is undirected.
+// Candidates come from the neighbouring angle buckets, as for the clustering itself, so
+// pairs further apart than one bucket are never counted even with a larger max_angle.
+
+use rayon::prelude::*;
+
+use crate::clustering::geometry::point::Point;
+use crate::clustering::geometry::trajectory::{Leg, Trajectory, angle_difference};
+use crate::clustering::storage::raw_trajectories::RawTrajectories;
+use crate::utils::cancellation_token::{CancellationToken, Cancelled};
+
+/// Percentage (0 to 100) of the weight of `raw_storage` with a neighbour within
+/// `max_angle` degrees and `max_dist`; 0 without any trajectory.
+/// Expects the spatial index to be built, otherwise every pair of nearby buckets is compared.
+/// Err(Cancelled) once `cancel_token` is cancelled, checked for every trajectory.
+pub fn correlation_percent(
+    raw_storage: &RawTrajectories,
+    max_dist: f64,
+    max_angle: f64,
+    cancel_token: &CancellationToken,
+) -> Result<f64, Cancelled> {
+    let (correlated, total): (u64, u64) = raw_storage
+        .trajectories
+        .par_iter()
+        .map(|traj| {
+            cancel_token.check()?;
+            let weight: u64 = traj.weight as u64;
+            if has_neighbour(raw_storage, traj, max_dist, max_angle) {
+                Ok((weight, weight))
+            } else {
+                Ok((0, weight))
+            }
+        })
+        .try_reduce(|| (0, 0), |a, b| Ok((a.0 + b.0, a.1 + b.1)))?;
+
+    if total == 0 {
+        Ok(0.0)
+    } else {
+        Ok(correlated as f64 / total as f64 * 100.0)
+    }
+}
+
+fn has_neighbour(
+    raw_storage: &RawTrajectories,
+    traj: &Trajectory,
+    max_dist: f64,
+    max_angle: f64,
+) -> bool {
+    traj.legs().iter().any(|leg| {
+        // Any point of a leg within max_dist is within this radius of its middle
+        let middle: Point = (leg.start + leg.end) * 0.5;
+        let radius: f64 = leg.get_spatial_length() / 2.0 + max_dist;
+
+        raw_storage
+            .nearby_angle_buckets(leg.angle)
+            .query(&middle, radius)
+            .into_iter()
+            .any(|(other_traj, other)| {
+                !std::ptr::eq(other_traj, traj)
+                    && angle_difference(leg.angle, other.angle, raw_storage.undirected) <= max_angle
+                    && line_distance(leg, other) <= max_dist
+            })
+    })
+}
+
+// Distance between the start→end lines: 0 when they cross, else the closest endpoint
+fn line_distance(a: &Leg, b: &Leg) -> f64 {
+    if lines_cross(&a.start, &a.end, &b.start, &b.end) {
+        return 0.0;
+    }
+    point_line_distance(&a.start, &b.start, &b.end)
+        .min(point_line_distance(&a.end, &b.start, &b.end))
+        .min(point_line_distance(&b.start, &a.start, &a.end))
+        .min(point_line_distance(&b.end, &a.start, &a.end))
+}
+
+fn point_line_distance(p: &Point, start: &Point, end: &Point) -> f64 {
+    let (dx, dy): (f64, f64) = (end.x - start.x, end.y - start.y);
+    let length_sq: f64 = dx * dx + dy * dy;
+    let t: f64 = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((p.x - start.x) * dx + (p.y - start.y) * dy) / length_sq).clamp(0.0, 1.0)
+    };
+    (p.x - (start.x + t * dx)).hypot(p.y - (start.y + t * dy))
+}
+
+// Proper crossing only; touching or collinear lines get their distance from the endpoints
+fn lines_cross(p1: &Point, p2: &Point, q1: &Point, q2: &Point) -> bool {
+    let cross =
+        |o: &Point, a: &Point, b: &Point| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+    let (d1, d2): (f64, f64) = (cross(q1, q2, p1), cross(q1, q2, p2));
+    let (d3, d4): (f64, f64) = (cross(p1, p2, q1), cross(p1, p2, q2));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::geometry::input_od_line::InputODLine;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    // weight, start, end
+    type Line = (u32, (f64, f64), (f64, f64));
+
+    fn storage_of(lines: &[Line]) -> RawTrajectories {
+        let mut raw_storage: RawTrajectories = RawTrajectories::new(30.0, false);
+        for (index, &(weight, start, end)) in lines.iter().enumerate() {
+            let input: InputODLine = InputODLine {
+                line_id: index + 1,
+                weight,
+                start: point(start.0, start.1),
+                end: point(end.0, end.1),
+                via: Vec::new(),
+            };
+            raw_storage.add_trajectory(Trajectory::new(input, 10.0));
+        }
+        raw_storage.build_spatial_index(50.0);
+        raw_storage
+    }
+
+    fn percent(raw_storage: &RawTrajectories, max_dist: f64, max_angle: f64) -> f64 {
+        correlation_percent(raw_storage, max_dist, max_angle, &CancellationToken::new()).unwrap()
+    }
+
+    #[test]
+    fn lone_lines_are_not_correlated() {
+        let raw_storage: RawTrajectories = storage_of(&[
+            (3, (0.0, 0.0), (100.0, 0.0)),
+            (1, (0.0, 20.0), (100.0, 20.0)),
+            (4, (0.0, 5000.0), (100.0, 5000.0)),
+        ]);
+        assert_eq!(percent(&raw_storage, 30.0, 10.0), 50.0);
+        assert_eq!(percent(&raw_storage, 10.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn neighbour_must_share_the_direction() {
+        let raw_storage: RawTrajectories = storage_of(&[
+            (1, (0.0, 0.0), (100.0, 0.0)),
+            (1, (0.0, 10.0), (100.0, 30.0)), // about 11° apart
+        ]);
+        assert_eq!(percent(&raw_storage, 50.0, 15.0), 100.0);
+        assert_eq!(percent(&raw_storage, 50.0, 5.0), 0.0);
+    }
+
+    #[test]
+    fn empty_storage_is_zero() {
+        assert_eq!(percent(&storage_of(&[]), 50.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn crossing_lines_are_at_distance_zero() {
+        let (p1, p2): (Point, Point) = (point(0.0, 0.0), point(10.0, 10.0));
+        assert!(lines_cross(&p1, &p2, &point(0.0, 10.0), &point(10.0, 0.0)));
+        // Touching at an endpoint is not a proper crossing
+        assert!(!lines_cross(
+            &p1,
+            &p2,
+            &point(10.0, 10.0),
+            &point(20.0, 0.0)
+        ));
+
+        assert_eq!(
+            point_line_distance(&point(5.0, 3.0), &point(0.0, 0.0), &point(10.0, 0.0)),
+            3.0
+        );
+        assert_eq!(
+            point_line_distance(&point(13.0, 4.0), &point(0.0, 0.0), &point(10.0, 0.0)),
+            5.0
+        );
+        assert_eq!(
+            point_line_distance(&point(3.0, 4.0), &point(0.0, 0.0), &point(0.0, 0.0)),
+            5.0
+        );
+    }
+}
@@ -4,11 +4,18 @@
 // calls try_run(...). If a task is already running, try_run returns false
 // immediately and the button stays disabled. Otherwise it spawns one std::thread
 // which may internally use the custom Rayon pool inside MainTraclusDL.
+//
+// The runner shares the CancellationToken of MainTraclusDL: cancel() only raises the
+// flag, the task itself stops at its next check and releases the mutex normally.
+// A task that panics is unwound with the mutex still held, so the mutex is not poisoned
+// and the next task runs; the GUI notices the missing end event of its clustering.
 
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
 use crate::clustering::main_traclusdl::MainTraclusDL;
+use crate::utils::cancellation_token::CancellationToken;
 
 // Shared bool: true while any task is executing.
 // Shared ownership between GUI thread and worker thread.
@@ -29,12 +36,15 @@ impl Drop for ReleaseOnDrop {
 
 pub struct GuiParallelRunner {
     is_running: RunningFlag,
+    cancel_token: CancellationToken,
 }
 
 impl GuiParallelRunner {
-    pub fn new() -> Self {
+    /// `cancel_token` must be the one checked by the tasks (MainTraclusDL::cancellation_token).
+    pub fn new(cancel_token: CancellationToken) -> Self {
         Self {
             is_running: Arc::new(Mutex::new(false)),
+            cancel_token,
         }
     }
 
@@ -53,12 +63,17 @@ impl GuiParallelRunner {
             return false;
         }
 
+        // A Stop pressed during a previous task must not cancel this one
+        self.cancel_token.reset();
         let flag = Arc::clone(&self.is_running);
 
         thread::spawn(move || {
             // Guard releases the flag when this thread scope exits, even on panic
             let _guard = ReleaseOnDrop(flag);
-            task(&mut main.lock().unwrap());
+            let mut main = main.lock().unwrap();
+            // The panic message is printed by the default hook; the lock is released after
+            // the unwinding, unpoisoned
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| task(&mut main)));
         });
 
         true
@@ -75,7 +90,41 @@ impl GuiParallelRunner {
         true
     }
 
+    /// Requests the running task to stop. No effect when idle.
+    pub fn cancel(&self) {
+        if self.is_running() {
+            self.cancel_token.cancel();
+        }
+    }
+
     pub fn is_running(&self) -> bool {
         *self.is_running.lock().unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_idle(runner: &GuiParallelRunner) {
+        while runner.is_running() {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn a_panicking_task_leaves_the_runner_usable() {
+        let main: Arc<Mutex<MainTraclusDL>> = Arc::new(Mutex::new(MainTraclusDL::new(1)));
+        let runner: GuiParallelRunner =
+            GuiParallelRunner::new(main.lock().unwrap().cancellation_token());
+
+        assert!(runner.try_run(Arc::clone(&main), |_| panic!("task failure")));
+        wait_idle(&runner);
+        assert!(!main.is_poisoned());
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        assert!(runner.try_run(Arc::clone(&main), move |_| tx.send(()).unwrap()));
+        wait_idle(&runner);
+        assert!(rx.try_recv().is_ok());
+    }
+}
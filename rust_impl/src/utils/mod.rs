@@ -1 +1,7 @@
+pub mod cancellation_token;
+pub mod correlation;
 pub mod gui_parallel_runner;
+pub mod progress_reporter;
+pub mod run_timings;
+pub mod seeded_rng;
+pub mod time_estimator;
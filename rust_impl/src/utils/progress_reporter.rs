@@ -0,0 +1,220 @@
+// progress_reporter.rs - Progress counters shared by the clustering threads
+//
+// Counters are atomics so the Rayon workers can report through a shared reference.
+// An event is only emitted every `step` items (about 1% of the work), and a disabled
+// reporter (no subscriber, e.g. InterfaceMode::Performance) returns before touching them.
+// The time estimator sits behind a mutex that is only locked when an event is emitted.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::clustering::objects::cluster::Cluster;
+use crate::gui::app_events::{AppEvent, EventSender};
+use crate::utils::time_estimator::TimeEstimator;
+
+// Number of progress events emitted per phase, at most
+const NUM_PROGRESS_STEPS: usize = 100;
+
+pub struct ProgressReporter {
+    sender: Option<EventSender>,
+
+    total_traj: usize,
+    traj_step: usize,
+    num_traj_done: AtomicUsize,
+    num_clusters: AtomicUsize,
+    num_members: AtomicUsize,
+
+    corridor_step: AtomicUsize,
+    num_corridors_done: AtomicUsize,
+
+    estimator: Mutex<TimeEstimator>,
+}
+
+impl ProgressReporter {
+    /// `total_traj` is the number of seed trajectory legs the clustering will go through.
+    pub fn new(sender: EventSender, total_traj: usize) -> Self {
+        Self {
+            sender: Some(sender),
+            total_traj,
+            traj_step: Self::step_for(total_traj),
+            num_traj_done: AtomicUsize::new(0),
+            num_clusters: AtomicUsize::new(0),
+            num_members: AtomicUsize::new(0),
+            corridor_step: AtomicUsize::new(1),
+            num_corridors_done: AtomicUsize::new(0),
+            estimator: Mutex::new(TimeEstimator::start()),
+        }
+    }
+
+    /// Reporter that counts and emits nothing.
+    pub fn disabled() -> Self {
+        Self {
+            sender: None,
+            total_traj: 0,
+            traj_step: 1,
+            num_traj_done: AtomicUsize::new(0),
+            num_clusters: AtomicUsize::new(0),
+            num_members: AtomicUsize::new(0),
+            corridor_step: AtomicUsize::new(1),
+            num_corridors_done: AtomicUsize::new(0),
+            estimator: Mutex::new(TimeEstimator::start()),
+        }
+    }
+
+    #[inline]
+    fn step_for(total: usize) -> usize {
+        (total / NUM_PROGRESS_STEPS).max(1)
+    }
+
+    /// Called once per seed trajectory leg with the clusters it produced, from any thread.
+    #[inline]
+    pub fn trajectory_done(&self, clusters: &[Cluster]) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        let members: usize = clusters.iter().map(|c| c.members.len()).sum();
+        let num_clusters: usize = self
+            .num_clusters
+            .fetch_add(clusters.len(), Ordering::Relaxed)
+            + clusters.len();
+        let num_members: usize = self.num_members.fetch_add(members, Ordering::Relaxed) + members;
+
+        let done: usize = self.num_traj_done.fetch_add(1, Ordering::Relaxed) + 1;
+        if done.is_multiple_of(self.traj_step) || done == self.total_traj {
+            sender.emit(AppEvent::ComputationClusteringProgress {
+                num_traj_done: done,
+                total_traj: self.total_traj,
+            });
+
+            let remaining: Duration = self.estimator.lock().unwrap().remaining_during_clustering(
+                done,
+                self.total_traj,
+                num_clusters,
+                num_members,
+            );
+            sender.emit(AppEvent::TimeEstimate {
+                remaining_secs: remaining.as_secs_f64(),
+            });
+        }
+    }
+
+    /// Called before the corridor finalisation with the number of clusters to pop.
+    pub fn start_finalization(&self, num_clusters: usize) {
+        self.corridor_step
+            .store(Self::step_for(num_clusters), Ordering::Relaxed);
+        self.num_corridors_done.store(0, Ordering::Relaxed);
+        self.estimator
+            .lock()
+            .unwrap()
+            .start_finalization(num_clusters);
+    }
+
+    /// Called once per corridor created.
+    #[inline]
+    pub fn corridor_done(&self, num_clusters_left: usize) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        let done: usize = self.num_corridors_done.fetch_add(1, Ordering::Relaxed) + 1;
+        let step: usize = self.corridor_step.load(Ordering::Relaxed);
+        if done.is_multiple_of(step) || num_clusters_left == 0 {
+            sender.emit(AppEvent::CorridorFinalizationProgress {
+                num_corridors_done: done,
+                num_clusters_left,
+            });
+
+            let remaining: Duration = self
+                .estimator
+                .lock()
+                .unwrap()
+                .remaining_during_finalization(num_clusters_left);
+            sender.emit(AppEvent::TimeEstimate {
+                remaining_secs: remaining.as_secs_f64(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::app_events::ComputationEvent;
+    use std::sync::mpsc::Receiver;
+
+    fn clustering_progress(events: &Receiver<AppEvent>) -> Vec<usize> {
+        events
+            .try_iter()
+            .filter_map(|event| match event {
+                AppEvent::ComputationClusteringProgress { num_traj_done, .. } => {
+                    Some(num_traj_done)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clustering_progress_every_percent_and_at_the_end() {
+        let mut event: ComputationEvent = ComputationEvent::new();
+        let events: Receiver<AppEvent> = event.subscribe();
+        let reporter: ProgressReporter = ProgressReporter::new(event.sender(), 250);
+
+        for _ in 0..250 {
+            reporter.trajectory_done(&[]);
+        }
+
+        let done: Vec<usize> = clustering_progress(&events);
+        assert_eq!(done.len(), 125);
+        assert_eq!(done.first(), Some(&2));
+        assert_eq!(done.last(), Some(&250));
+    }
+
+    #[test]
+    fn small_runs_report_every_trajectory() {
+        let mut event: ComputationEvent = ComputationEvent::new();
+        let events: Receiver<AppEvent> = event.subscribe();
+        let reporter: ProgressReporter = ProgressReporter::new(event.sender(), 3);
+
+        for _ in 0..3 {
+            reporter.trajectory_done(&[]);
+        }
+        assert_eq!(clustering_progress(&events), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn finalization_reports_the_last_corridor() {
+        let mut event: ComputationEvent = ComputationEvent::new();
+        let events: Receiver<AppEvent> = event.subscribe();
+        let reporter: ProgressReporter = ProgressReporter::new(event.sender(), 0);
+
+        reporter.start_finalization(1000);
+        for left in (0..1000).rev() {
+            reporter.corridor_done(left);
+        }
+
+        let left: Vec<usize> = events
+            .try_iter()
+            .filter_map(|event| match event {
+                AppEvent::CorridorFinalizationProgress {
+                    num_clusters_left, ..
+                } => Some(num_clusters_left),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(left.len(), 100);
+        assert_eq!(left.first(), Some(&990));
+        assert_eq!(left.last(), Some(&0));
+    }
+
+    #[test]
+    fn disabled_reporter_emits_nothing() {
+        let reporter: ProgressReporter = ProgressReporter::disabled();
+        reporter.trajectory_done(&[]);
+        reporter.start_finalization(5);
+        reporter.corridor_done(0);
+        assert_eq!(reporter.num_traj_done.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,22 @@
+// run_timings.rs - Wall-clock durations of the steps of a run
+//
+// Kept by MainTraclusDL for the last load and the last clustering, and written with
+// the results by the output writers that carry run metadata (GeoPackage).
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunTimings {
+    /// Reading the input file and building the trajectories
+    pub loading: Duration,
+    /// Spatial index and cluster discovery
+    pub clustering: Duration,
+    /// Corridor creation and the non-clustered segments
+    pub finalization: Duration,
+}
+
+impl RunTimings {
+    pub fn total(&self) -> Duration {
+        self.loading + self.clustering + self.finalization
+    }
+}
@@ -0,0 +1,338 @@
+// time_estimator.rs - Run time estimates shown behind the "Estimated time" label and in the logger
+//
+// Two estimates are produced:
+// - a pre-run estimate, from the segments, legs and bounding box of every angle bucket:
+//   the legs a query returns and the members of a cluster are taken as if the lines of the
+//   neighbouring buckets were spread evenly over their bounding box, and the counts are
+//   turned into time with the per-operation costs below, to tell minutes from hours before
+//   pressing Start;
+// - a live estimate, from the seeds processed per second during clustering plus a model
+//   of the finalisation (cluster count × average members), then from the measured rate
+//   of cluster removal once the finalisation has started.
+//
+// The NANOS_PER_* costs are rough orders of magnitude of a release build on one core.
+// Lines gathered in a few corridors are denser than the even spread assumes, so the
+// pre-run estimate runs low on them; the live estimate corrects it once the run started.
+
+use std::f64::consts::PI;
+use std::time::{Duration, Instant};
+
+use crate::clustering::algorithms::segment_distance::{self, SegmentDistance};
+use crate::clustering::geometry::point::Point;
+use crate::clustering::objects::cluster_member::ClusterMember;
+use crate::clustering::storage::raw_trajectories::RawTrajectories;
+use crate::io::args::{ExecutionMode, TraclusArgs};
+
+// Cost of one neighbourhood query of a seed or cluster member (grid lookup, candidate list)
+const NANOS_PER_QUERY: f64 = 670.0;
+// Cost of the angle and distance tests of one leg returned by a query
+const NANOS_PER_CANDIDATE: f64 = 170.0;
+// Cost of one cluster member during the finalisation (index lookups + cleaning)
+const NANOS_PER_FINALIZED_MEMBER: f64 = 100.0;
+
+// ─────────────────────────────────────────────
+// Pre-run estimate
+// ─────────────────────────────────────────────
+
+// What the estimate needs of an angle bucket
+#[derive(Clone, Copy)]
+struct BucketOccupancy {
+    segments: f64,
+    legs: f64,
+    length: f64, // sum of the leg lengths
+    min: Point,
+    max: Point,
+}
+
+impl BucketOccupancy {
+    fn empty() -> Self {
+        Self {
+            segments: 0.0,
+            legs: 0.0,
+            length: 0.0,
+            min: Point {
+                x: f64::INFINITY,
+                y: f64::INFINITY,
+            },
+            max: Point {
+                x: f64::NEG_INFINITY,
+                y: f64::NEG_INFINITY,
+            },
+        }
+    }
+
+    fn merge(&self, other: &BucketOccupancy) -> BucketOccupancy {
+        BucketOccupancy {
+            segments: self.segments + other.segments,
+            legs: self.legs + other.legs,
+            length: self.length + other.length,
+            min: Point {
+                x: self.min.x.min(other.min.x),
+                y: self.min.y.min(other.min.y),
+            },
+            max: Point {
+                x: self.max.x.max(other.max.x),
+                y: self.max.y.max(other.max.y),
+            },
+        }
+    }
+
+    // Expected legs within `radius` of a point of the box: those crossing a disc of that
+    // radius, for legs of the average length spread evenly over the box (Buffon's needle).
+    fn legs_within(&self, radius: f64) -> f64 {
+        if self.legs == 0.0 {
+            return 0.0;
+        }
+        if radius.is_infinite() {
+            return self.legs;
+        }
+        let avg_length: f64 = self.length / self.legs;
+        let reach: f64 = 2.0 * radius * avg_length + PI * radius * radius;
+        let area: f64 = ((self.max.x - self.min.x) * (self.max.y - self.min.y)).max(reach);
+        self.legs * reach / area
+    }
+}
+
+/// Rough duration of a full clustering run on `raw_storage` with `args`.
+///
+/// Every segment of a bucket seeds a cluster whose members are the legs of the bucket
+/// within max_dist; the seed and each member query the legs of the neighbouring buckets
+/// within the search radius. Reads each leg once and costs O(buckets) after that, so it
+/// needs neither the spatial index nor a cancellation token.
+pub fn estimate_pre_run(
+    raw_storage: &RawTrajectories,
+    args: &TraclusArgs,
+    num_threads: usize,
+) -> Duration {
+    let buckets: Vec<BucketOccupancy> = raw_storage
+        .traj_buckets
+        .iter()
+        .map(|bucket| {
+            raw_storage.bucket_legs(bucket).fold(
+                BucketOccupancy::empty(),
+                |occupancy, (traj, leg)| {
+                    occupancy.merge(&BucketOccupancy {
+                        segments: traj.leg_segments(leg).len() as f64,
+                        legs: 1.0,
+                        length: leg.get_spatial_length(),
+                        min: Point {
+                            x: leg.start.x.min(leg.end.x),
+                            y: leg.start.y.min(leg.end.y),
+                        },
+                        max: Point {
+                            x: leg.start.x.max(leg.end.x),
+                            y: leg.start.y.max(leg.end.y),
+                        },
+                    })
+                },
+            )
+        })
+        .collect();
+
+    // Search radius around a seed of full segment length
+    let metric: Box<dyn SegmentDistance> = segment_distance::from_options(&args.distance);
+    let seed: ClusterMember = ClusterMember::new(
+        0,
+        0,
+        1,
+        Point {
+            x: args.segment_size / 2.0,
+            y: 0.0,
+        },
+        Point { x: 0.0, y: 0.0 },
+    );
+    let radius: f64 = metric.search_radius(&seed, args.max_dist);
+
+    let (mut queries, mut candidates, mut members): (f64, f64, f64) = (0.0, 0.0, 0.0);
+    for (i, bucket) in buckets.iter().enumerate() {
+        if bucket.segments == 0.0 {
+            continue;
+        }
+        let mut nearby_indices: Vec<usize> =
+            raw_storage.nearby_bucket_indices(raw_storage.traj_buckets[i].angle_start);
+        nearby_indices.sort_unstable();
+        nearby_indices.dedup(); // with less than three buckets, the same one wraps around
+        let nearby: BucketOccupancy = nearby_indices
+            .into_iter()
+            .fold(BucketOccupancy::empty(), |occupancy, j| {
+                occupancy.merge(&buckets[j])
+            });
+
+        let cluster_members: f64 = bucket.legs_within(args.max_dist);
+        let bucket_queries: f64 = bucket.segments * (1.0 + cluster_members);
+        queries += bucket_queries;
+        candidates += bucket_queries * nearby.legs_within(radius);
+        members += bucket.segments * cluster_members;
+    }
+
+    let mut clustering_nanos: f64 = NANOS_PER_QUERY * queries + NANOS_PER_CANDIDATE * candidates;
+    if args.mode == ExecutionMode::ParallelRayon {
+        clustering_nanos /= num_threads.max(1) as f64;
+    }
+    let finalization_nanos: f64 = NANOS_PER_FINALIZED_MEMBER * members;
+
+    Duration::from_secs_f64((clustering_nanos + finalization_nanos) * 1e-9)
+}
+
+// ─────────────────────────────────────────────
+// Live estimate
+// ─────────────────────────────────────────────
+
+pub struct TimeEstimator {
+    clustering_start: Instant,
+    finalization_start: Option<(Instant, usize)>, // start time, clusters at start
+}
+
+impl TimeEstimator {
+    /// Starts the clock; call when the clustering begins.
+    pub fn start() -> Self {
+        Self {
+            clustering_start: Instant::now(),
+            finalization_start: None,
+        }
+    }
+
+    /// Remaining time while clustering: extrapolated seed rate plus the finalisation model,
+    /// fed with the clusters and members found so far, scaled to all the trajectories.
+    pub fn remaining_during_clustering(
+        &self,
+        num_traj_done: usize,
+        total_traj: usize,
+        num_clusters: usize,
+        num_members: usize,
+    ) -> Duration {
+        if num_traj_done == 0 {
+            return Duration::ZERO;
+        }
+
+        let elapsed: f64 = self.clustering_start.elapsed().as_secs_f64();
+        let remaining_share: f64 = (total_traj - num_traj_done) as f64 / num_traj_done as f64;
+        let clustering_secs: f64 = elapsed * remaining_share;
+
+        // Finalisation model: clusters expected at the end × their average size so far
+        let predicted_clusters: f64 =
+            num_clusters as f64 * total_traj as f64 / num_traj_done as f64;
+        let avg_members: f64 = if num_clusters == 0 {
+            0.0
+        } else {
+            num_members as f64 / num_clusters as f64
+        };
+        let finalization_secs: f64 =
+            predicted_clusters * avg_members * NANOS_PER_FINALIZED_MEMBER * 1e-9;
+
+        Duration::from_secs_f64(clustering_secs + finalization_secs)
+    }
+
+    pub fn start_finalization(&mut self, num_clusters: usize) {
+        self.finalization_start = Some((Instant::now(), num_clusters));
+    }
+
+    /// Remaining time while finalising: clusters left over the measured removal rate.
+    pub fn remaining_during_finalization(&self, num_clusters_left: usize) -> Duration {
+        let Some((start, initial_clusters)) = self.finalization_start else {
+            return Duration::ZERO;
+        };
+
+        let removed: usize = initial_clusters.saturating_sub(num_clusters_left);
+        if removed == 0 {
+            return Duration::ZERO;
+        }
+
+        let secs_per_cluster: f64 = start.elapsed().as_secs_f64() / removed as f64;
+        Duration::from_secs_f64(secs_per_cluster * num_clusters_left as f64)
+    }
+}
+
+/// "-- min --s" style text used by the GUI label and the logger.
+pub fn format_duration(duration: Duration) -> String {
+    let total_secs: u64 = duration.as_secs();
+    if total_secs == 0 {
+        "< 1s".to_string()
+    } else if total_secs >= 3600 {
+        format!(
+            "{} h {:02} min",
+            total_secs / 3600,
+            (total_secs % 3600) / 60
+        )
+    } else {
+        format!("{} min {:02}s", total_secs / 60, total_secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::geometry::input_od_line::InputODLine;
+    use crate::clustering::geometry::trajectory::Trajectory;
+
+    // `count` parallel lines of length 1000 eastwards, `spacing` apart
+    fn parallel_lines(count: usize, spacing: f64) -> RawTrajectories {
+        let mut raw_storage: RawTrajectories = RawTrajectories::new(30.0, false);
+        for index in 0..count {
+            let y: f64 = index as f64 * spacing;
+            let input: InputODLine = InputODLine {
+                line_id: index + 1,
+                weight: 1,
+                start: Point { x: 0.0, y },
+                end: Point { x: 1000.0, y },
+                via: Vec::new(),
+            };
+            raw_storage.add_trajectory(Trajectory::new(input, 100.0));
+        }
+        raw_storage
+    }
+
+    fn estimate(raw_storage: &RawTrajectories) -> Duration {
+        let args: TraclusArgs = TraclusArgs {
+            max_dist: 50.0,
+            segment_size: 100.0,
+            ..TraclusArgs::default()
+        };
+        estimate_pre_run(raw_storage, &args, 1)
+    }
+
+    #[test]
+    fn no_pre_run_estimate_without_lines() {
+        assert_eq!(estimate(&parallel_lines(0, 10.0)), Duration::ZERO);
+    }
+
+    #[test]
+    fn denser_lines_take_longer() {
+        let sparse: Duration = estimate(&parallel_lines(100, 1000.0));
+        let dense: Duration = estimate(&parallel_lines(100, 10.0));
+        assert!(sparse > Duration::ZERO);
+        assert!(dense > sparse * 10);
+        assert!(estimate(&parallel_lines(200, 10.0)) > dense);
+    }
+
+    #[test]
+    fn format_duration_by_magnitude() {
+        assert_eq!(format_duration(Duration::from_millis(400)), "< 1s");
+        assert_eq!(format_duration(Duration::from_secs(75)), "1 min 15s");
+        assert_eq!(
+            format_duration(Duration::from_secs(3600 + 5 * 60 + 59)),
+            "1 h 05 min"
+        );
+    }
+
+    #[test]
+    fn no_live_estimate_before_any_progress() {
+        let mut estimator: TimeEstimator = TimeEstimator::start();
+        assert_eq!(
+            estimator.remaining_during_clustering(0, 100, 0, 0),
+            Duration::ZERO
+        );
+        assert_eq!(estimator.remaining_during_finalization(10), Duration::ZERO);
+
+        estimator.start_finalization(10);
+        assert_eq!(estimator.remaining_during_finalization(10), Duration::ZERO);
+    }
+
+    #[test]
+    fn clustering_done_leaves_only_the_finalization() {
+        let estimator: TimeEstimator = TimeEstimator::start();
+        let remaining: Duration = estimator.remaining_during_clustering(100, 100, 1000, 10_000);
+        let finalization: f64 = 10_000.0 * NANOS_PER_FINALIZED_MEMBER * 1e-9;
+        assert!((remaining.as_secs_f64() - finalization).abs() < 1e-9);
+    }
+}